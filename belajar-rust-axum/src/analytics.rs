@@ -0,0 +1,387 @@
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+};
+use belajar_rust_database::{
+    model::{self as entity, ProductId},
+    repository::{ProductRepository, VisitorRepository},
+};
+use belajar_rust_redis::{
+    ranking::{Board, SalesRank, SalesRanking},
+    visitors::VisitorCounter,
+};
+use chrono::{DateTime, Days, NaiveDate, Utc};
+use log::{debug, info, warn};
+use tokio::sync::mpsc::{self, Receiver, Sender, error::TrySendError};
+
+use crate::{
+    error::{AppError, AppResult},
+    extract::{Json, Path, Query},
+    model::{
+        DailyVisitors, DailyVisitorsQuery, ProductRank, RankQuery, RankingQuery, VisitorCount,
+        VisitorsQuery,
+    },
+    rate_limit::client_ip,
+    state::AppState,
+};
+
+/*
+ANALYTICS
+- Middleware `count_visitors` mencatat setiap request ke HyperLogLog di Redis, lihat `belajar_rust_redis::visitors`
+- Pengunjung selalu dikenali dari client IP, baik di route public maupun route yang membutuhkan login,
+IP nya dibaca dengan `client_ip` yang sama seperti rate limit, sehingga `X-Forwarded-For` palsu tidak menambah hitungan pengunjung,
+sehingga orang yang sama tidak dihitung dua kali saat berpindah dari route public ke route yang membutuhkan login
+- Route dicatat dengan method dan path template nya, misal `GET /products/{id}`, bukan path aslinya,
+sehingga jumlah key tidak bertambah untuk setiap id yang berbeda, request yang tidak cocok dengan route manapun tidak dicatat
+- Pencatatan dikirim ke antrian dengan kapasitas terbatas, lalu dicatat ke Redis oleh satu worker di background task,
+sehingga request tidak menunggu Redis, dan Redis yang lambat tidak membuat task menumpuk tanpa batas
+- Jika antrian penuh, kunjungan tersebut dibuang, Redis yang error hanya dicatat di log
+- `GET /analytics/visitors` menghitung pengunjung unik per hari, minggu, atau bulan dari Redis
+- `GET /analytics/visitors/daily` membaca hitungan harian yang sudah disimpan di database,
+termasuk hari yang key Redis nya sudah kadaluarsa
+- Job `persist_visitors` menyimpan hitungan hari ini dan kemarin ke database secara berkala,
+kemarin tetap disimpan agar kunjungan di akhir hari tidak terlewat, menyimpan berulang kali aman
+karena database menyimpan hitungan yang terbesar
+
+SALES RANKING
+- `GET /analytics/rankings/{board}` menampilkan product terlaris di papan `daily`, `weekly`, atau `all_time`,
+lihat `belajar_rust_redis::ranking`
+- `GET /analytics/rankings/{board}/products/{id}` menampilkan peringkat dan jumlah terjual satu product,
+404 jika product tersebut belum terjual di papan itu
+- Query `date` memilih papan hari atau minggu yang lain, selama papan nya belum kadaluarsa
+*/
+
+fn visitor_id(request: &Request, trust_forwarded_for: bool) -> String {
+    format!("ip:{}", client_ip(request, trust_forwarded_for))
+}
+
+struct Visit {
+    visitor: String,
+    route: String,
+}
+
+/// Queues visits for one worker that records them in Redis.
+#[derive(Clone)]
+pub struct VisitorRecorder {
+    sender: Sender<Visit>,
+}
+
+impl VisitorRecorder {
+    fn new(capacity: usize) -> (Self, Receiver<Visit>) {
+        let (sender, receiver) = mpsc::channel(capacity);
+        (VisitorRecorder { sender }, receiver)
+    }
+
+    /// Creates the recorder and spawns its worker, which stops once every
+    /// recorder is dropped.
+    pub fn spawn(counter: VisitorCounter, capacity: usize) -> Self {
+        let (recorder, mut receiver) = Self::new(capacity);
+        tokio::spawn(async move {
+            while let Some(visit) = receiver.recv().await {
+                let today = Utc::now().date_naive();
+                if let Err(error) = counter.record(&visit.visitor, &visit.route, today).await {
+                    warn!("Failed to record visitor of {}: {}", visit.route, error);
+                }
+            }
+        });
+        recorder
+    }
+
+    /// Returns `false` when the visit is dropped because the queue is full.
+    fn record(&self, visitor: String, route: String) -> bool {
+        match self.sender.try_send(Visit { visitor, route }) {
+            Ok(()) => true,
+            Err(TrySendError::Full(visit)) => {
+                debug!("Visitor queue is full, dropped visit of {}", visit.route);
+                false
+            }
+            Err(TrySendError::Closed(_)) => false,
+        }
+    }
+}
+
+pub async fn count_visitors(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path = request.extensions().get::<MatchedPath>();
+    if let (Some(redis), Some(path)) = (&state.redis, path) {
+        let route = format!("{} {}", request.method(), path.as_str());
+        let visitor = visitor_id(&request, state.rate_limits.trust_forwarded_for);
+        redis.visits.record(visitor, route);
+    }
+    next.run(request).await
+}
+
+fn analytics_unavailable(message: &str) -> AppError {
+    AppError::new(
+        StatusCode::SERVICE_UNAVAILABLE,
+        "analytics_unavailable",
+        message,
+    )
+}
+
+pub async fn visitors(
+    State(counter): State<Option<VisitorCounter>>,
+    Query(query): Query<VisitorsQuery>,
+) -> AppResult<Json<VisitorCount>> {
+    let counter =
+        counter.ok_or_else(|| analytics_unavailable("visitor analytics is not configured"))?;
+    let date = query.date.unwrap_or_else(|| Utc::now().date_naive());
+    let (start, end) = query.period.range(date);
+    let visitors = counter
+        .count(query.period, date, query.route.as_deref())
+        .await
+        .map_err(|error| {
+            warn!("Failed to count visitors: {}", error);
+            analytics_unavailable("visitor analytics is unavailable")
+        })?;
+    Ok(Json(VisitorCount {
+        period: query.period,
+        start,
+        end,
+        route: query.route,
+        visitors,
+    }))
+}
+
+pub async fn daily_visitors(
+    State(repository): State<Arc<dyn VisitorRepository>>,
+    Query(query): Query<DailyVisitorsQuery>,
+) -> AppResult<Json<Vec<DailyVisitors>>> {
+    if query.from > query.to {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "invalid_query",
+            "from must not be after to",
+        ));
+    }
+    let route = query
+        .route
+        .as_deref()
+        .unwrap_or(entity::DailyVisitors::ALL_ROUTES);
+    let counts = repository.daily(route, query.from, query.to).await?;
+    Ok(Json(counts.into_iter().map(DailyVisitors::from).collect()))
+}
+
+fn ranking_at(date: Option<NaiveDate>) -> DateTime<Utc> {
+    date.and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|at| at.and_utc())
+        .unwrap_or_else(Utc::now)
+}
+
+fn ranking_error(error: redis::RedisError) -> AppError {
+    warn!("Failed to read sales ranking: {}", error);
+    analytics_unavailable("sales ranking is unavailable")
+}
+
+async fn product_rank_of(
+    products: &dyn ProductRepository,
+    rank: SalesRank,
+) -> AppResult<ProductRank> {
+    let product = products.find_by_id(rank.product_id).await?;
+    Ok(ProductRank {
+        rank: rank.rank,
+        product_id: rank.product_id.into(),
+        name: product.map(|product| product.name),
+        units_sold: rank.score,
+    })
+}
+
+pub async fn top_products(
+    State(ranking): State<Option<SalesRanking>>,
+    State(products): State<Arc<dyn ProductRepository>>,
+    Path(board): Path<Board>,
+    Query(query): Query<RankingQuery>,
+) -> AppResult<Json<Vec<ProductRank>>> {
+    let ranking =
+        ranking.ok_or_else(|| analytics_unavailable("sales ranking is not configured"))?;
+    let limit = query.limit.min(RankingQuery::MAX_LIMIT);
+    let top = ranking
+        .top(board, ranking_at(query.date), limit)
+        .await
+        .map_err(ranking_error)?;
+
+    let mut ranks = Vec::with_capacity(top.len());
+    for rank in top {
+        ranks.push(product_rank_of(products.as_ref(), rank).await?);
+    }
+    Ok(Json(ranks))
+}
+
+pub async fn product_rank(
+    State(ranking): State<Option<SalesRanking>>,
+    State(products): State<Arc<dyn ProductRepository>>,
+    Path((board, id)): Path<(Board, u64)>,
+    Query(query): Query<RankQuery>,
+) -> AppResult<Json<ProductRank>> {
+    let ranking =
+        ranking.ok_or_else(|| analytics_unavailable("sales ranking is not configured"))?;
+    let rank = ranking
+        .rank(board, ranking_at(query.date), ProductId(id))
+        .await
+        .map_err(ranking_error)?
+        .ok_or_else(AppError::not_found)?;
+    Ok(Json(product_rank_of(products.as_ref(), rank).await?))
+}
+
+async fn persist_day(
+    counter: &VisitorCounter,
+    repository: &dyn VisitorRepository,
+    date: NaiveDate,
+) -> anyhow::Result<usize> {
+    let counts = counter.daily_counts(date).await?;
+    repository.save_daily(&counts).await?;
+    Ok(counts.len())
+}
+
+/// Saves the counts of yesterday and today every `period`, runs until the
+/// application stops.
+pub async fn persist_visitors(
+    counter: VisitorCounter,
+    repository: Arc<dyn VisitorRepository>,
+    period: Duration,
+) {
+    let mut interval = tokio::time::interval(period);
+    loop {
+        interval.tick().await;
+        let today = Utc::now().date_naive();
+        for date in [today - Days::new(1), today] {
+            match persist_day(&counter, repository.as_ref(), date).await {
+                Ok(saved) => info!("Saved {} visitor counts of {}", saved, date),
+                Err(error) => warn!("Failed to save visitor counts of {}: {}", date, error),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use axum::{
+        body::Body,
+        extract::{ConnectInfo, Request},
+        http::{StatusCode, header},
+    };
+    use axum_test::TestServer;
+    use belajar_rust_database::{model as entity, testing::TestDatabase};
+    use chrono::NaiveDate;
+    use serde_json::json;
+
+    use super::{VisitorRecorder, visitor_id};
+    use crate::{
+        app,
+        auth::{Claims, seed_admin},
+        model::DailyVisitors,
+        state::AppState,
+    };
+
+    #[test]
+    fn test_visitor_id() {
+        let mut request = Request::builder().uri("/").body(Body::empty()).unwrap();
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(SocketAddr::from(([10, 0, 0, 1], 4000))));
+        assert_eq!("ip:10.0.0.1", visitor_id(&request, false));
+
+        request.extensions_mut().insert(Claims {
+            sub: 7,
+            email: "admin@example.com".to_string(),
+            jti: "jti".to_string(),
+            iat: 0,
+            exp: 0,
+        });
+        assert_eq!("ip:10.0.0.1", visitor_id(&request, false));
+
+        request
+            .headers_mut()
+            .insert("x-forwarded-for", "1.2.3.4, 192.168.1.1".parse().unwrap());
+        assert_eq!("ip:192.168.1.1", visitor_id(&request, true));
+    }
+
+    #[tokio::test]
+    async fn test_visitor_queue_is_bounded() {
+        let (recorder, mut receiver) = VisitorRecorder::new(2);
+        assert!(recorder.record("ip:10.0.0.1".to_string(), "GET /".to_string()));
+        assert!(recorder.record("ip:10.0.0.2".to_string(), "GET /".to_string()));
+        assert!(!recorder.record("ip:10.0.0.3".to_string(), "GET /".to_string()));
+
+        assert_eq!("ip:10.0.0.1", receiver.recv().await.unwrap().visitor);
+        assert!(recorder.record("ip:10.0.0.4".to_string(), "GET /".to_string()));
+        drop(receiver);
+        assert!(!recorder.record("ip:10.0.0.5".to_string(), "GET /".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_analytics() {
+        let database = TestDatabase::sqlite().await.unwrap();
+        let state = AppState::for_database(&database);
+        let date = |day| NaiveDate::from_ymd_opt(2025, 9, day).unwrap();
+        state
+            .visitors
+            .save_daily(&[
+                entity::DailyVisitors {
+                    date: date(7),
+                    route: entity::DailyVisitors::ALL_ROUTES.to_string(),
+                    visitors: 10,
+                },
+                entity::DailyVisitors {
+                    date: date(8),
+                    route: "GET /products".to_string(),
+                    visitors: 4,
+                },
+            ])
+            .await
+            .unwrap();
+        let admin = seed_admin(&*state.users, "Admin", "admin@example.com", "password123")
+            .await
+            .unwrap();
+        let (token, _) = state.auth.issue(&admin, None).await.unwrap();
+        let mut server = TestServer::new(app(state)).unwrap();
+        server.add_header(header::AUTHORIZATION, format!("Bearer {}", token));
+
+        let response = server
+            .get("/analytics/visitors/daily?from=2025-09-01&to=2025-09-30")
+            .await;
+        response.assert_status_ok();
+        let counts: Vec<DailyVisitors> = response.json();
+        assert_eq!(
+            vec![(date(7), 10)],
+            counts
+                .into_iter()
+                .map(|count| (count.date, count.visitors))
+                .collect::<Vec<_>>()
+        );
+        server
+            .get("/analytics/visitors/daily?from=2025-09-01&to=2025-09-30&route=GET%20/products")
+            .await
+            .assert_json(&json!([{ "date": "2025-09-08", "visitors": 4 }]));
+        server
+            .get("/analytics/visitors/daily?from=2025-09-30&to=2025-09-01")
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+
+        let response = server.get("/analytics/visitors?period=week").await;
+        response.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        response.assert_json_contains(&json!({ "code": "analytics_unavailable" }));
+
+        server
+            .get("/analytics/rankings/weekly")
+            .await
+            .assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        server
+            .get("/analytics/rankings/all_time/products/1")
+            .await
+            .assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        server
+            .get("/analytics/rankings/monthly")
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+    }
+}
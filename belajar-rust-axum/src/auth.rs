@@ -0,0 +1,549 @@
+use std::sync::Arc;
+
+use argon2::{
+    Argon2, PasswordHash, PasswordHasher, PasswordVerifier,
+    password_hash::{SaltString, rand_core::OsRng},
+};
+use axum::{
+    Extension,
+    extract::{Query, Request, State},
+    http::{HeaderMap, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use belajar_rust_database::repository::{NewUser, UserError, UserRepository};
+use belajar_rust_redis::session::{MemorySessionStore, Session, SessionStore};
+use chrono::{Duration, Utc};
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use log::error;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{AppError, AppResult},
+    extract::{Json, JsonOrForm, Path},
+    model::User,
+    state::AppState,
+};
+
+/*
+AUTHENTICATION
+- Login menghasilkan token JWT yang ditandatangani menggunakan secret aplikasi
+- Setiap token memiliki id unik (jti), yang juga menjadi id session di SessionStore
+- Token hanya diterima selama session nya masih ada, sehingga logout cukup menghapus session tersebut
+- Session disimpan di Redis jika dikonfigurasi, sehingga logout berlaku di semua instance aplikasi,
+jika tidak, session disimpan di memory
+- Endpoint yang dilindungi memakai middleware `require_auth`, yang membaca header `Authorization: Bearer <token>`
+- WebSocket dan EventSource di browser tidak bisa mengirim header, sehingga endpoint live memakai `require_auth_or_query_token`,
+yang juga menerima token dari query `?token=<token>`, token nya dicek ke SessionStore yang sama
+- Login menerima body JSON maupun form (`application/x-www-form-urlencoded`), user nya dibaca dari tabel `users`
+- Akun admin pertama dibuat saat aplikasi berjalan menggunakan `seed_admin`, dari `auth.admin_email` dan `auth.admin_password`
+- Jika token valid, Claims disimpan di extension request, sehingga bisa diambil handler menggunakan `Extension<Claims>`
+
+SESSIONS
+- `GET /auth/sessions` menampilkan semua session user yang sedang login, misal untuk halaman "perangkat yang login"
+- `DELETE /auth/sessions/{id}` membatalkan satu session, dan `DELETE /auth/sessions` membatalkan semua session user
+- Semua session user juga dibatalkan saat password nya diubah atau user nya dihapus
+*/
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: u64,
+    pub email: String,
+    pub jti: String,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+#[derive(Clone)]
+pub struct Auth {
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+    ttl: Duration,
+    sessions: Arc<dyn SessionStore>,
+}
+
+impl Auth {
+    /// Keeps sessions in memory, see [`Auth::with_sessions`].
+    pub fn new(secret: &[u8], ttl: Duration) -> Self {
+        Auth {
+            encoding: EncodingKey::from_secret(secret),
+            decoding: DecodingKey::from_secret(secret),
+            ttl,
+            sessions: Arc::new(MemorySessionStore::default()),
+        }
+    }
+
+    pub fn with_sessions(mut self, sessions: Arc<dyn SessionStore>) -> Self {
+        self.sessions = sessions;
+        self
+    }
+
+    /// Signs a token and stores its session until the token expires.
+    pub async fn issue(
+        &self,
+        user: &User,
+        user_agent: Option<&str>,
+    ) -> AppResult<(String, Claims)> {
+        let now = Utc::now();
+        let claims = Claims {
+            sub: user.id,
+            email: user.email.clone(),
+            jti: uuid::Uuid::new_v4().to_string(),
+            iat: now.timestamp(),
+            exp: (now + self.ttl).timestamp(),
+        };
+        let token = encode(&Header::default(), &claims, &self.encoding)
+            .map_err(|error| AppError::internal(error.to_string()))?;
+        self.sessions
+            .create(&Session {
+                id: claims.jti.clone(),
+                user_id: claims.sub,
+                created_at: claims.iat,
+                expires_at: claims.exp,
+                user_agent: user_agent.map(str::to_string),
+            })
+            .await
+            .map_err(AuthError::from)?;
+        Ok((token, claims))
+    }
+
+    pub async fn verify(&self, token: &str) -> Result<Claims, AuthError> {
+        let claims = decode::<Claims>(token, &self.decoding, &Validation::default())
+            .map_err(|_| AuthError::InvalidToken)?
+            .claims;
+        if !self.sessions.is_active(&claims.jti).await? {
+            return Err(AuthError::RevokedToken);
+        }
+        Ok(claims)
+    }
+
+    pub async fn revoke(&self, claims: &Claims) -> Result<(), AuthError> {
+        self.sessions.revoke(&claims.jti).await?;
+        Ok(())
+    }
+
+    /// Logs the user out everywhere, returns the number of sessions revoked.
+    pub async fn revoke_all(&self, user_id: u64) -> Result<usize, AuthError> {
+        Ok(self.sessions.revoke_all(user_id).await?)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum AuthError {
+    MissingToken,
+    InvalidToken,
+    RevokedToken,
+    InvalidCredentials,
+    SessionStore,
+}
+
+impl From<redis::RedisError> for AuthError {
+    fn from(error: redis::RedisError) -> Self {
+        error!("Session store error: {}", error);
+        AuthError::SessionStore
+    }
+}
+
+impl IntoResponse for AuthError {
+    fn into_response(self) -> Response {
+        AppError::from(self).into_response()
+    }
+}
+
+pub fn hash_password(password: &str) -> String {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .expect("argon2 hashing with default params never fails")
+        .to_string()
+}
+
+/// Creates the initial admin account, or returns it when the email is
+/// already registered.
+pub async fn seed_admin(
+    users: &dyn UserRepository,
+    name: &str,
+    email: &str,
+    password: &str,
+) -> Result<User, UserError> {
+    if let Some(user) = users.find_by_email(email).await? {
+        return Ok(user.into());
+    }
+    let user = NewUser {
+        name: name.to_string(),
+        email: email.to_string(),
+        password: hash_password(password),
+    };
+    let id = users.insert("system", &user).await?;
+    Ok(User {
+        id: id.into(),
+        name: user.name,
+        email: user.email,
+        password: user.password,
+    })
+}
+
+pub fn verify_password(password: &str, hash: &str) -> bool {
+    PasswordHash::new(hash)
+        .map(|hash| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &hash)
+                .is_ok()
+        })
+        .unwrap_or(false)
+}
+
+fn bearer_token(request: &Request) -> Option<&str> {
+    request
+        .headers()
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+}
+
+#[derive(Deserialize)]
+struct TokenQuery {
+    token: String,
+}
+
+fn query_token(request: &Request) -> Option<String> {
+    Query::<TokenQuery>::try_from_uri(request.uri())
+        .ok()
+        .map(|Query(query)| query.token)
+}
+
+async fn authenticate(
+    auth: &Auth,
+    token: Option<&str>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, AuthError> {
+    let claims = auth.verify(token.ok_or(AuthError::MissingToken)?).await?;
+    request.extensions_mut().insert(claims);
+    Ok(next.run(request).await)
+}
+
+pub async fn require_auth(
+    State(auth): State<Auth>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AuthError> {
+    let token = bearer_token(&request).map(str::to_string);
+    authenticate(&auth, token.as_deref(), request, next).await
+}
+
+/// Like [`require_auth`], but also accepts the token in `?token=`, for
+/// browser WebSocket and EventSource clients that cannot set headers.
+pub async fn require_auth_or_query_token(
+    State(auth): State<Auth>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AuthError> {
+    let token = bearer_token(&request)
+        .map(str::to_string)
+        .or_else(|| query_token(&request));
+    authenticate(&auth, token.as_deref(), request, next).await
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoginRequest {
+    pub email: String,
+    pub password: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoginResponse {
+    pub token: String,
+    pub token_type: String,
+    pub expires_at: i64,
+}
+
+pub async fn login(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    JsonOrForm(request): JsonOrForm<LoginRequest>,
+) -> AppResult<Json<LoginResponse>> {
+    let user = state
+        .users
+        .find_by_email(&request.email)
+        .await?
+        .filter(|user| verify_password(&request.password, &user.password))
+        .ok_or(AuthError::InvalidCredentials)?;
+    let user_agent = headers
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok());
+    let (token, claims) = state.auth.issue(&user.into(), user_agent).await?;
+
+    Ok(Json(LoginResponse {
+        token,
+        token_type: "Bearer".to_string(),
+        expires_at: claims.exp,
+    }))
+}
+
+pub async fn logout(
+    State(auth): State<Auth>,
+    Extension(claims): Extension<Claims>,
+) -> AppResult<StatusCode> {
+    auth.revoke(&claims).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionResponse {
+    pub id: String,
+    pub created_at: i64,
+    pub expires_at: i64,
+    pub user_agent: Option<String>,
+    /// True for the session of the token used in this request.
+    pub current: bool,
+}
+
+pub async fn list_sessions(
+    State(auth): State<Auth>,
+    Extension(claims): Extension<Claims>,
+) -> AppResult<Json<Vec<SessionResponse>>> {
+    let sessions = auth
+        .sessions
+        .list(claims.sub)
+        .await
+        .map_err(AuthError::from)?;
+    Ok(Json(
+        sessions
+            .into_iter()
+            .map(|session| SessionResponse {
+                current: session.id == claims.jti,
+                id: session.id,
+                created_at: session.created_at,
+                expires_at: session.expires_at,
+                user_agent: session.user_agent,
+            })
+            .collect(),
+    ))
+}
+
+pub async fn revoke_session(
+    State(auth): State<Auth>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> AppResult<StatusCode> {
+    // Only sessions of the current user can be revoked, others look like they do not exist.
+    let sessions = auth
+        .sessions
+        .list(claims.sub)
+        .await
+        .map_err(AuthError::from)?;
+    if !sessions.iter().any(|session| session.id == id) {
+        return Err(AppError::not_found());
+    }
+    auth.sessions.revoke(&id).await.map_err(AuthError::from)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn revoke_all_sessions(
+    State(auth): State<Auth>,
+    Extension(claims): Extension<Claims>,
+) -> AppResult<StatusCode> {
+    auth.revoke_all(claims.sub).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        Extension, Router,
+        http::{StatusCode, header},
+        middleware,
+        routing::get,
+    };
+    use axum_test::TestServer;
+    use belajar_rust_database::testing::TestDatabase;
+    use chrono::Duration;
+    use serde_json::json;
+
+    use super::{
+        Auth, AuthError, Claims, LoginResponse, SessionResponse, hash_password, require_auth,
+        seed_admin, verify_password,
+    };
+    use crate::{app, error::ErrorBody, model::User, state::AppState};
+
+    fn user() -> User {
+        User {
+            id: 1,
+            name: "Admin".to_string(),
+            email: "admin@example.com".to_string(),
+            password: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_hash_password() {
+        let hash = hash_password("password123");
+        assert_ne!("password123", hash);
+        assert!(verify_password("password123", &hash));
+        assert!(!verify_password("password124", &hash));
+        assert!(!verify_password("password123", "not-a-hash"));
+    }
+
+    #[tokio::test]
+    async fn test_require_auth() {
+        let auth = Auth::new(b"secret", Duration::minutes(5));
+        let app = Router::new()
+            .route(
+                "/me",
+                get(|Extension(claims): Extension<Claims>| async move { claims.email }),
+            )
+            .route_layer(middleware::from_fn_with_state(auth.clone(), require_auth));
+        let server = TestServer::new(app).unwrap();
+
+        let response = server.get("/me").await;
+        response.assert_status_unauthorized();
+        assert_eq!("Bearer", response.header("www-authenticate"));
+        let body: ErrorBody = response.json();
+        assert_eq!(401, body.status);
+        assert_eq!("missing_token", body.code);
+
+        server
+            .get("/me")
+            .authorization_bearer("not-a-jwt")
+            .await
+            .assert_status_unauthorized();
+
+        let (token, claims) = auth.issue(&user(), None).await.unwrap();
+        let response = server.get("/me").authorization_bearer(&token).await;
+        response.assert_status_ok();
+        response.assert_text("admin@example.com");
+
+        auth.revoke(&claims).await.unwrap();
+        let response = server.get("/me").authorization_bearer(&token).await;
+        response.assert_status_unauthorized();
+        response.assert_json_contains(&json!({ "code": "revoked_token" }));
+    }
+
+    #[tokio::test]
+    async fn test_reject_token_from_other_secret() {
+        let auth = Auth::new(b"secret", Duration::minutes(5));
+        let other = Auth::new(b"another-secret", Duration::minutes(5));
+        let (token, _) = other.issue(&user(), None).await.unwrap();
+        assert_eq!(
+            Some(AuthError::InvalidToken),
+            auth.verify(&token).await.err()
+        );
+
+        let expired = Auth::new(b"secret", Duration::minutes(-5));
+        let (token, _) = expired.issue(&user(), None).await.unwrap();
+        assert_eq!(
+            Some(AuthError::InvalidToken),
+            auth.verify(&token).await.err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_login_logout() {
+        let database = TestDatabase::sqlite().await.unwrap();
+        let state = AppState::for_database(&database);
+        seed_admin(&*state.users, "Admin", "admin@example.com", "password123")
+            .await
+            .unwrap();
+        let server = TestServer::new(app(state)).unwrap();
+
+        server
+            .post("/auth/login")
+            .json(&json!({ "email": "admin@example.com", "password": "wrong-password" }))
+            .await
+            .assert_status_unauthorized();
+
+        let response = server
+            .post("/auth/login")
+            .json(&json!({ "email": "admin@example.com", "password": "password123" }))
+            .await;
+        response.assert_status_ok();
+        let login: LoginResponse = response.json();
+
+        server
+            .post("/auth/login")
+            .form(&[("email", "admin@example.com"), ("password", "password123")])
+            .await
+            .assert_status_ok();
+
+        server.get("/users").await.assert_status_unauthorized();
+        server
+            .get("/users")
+            .authorization_bearer(&login.token)
+            .await
+            .assert_status_ok();
+        server.get("/products").await.assert_status_ok();
+
+        server
+            .post("/auth/logout")
+            .authorization_bearer(&login.token)
+            .await
+            .assert_status(StatusCode::NO_CONTENT);
+        server
+            .get("/users")
+            .authorization_bearer(&login.token)
+            .await
+            .assert_status_unauthorized();
+    }
+
+    #[tokio::test]
+    async fn test_sessions() {
+        let database = TestDatabase::sqlite().await.unwrap();
+        let state = AppState::for_database(&database);
+        seed_admin(&*state.users, "Admin", "admin@example.com", "password123")
+            .await
+            .unwrap();
+        let server = TestServer::new(app(state)).unwrap();
+
+        let mut tokens = Vec::new();
+        for user_agent in ["PostmanRuntime/7.45.0", "curl/8.5.0"] {
+            let login: LoginResponse = server
+                .post("/auth/login")
+                .add_header(header::USER_AGENT, user_agent)
+                .json(&json!({ "email": "admin@example.com", "password": "password123" }))
+                .await
+                .json();
+            tokens.push(login.token);
+        }
+
+        let sessions: Vec<SessionResponse> = server
+            .get("/auth/sessions")
+            .authorization_bearer(&tokens[0])
+            .await
+            .json();
+        assert_eq!(2, sessions.len());
+        let other = sessions.iter().find(|session| !session.current).unwrap();
+        assert_eq!(Some("curl/8.5.0"), other.user_agent.as_deref());
+
+        server
+            .delete("/auth/sessions/not-my-session")
+            .authorization_bearer(&tokens[0])
+            .await
+            .assert_status_not_found();
+        server
+            .delete(&format!("/auth/sessions/{}", other.id))
+            .authorization_bearer(&tokens[0])
+            .await
+            .assert_status(StatusCode::NO_CONTENT);
+        server
+            .get("/auth/sessions")
+            .authorization_bearer(&tokens[1])
+            .await
+            .assert_status_unauthorized();
+
+        server
+            .delete("/auth/sessions")
+            .authorization_bearer(&tokens[0])
+            .await
+            .assert_status(StatusCode::NO_CONTENT);
+        server
+            .get("/auth/sessions")
+            .authorization_bearer(&tokens[0])
+            .await
+            .assert_status_unauthorized();
+    }
+}
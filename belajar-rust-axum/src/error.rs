@@ -0,0 +1,239 @@
+use std::collections::BTreeMap;
+
+use axum::{
+    extract::rejection::{FormRejection, JsonRejection, PathRejection, QueryRejection},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use belajar_rust_database::{
+    import::ImportError,
+    repository::{CheckoutError, UpdateProductError, UserError},
+};
+use log::error;
+use serde::{Deserialize, Serialize};
+use validator::ValidationErrors;
+
+use crate::{auth::AuthError, validation::flatten_errors};
+
+/*
+ERROR RESPONSE
+- Semua error dari handler dikembalikan dalam bentuk AppError
+- AppError diubah menjadi response JSON dengan format yang sama, sehingga mudah dibaca oleh client:
+`{ "status": 404, "code": "not_found", "message": "...", "errors": { "field": [...] } }`
+- `code` adalah kode error yang stabil untuk dibaca program, sedangkan `message` ditujukan untuk manusia
+- `errors` hanya muncul jika ada error per field, misal saat validasi request gagal
+- Product yang sudah diubah orang lain sejak dibaca (versi nya berbeda) dikembalikan dengan status 409 `version_conflict`,
+client sebaiknya membaca ulang product tersebut sebelum mencoba lagi
+- Error database tidak pernah dikirim ke client, karena pesannya bisa berisi nama tabel, query, atau alamat server
+- Error tersebut hanya ditulis ke log, sedangkan client hanya menerima status 500 `internal_error` dengan pesan yang tetap
+*/
+
+pub type AppResult<T> = Result<T, AppError>;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldError {
+    pub code: String,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ErrorBody {
+    pub status: u16,
+    pub code: String,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub errors: BTreeMap<String, Vec<FieldError>>,
+}
+
+#[derive(Debug)]
+pub struct AppError {
+    pub status: StatusCode,
+    pub code: &'static str,
+    pub message: String,
+    pub errors: BTreeMap<String, Vec<FieldError>>,
+}
+
+impl AppError {
+    pub fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        AppError {
+            status,
+            code,
+            message: message.into(),
+            errors: BTreeMap::new(),
+        }
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        AppError::new(StatusCode::INTERNAL_SERVER_ERROR, "internal_error", message)
+    }
+
+    pub fn not_found() -> Self {
+        AppError::new(StatusCode::NOT_FOUND, "not_found", "data not found")
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let status = self.status;
+        let body = axum::Json(ErrorBody {
+            status: status.as_u16(),
+            code: self.code.to_string(),
+            message: self.message,
+            errors: self.errors,
+        });
+
+        if status == StatusCode::UNAUTHORIZED {
+            (status, [(header::WWW_AUTHENTICATE, "Bearer")], body).into_response()
+        } else {
+            (status, body).into_response()
+        }
+    }
+}
+
+impl From<UserError> for AppError {
+    fn from(error: UserError) -> Self {
+        match error {
+            UserError::EmailTaken(_) => {
+                AppError::new(StatusCode::CONFLICT, "email_taken", error.to_string())
+            }
+            UserError::Database(error) => error.into(),
+        }
+    }
+}
+
+impl From<sqlx::Error> for AppError {
+    fn from(error: sqlx::Error) -> Self {
+        error!("Database error: {}", error);
+        AppError::internal("internal server error")
+    }
+}
+
+impl From<UpdateProductError> for AppError {
+    fn from(error: UpdateProductError) -> Self {
+        let (status, code) = match error {
+            UpdateProductError::NotFound(_) => (StatusCode::NOT_FOUND, "not_found"),
+            UpdateProductError::Conflict { .. } => (StatusCode::CONFLICT, "version_conflict"),
+            UpdateProductError::Database(error) => return error.into(),
+        };
+        AppError::new(status, code, error.to_string())
+    }
+}
+
+impl From<CheckoutError> for AppError {
+    fn from(error: CheckoutError) -> Self {
+        let (status, code) = match error {
+            CheckoutError::EmptyTransaction => (StatusCode::BAD_REQUEST, "empty_transaction"),
+            CheckoutError::ProductNotFound(_) => (StatusCode::NOT_FOUND, "product_not_found"),
+            CheckoutError::InsufficientStock { .. } => (StatusCode::CONFLICT, "insufficient_stock"),
+            CheckoutError::Database(error) => return error.into(),
+        };
+        AppError::new(status, code, error.to_string())
+    }
+}
+
+impl From<ImportError> for AppError {
+    fn from(error: ImportError) -> Self {
+        match error {
+            ImportError::Csv(error) => {
+                AppError::new(StatusCode::BAD_REQUEST, "invalid_csv", error.to_string())
+            }
+            ImportError::Database(error) => error.into(),
+        }
+    }
+}
+
+impl From<AuthError> for AppError {
+    fn from(error: AuthError) -> Self {
+        let (code, message) = match error {
+            AuthError::SessionStore => {
+                return AppError::new(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "session_store_unavailable",
+                    "session store is unavailable",
+                );
+            }
+            AuthError::MissingToken => ("missing_token", "missing bearer token"),
+            AuthError::InvalidToken => ("invalid_token", "invalid or expired token"),
+            AuthError::RevokedToken => ("revoked_token", "token has been revoked"),
+            AuthError::InvalidCredentials => ("invalid_credentials", "invalid email or password"),
+        };
+        AppError::new(StatusCode::UNAUTHORIZED, code, message)
+    }
+}
+
+impl From<JsonRejection> for AppError {
+    fn from(rejection: JsonRejection) -> Self {
+        let code = match rejection {
+            JsonRejection::JsonDataError(_) => "invalid_body",
+            JsonRejection::JsonSyntaxError(_) => "malformed_json",
+            JsonRejection::MissingJsonContentType(_) => "unsupported_media_type",
+            _ => "bad_request",
+        };
+        AppError::new(rejection.status(), code, rejection.body_text())
+    }
+}
+
+impl From<FormRejection> for AppError {
+    fn from(rejection: FormRejection) -> Self {
+        let code = match rejection {
+            FormRejection::FailedToDeserializeForm(_)
+            | FormRejection::FailedToDeserializeFormBody(_) => "invalid_body",
+            FormRejection::InvalidFormContentType(_) => "unsupported_media_type",
+            _ => "bad_request",
+        };
+        AppError::new(rejection.status(), code, rejection.body_text())
+    }
+}
+
+impl From<PathRejection> for AppError {
+    fn from(rejection: PathRejection) -> Self {
+        AppError::new(rejection.status(), "invalid_path", rejection.body_text())
+    }
+}
+
+impl From<QueryRejection> for AppError {
+    fn from(rejection: QueryRejection) -> Self {
+        AppError::new(rejection.status(), "invalid_query", rejection.body_text())
+    }
+}
+
+impl From<ValidationErrors> for AppError {
+    fn from(errors: ValidationErrors) -> Self {
+        AppError {
+            status: StatusCode::UNPROCESSABLE_ENTITY,
+            code: "validation_failed",
+            message: "request validation failed".to_string(),
+            errors: flatten_errors(&errors),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+
+    use super::AppError;
+    use crate::auth::AuthError;
+
+    #[test]
+    fn test_database_error_is_not_exposed() {
+        let error = AppError::from(sqlx::Error::Protocol(
+            "table minipos.users doesn't exist".to_string(),
+        ));
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, error.status);
+        assert_eq!("internal_error", error.code);
+        assert_eq!("internal server error", error.message);
+    }
+
+    #[test]
+    fn test_session_store_error_is_not_exposed() {
+        let error = AppError::from(AuthError::from(redis::RedisError::from((
+            redis::ErrorKind::IoError,
+            "connection refused",
+            "redis://10.0.0.1:6379".to_string(),
+        ))));
+        assert_eq!(StatusCode::SERVICE_UNAVAILABLE, error.status);
+        assert_eq!("session_store_unavailable", error.code);
+        assert_eq!("session store is unavailable", error.message);
+    }
+}
@@ -0,0 +1,155 @@
+use axum::{
+    extract::{FromRef, FromRequest, FromRequestParts, Request},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use belajar_rust_database::{export::ExportFormat, repository::Page};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use validator::{Validate, ValidateArgs};
+
+use crate::error::AppError;
+
+/*
+EXTRACTOR
+- Extractor bawaan Axum (`Json`, `Path`, `Query`, `Form`) mengembalikan error dalam bentuk text biasa
+- Extractor di module ini membungkus extractor bawaan Axum, namun rejection nya diubah menjadi AppError,
+sehingga error parsing request juga dikembalikan dalam format JSON yang sama
+- `JsonOrForm` membaca body sebagai form jika Content-Type nya `application/x-www-form-urlencoded`,
+selain itu body dibaca sebagai JSON
+- `ValidatedJson` membaca body JSON lalu menjalankan `validate()`, jika gagal response nya 422 dengan error per field
+- `ValidatedJsonWith` sama seperti `ValidatedJson`, namun menjalankan `validate_with_args()` dengan context
+yang diambil dari state menggunakan `FromRef`
+*/
+
+#[derive(FromRequest)]
+#[from_request(via(axum::Json), rejection(AppError))]
+pub struct Json<T>(pub T);
+
+impl<T: Serialize> IntoResponse for Json<T> {
+    fn into_response(self) -> Response {
+        axum::Json(self.0).into_response()
+    }
+}
+
+#[derive(FromRequestParts)]
+#[from_request(via(axum::extract::Path), rejection(AppError))]
+pub struct Path<T>(pub T);
+
+#[derive(FromRequestParts)]
+#[from_request(via(axum::extract::Query), rejection(AppError))]
+pub struct Query<T>(pub T);
+
+#[derive(FromRequest)]
+#[from_request(via(axum::Form), rejection(AppError))]
+pub struct Form<T>(pub T);
+
+pub struct JsonOrForm<T>(pub T);
+
+impl<S, T> FromRequest<S> for JsonOrForm<T>
+where
+    S: Send + Sync,
+    T: DeserializeOwned,
+{
+    type Rejection = AppError;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let is_form = request
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("application/x-www-form-urlencoded"));
+
+        if is_form {
+            let Form(value) = Form::<T>::from_request(request, state).await?;
+            Ok(JsonOrForm(value))
+        } else {
+            let Json(value) = Json::<T>::from_request(request, state).await?;
+            Ok(JsonOrForm(value))
+        }
+    }
+}
+
+pub struct ValidatedJson<T>(pub T);
+
+impl<S, T> FromRequest<S> for ValidatedJson<T>
+where
+    S: Send + Sync,
+    T: DeserializeOwned + Validate,
+{
+    type Rejection = AppError;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<T>::from_request(request, state).await?;
+        value.validate()?;
+        Ok(ValidatedJson(value))
+    }
+}
+
+pub struct ValidatedJsonWith<T>(pub T);
+
+impl<S, T, C> FromRequest<S> for ValidatedJsonWith<T>
+where
+    S: Send + Sync,
+    T: DeserializeOwned + for<'a> ValidateArgs<'a, Args = &'a C>,
+    C: FromRef<S> + Send,
+{
+    type Rejection = AppError;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let context = C::from_ref(state);
+        let Json(value) = Json::<T>::from_request(request, state).await?;
+        value.validate_with_args(&context)?;
+        Ok(ValidatedJsonWith(value))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListQuery {
+    #[serde(default = "ListQuery::default_page")]
+    pub page: usize,
+    #[serde(default = "ListQuery::default_size")]
+    pub size: usize,
+    pub q: Option<String>,
+}
+
+impl ListQuery {
+    const MAX_SIZE: usize = 100;
+
+    fn default_page() -> usize {
+        1
+    }
+
+    fn default_size() -> usize {
+        20
+    }
+
+    /// The page size and the number of items before the page.
+    pub fn limit_and_skip(&self) -> (usize, usize) {
+        let size = self.size.clamp(1, Self::MAX_SIZE);
+        (size, self.page.saturating_sub(1).saturating_mul(size))
+    }
+
+    /// The requested page for a repository query, `page` starts from 1 and
+    /// `size` is capped at 100.
+    pub fn page<K>(&self) -> Page<K> {
+        let (size, skip) = self.limit_and_skip();
+        Page::Offset {
+            limit: size as u32,
+            offset: skip as u64,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportQuery {
+    /// Validates every row without saving anything.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    /// `csv` (default), `ndjson` or `jsonl`.
+    #[serde(default)]
+    pub format: ExportFormat,
+}
@@ -0,0 +1,905 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    Extension,
+    body::{Body, Bytes},
+    extract::State,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use belajar_rust_database::{
+    export::{ExportTable, Exporter},
+    import::{ImportOptions, ImportReport, ImportTable, Importer},
+    model::{ProductId, Seller, SellerId, TransactionId, UserId},
+    repository::{
+        NewProduct, NewTransactionItem, NewUser, ProductRepository, SellerRepository,
+        TransactionRepository, UserRepository,
+    },
+};
+use belajar_rust_redis::{
+    cache::{CacheStats, RedisCache},
+    connection::RedisConnection,
+    event::{Event, Producer, TransactionCreated},
+    geo::{GeoQuery, MAX_NEARBY_RESULTS, SellerGeoIndex},
+};
+use log::{error, warn};
+use serde::{Deserialize, Serialize};
+use sqlx::MySqlPool;
+use validator::Validate;
+
+use crate::{
+    auth::{Auth, Claims, hash_password},
+    error::{AppError, AppResult},
+    extract::{
+        ExportQuery, ImportQuery, Json, ListQuery, Path, Query, ValidatedJson, ValidatedJsonWith,
+    },
+    model::{
+        CreateProductRequest, CreateTransactionRequest, CreateUserRequest, NearbySeller,
+        NearbySellersQuery, Product, Transaction, UpdateProductRequest, UpdateUserRequest, User,
+    },
+};
+
+/*
+HANDLER
+- Handler adalah function async yang dipanggil oleh Router ketika path dan method nya cocok
+- Repository dan MySqlPool diambil menggunakan extractor `State`, id diambil dari path menggunakan extractor `Path`,
+parameter pagination dan pencarian diambil dari query string menggunakan extractor `Query`
+- Body request dibaca menggunakan `ValidatedJson`, sehingga handler hanya menerima data yang sudah valid
+- Handler mengembalikan AppResult, sehingga error otomatis diubah menjadi response JSON
+- Email user yang login (dari `Extension<Claims>`) dicatat sebagai actor di audit log setiap perubahan user, product dan transaction
+- `/health` mengembalikan 503 jika database tidak tersedia, Redis yang mati hanya membuat status nya `degraded`,
+karena fitur yang memakai Redis tetap berjalan tanpa Redis, field `redis` tidak ada jika Redis tidak dikonfigurasi
+- Field `cache` berisi jumlah hit, miss dan error cache product dan seller sejak aplikasi berjalan, juga hanya ada jika Redis dikonfigurasi
+*/
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Health {
+    pub status: String,
+    pub database: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redis: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache: Option<CacheHealth>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct CacheHealth {
+    pub hits: u64,
+    pub misses: u64,
+    pub errors: u64,
+    pub hit_ratio: f64,
+}
+
+impl From<CacheStats> for CacheHealth {
+    fn from(stats: CacheStats) -> Self {
+        CacheHealth {
+            hits: stats.hits,
+            misses: stats.misses,
+            errors: stats.errors,
+            hit_ratio: stats.hit_ratio(),
+        }
+    }
+}
+
+pub async fn health(
+    State(pool): State<MySqlPool>,
+    State(redis): State<Option<RedisConnection>>,
+    State(cache): State<Option<RedisCache>>,
+) -> AppResult<Json<Health>> {
+    sqlx::query("select 1")
+        .execute(&pool)
+        .await
+        .map_err(|error| {
+            error!("Database health check failed: {}", error);
+            AppError::new(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "database_unavailable",
+                "database is unavailable",
+            )
+        })?;
+
+    let redis = match redis {
+        Some(redis) => Some(match redis.check().await {
+            Ok(_) => "up",
+            Err(error) => {
+                warn!("Redis health check failed: {}", error);
+                "down"
+            }
+        }),
+        None => None,
+    };
+    Ok(Json(Health {
+        status: if redis == Some("down") {
+            "degraded"
+        } else {
+            "ok"
+        }
+        .to_string(),
+        database: "up".to_string(),
+        redis: redis.map(str::to_string),
+        cache: cache.map(|cache| cache.stats().into()),
+    }))
+}
+
+pub async fn list_users(
+    State(users): State<Arc<dyn UserRepository>>,
+    Query(query): Query<ListQuery>,
+) -> AppResult<Json<Vec<User>>> {
+    let page = query.page();
+    let found = match &query.q {
+        Some(name) => users.search_by_name(name, &page).await?,
+        None => users.list(&page).await?,
+    };
+    Ok(Json(found.into_iter().map(User::from).collect()))
+}
+
+pub async fn create_user(
+    State(users): State<Arc<dyn UserRepository>>,
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(request): ValidatedJson<CreateUserRequest>,
+) -> AppResult<(StatusCode, Json<User>)> {
+    let user = NewUser {
+        name: request.name,
+        email: request.email,
+        password: hash_password(&request.password),
+    };
+    let id = users.insert(&claims.email, &user).await?;
+    let user = users
+        .find_by_id(id)
+        .await?
+        .ok_or_else(AppError::not_found)?;
+    Ok((StatusCode::CREATED, Json(user.into())))
+}
+
+pub async fn get_user(
+    State(users): State<Arc<dyn UserRepository>>,
+    Path(id): Path<u64>,
+) -> AppResult<Json<User>> {
+    let user = users.find_by_id(UserId(id)).await?;
+    Ok(Json(user.ok_or_else(AppError::not_found)?.into()))
+}
+
+pub async fn update_user(
+    State(users): State<Arc<dyn UserRepository>>,
+    State(auth): State<Auth>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<u64>,
+    ValidatedJson(request): ValidatedJson<UpdateUserRequest>,
+) -> AppResult<Json<User>> {
+    let mut user = users
+        .find_by_id(UserId(id))
+        .await?
+        .ok_or_else(AppError::not_found)?;
+    let password_changed = request.password.is_some();
+    if let Some(name) = request.name {
+        user.name = name;
+    }
+    if let Some(email) = request.email {
+        user.email = email;
+    }
+    if let Some(password) = request.password {
+        user.password = hash_password(&password);
+    }
+
+    if !users.update(&claims.email, &user).await? {
+        return Err(AppError::not_found());
+    }
+    if password_changed {
+        auth.revoke_all(id).await?;
+    }
+    Ok(Json(user.into()))
+}
+
+pub async fn delete_user(
+    State(users): State<Arc<dyn UserRepository>>,
+    State(auth): State<Auth>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<u64>,
+) -> AppResult<StatusCode> {
+    if !users.delete(&claims.email, UserId(id)).await? {
+        return Err(AppError::not_found());
+    }
+    auth.revoke_all(id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn list_products(
+    State(products): State<Arc<dyn ProductRepository>>,
+    Query(query): Query<ListQuery>,
+) -> AppResult<Json<Vec<Product>>> {
+    let page = query.page();
+    let found = match &query.q {
+        Some(name) => products.search_by_name(name, &page).await?,
+        None => products.list(&page).await?,
+    };
+    Ok(Json(found.into_iter().map(Product::from).collect()))
+}
+
+pub async fn create_product(
+    State(products): State<Arc<dyn ProductRepository>>,
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(request): ValidatedJson<CreateProductRequest>,
+) -> AppResult<(StatusCode, Json<Product>)> {
+    let product = NewProduct {
+        name: request.name,
+        description: request.description,
+        price: request.price,
+        stock: request.stock,
+    };
+    let id = products.insert(&claims.email, &product).await?;
+    let product = products
+        .find_by_id(id)
+        .await?
+        .ok_or_else(AppError::not_found)?;
+    Ok((StatusCode::CREATED, Json(product.into())))
+}
+
+pub async fn get_product(
+    State(products): State<Arc<dyn ProductRepository>>,
+    Path(id): Path<u64>,
+) -> AppResult<Json<Product>> {
+    let product = products.find_by_id(ProductId(id)).await?;
+    Ok(Json(product.ok_or_else(AppError::not_found)?.into()))
+}
+
+/*
+UPDATE PRODUCT
+- Field yang tidak dikirim tidak diubah, sehingga product dibaca terlebih dahulu, lalu field yang dikirim ditimpa
+- Jika client mengirim `version`, update hanya berhasil jika product belum diubah sejak versi tersebut,
+jika sudah, response nya 409 Conflict
+- Tanpa `version`, versi yang baru dibaca yang digunakan, sehingga perubahan di antara membaca dan menyimpan tetap terdeteksi
+*/
+
+pub async fn update_product(
+    State(products): State<Arc<dyn ProductRepository>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<u64>,
+    ValidatedJson(request): ValidatedJson<UpdateProductRequest>,
+) -> AppResult<Json<Product>> {
+    let mut product = products
+        .find_by_id(ProductId(id))
+        .await?
+        .ok_or_else(AppError::not_found)?;
+    if let Some(version) = request.version {
+        product.version = version;
+    }
+    if let Some(name) = request.name {
+        product.name = name;
+    }
+    if let Some(description) = request.description {
+        product.description = description;
+    }
+    if let Some(price) = request.price {
+        product.price = price;
+    }
+    if let Some(stock) = request.stock {
+        product.stock = stock;
+    }
+    Ok(Json(products.update(&claims.email, &product).await?.into()))
+}
+
+pub async fn delete_product(
+    State(products): State<Arc<dyn ProductRepository>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<u64>,
+) -> AppResult<StatusCode> {
+    if !products.delete(&claims.email, ProductId(id)).await? {
+        return Err(AppError::not_found());
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn list_transactions(
+    State(transactions): State<Arc<dyn TransactionRepository>>,
+    Query(query): Query<ListQuery>,
+) -> AppResult<Json<Vec<Transaction>>> {
+    let found = transactions.list(&query.page()).await?;
+    Ok(Json(found.into_iter().map(Transaction::from).collect()))
+}
+
+/*
+CREATE TRANSACTION
+- Setelah checkout di-commit, event `transaction.created` dikirim ke Redis Stream jika Redis dikonfigurasi
+- Transaksi sudah tersimpan di database, sehingga gagal mengirim event hanya dicatat sebagai warning,
+request tetap berhasil
+*/
+
+pub async fn create_transaction(
+    State(transactions): State<Arc<dyn TransactionRepository>>,
+    State(events): State<Option<Producer>>,
+    Extension(claims): Extension<Claims>,
+    ValidatedJsonWith(request): ValidatedJsonWith<CreateTransactionRequest>,
+) -> AppResult<(StatusCode, Json<Transaction>)> {
+    let items: Vec<NewTransactionItem> = request
+        .items
+        .iter()
+        .map(|item| NewTransactionItem {
+            product_id: ProductId(item.product_id),
+            quantity: item.quantity,
+        })
+        .collect();
+    let transaction = transactions.checkout(&claims.email, &items).await?;
+    if let Some(events) = events {
+        let event = TransactionCreated::new(&claims.email, &transaction);
+        if let Err(error) = events.publish(&event).await {
+            warn!(
+                "Failed to publish {} for transaction {}: {}",
+                TransactionCreated::TYPE,
+                transaction.id.0,
+                error
+            );
+        }
+    }
+    Ok((StatusCode::CREATED, Json(transaction.into())))
+}
+
+pub async fn get_transaction(
+    State(transactions): State<Arc<dyn TransactionRepository>>,
+    Path(id): Path<u64>,
+) -> AppResult<Json<Transaction>> {
+    let transaction = transactions.find_by_id(TransactionId(id)).await?;
+    Ok(Json(transaction.ok_or_else(AppError::not_found)?.into()))
+}
+
+/// Geo index reads per request, each one after removing sellers that no longer exist.
+const NEARBY_ATTEMPTS: usize = 3;
+
+/*
+NEARBY SELLERS
+- Seller terdekat dicari di index Redis Geo, lalu data seller nya dibaca dari database, urutan nya tetap dari yang terdekat
+- Pagination menggunakan query `page` dan `size` yang sama dengan endpoint list lainnya,
+halaman yang melewati `MAX_NEARBY_RESULTS` hasil pertama ditolak dengan status 400
+- Index bisa tertinggal dari database, seller yang sudah dihapus dihapus juga dari index lalu halaman nya dibaca ulang,
+supaya halaman tidak kurang dari `size` selama masih ada hasil berikutnya
+- Data seller satu halaman dibaca sekaligus dengan `find_by_ids`, bukan satu query per seller
+- Tanpa Redis, endpoint ini mengembalikan status 503
+*/
+
+pub async fn nearby_sellers(
+    State(index): State<Option<SellerGeoIndex>>,
+    State(sellers): State<Arc<dyn SellerRepository>>,
+    Query(query): Query<NearbySellersQuery>,
+    Query(page): Query<ListQuery>,
+) -> AppResult<Json<Vec<NearbySeller>>> {
+    query.validate()?;
+    let (limit, offset) = page.limit_and_skip();
+    if offset.saturating_add(limit) > MAX_NEARBY_RESULTS {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "invalid_query",
+            format!(
+                "nearby seller search only returns the first {} results",
+                MAX_NEARBY_RESULTS
+            ),
+        ));
+    }
+    let unavailable = |message: &str| {
+        AppError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "geo_index_unavailable",
+            message,
+        )
+    };
+    let index = index.ok_or_else(|| unavailable("nearby seller search is not configured"))?;
+
+    let geo_query = GeoQuery {
+        latitude: query.lat,
+        longitude: query.lon,
+        radius: query.radius,
+        unit: query.unit,
+        offset,
+        limit,
+    };
+    let mut attempts = 0;
+    let (found, mut by_id) = loop {
+        let found = index.nearby(&geo_query).await.map_err(|error| {
+            warn!("Failed to search seller geo index: {}", error);
+            unavailable("nearby seller search is unavailable")
+        })?;
+        let ids: Vec<SellerId> = found.iter().map(|result| result.id).collect();
+        let by_id: HashMap<SellerId, Seller> = sellers
+            .find_by_ids(&ids)
+            .await?
+            .into_iter()
+            .map(|seller| (seller.id, seller))
+            .collect();
+
+        let stale: Vec<SellerId> = ids
+            .into_iter()
+            .filter(|id| !by_id.contains_key(id))
+            .collect();
+        attempts += 1;
+        if stale.is_empty() || attempts == NEARBY_ATTEMPTS {
+            break (found, by_id);
+        }
+        if let Err(error) = index.remove(&stale).await {
+            warn!("Failed to remove stale sellers from geo index: {}", error);
+            break (found, by_id);
+        }
+    };
+
+    let nearby = found
+        .into_iter()
+        .filter_map(|result| {
+            let seller = by_id.remove(&result.id)?;
+            Some(NearbySeller {
+                id: seller.id.into(),
+                name: seller.name,
+                latitude: seller.latitude,
+                longitude: seller.longitude,
+                distance: result.distance,
+            })
+        })
+        .collect();
+    Ok(Json(nearby))
+}
+
+/*
+EXPORT
+- Export dikirim sebagai body chunked menggunakan `Body::from_stream`, sehingga tabel yang besar
+tidak perlu ditampung di memory terlebih dahulu
+- Header `Content-Disposition: attachment` membuat browser menyimpan response sebagai file, misal `products.csv`
+- Jika terjadi error di tengah stream, status 200 sudah terlanjur dikirim, sehingga koneksi diputus
+dan client mendapatkan file yang tidak lengkap
+*/
+
+pub async fn export(
+    State(exporter): State<Arc<dyn Exporter>>,
+    Path(table): Path<ExportTable>,
+    Query(query): Query<ExportQuery>,
+) -> Response {
+    let format = query.format;
+    let headers = [
+        (header::CONTENT_TYPE, format.content_type().to_string()),
+        (
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", table.file_name(format)),
+        ),
+    ];
+    let body = Body::from_stream(exporter.export(table, format));
+    (headers, body).into_response()
+}
+
+/*
+IMPORT
+- File CSV dikirim langsung sebagai body request, misal `curl --data-binary @products.csv /imports/products`
+- Response nya adalah report import: jumlah baris, baris yang valid dan yang disimpan, serta error per baris yang ditolak
+- Baris yang ditolak tidak membuat import gagal, hanya file yang tidak bisa dibaca sama sekali yang menghasilkan 400 `invalid_csv`
+- Email user yang login dicatat sebagai actor di audit log setiap baris yang disimpan
+- Body request dibatasi oleh `DefaultBodyLimit` Axum, yaitu 2 MB
+*/
+
+pub async fn import(
+    State(importer): State<Arc<dyn Importer>>,
+    Extension(claims): Extension<Claims>,
+    Path(table): Path<ImportTable>,
+    Query(query): Query<ImportQuery>,
+    body: Bytes,
+) -> AppResult<Json<ImportReport>> {
+    let options = ImportOptions {
+        dry_run: query.dry_run,
+        actor: claims.email,
+        ..ImportOptions::default()
+    };
+    Ok(Json(importer.import(table, &body, &options).await?))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{StatusCode, header};
+    use axum_test::TestServer;
+    use belajar_rust_database::testing::TestDatabase;
+    use belajar_rust_redis::cache::CacheStats;
+    use serde_json::{Value, json};
+    use sqlx::Sqlite;
+
+    use super::CacheHealth;
+    use crate::{
+        app,
+        auth::seed_admin,
+        error::ErrorBody,
+        model::{Product, Transaction, User},
+        state::AppState,
+    };
+
+    /// Server without a database, logged in as an admin that is not stored.
+    async fn server() -> TestServer {
+        let admin = User {
+            id: 1,
+            name: "Admin".to_string(),
+            email: "admin@example.com".to_string(),
+            password: String::new(),
+        };
+        server_with(AppState::for_test(), admin).await
+    }
+
+    /// Server whose users, products and transactions are stored in a seeded
+    /// SQLite database, which is deleted when it is dropped.
+    async fn database_server() -> (TestServer, TestDatabase<Sqlite>) {
+        let database = TestDatabase::sqlite().await.unwrap();
+        let state = AppState::for_database(&database);
+        let admin = seed_admin(&*state.users, "Admin", "admin@example.com", "password123")
+            .await
+            .unwrap();
+        (server_with(state, admin).await, database)
+    }
+
+    async fn server_with(state: AppState, admin: User) -> TestServer {
+        let (token, _) = state.auth.issue(&admin, None).await.unwrap();
+
+        let mut server = TestServer::new(app(state)).unwrap();
+        server.add_header(header::AUTHORIZATION, format!("Bearer {}", token));
+        server
+    }
+
+    #[tokio::test]
+    async fn test_user_crud() {
+        let (server, _database) = database_server().await;
+
+        let response = server
+            .post("/users")
+            .json(&json!({
+                "name": "John Doe",
+                "email": "johndoe@example.com",
+                "password": "password123"
+            }))
+            .await;
+        response.assert_status(StatusCode::CREATED);
+        assert!(!response.text().contains("password123"));
+        let user: Value = response.json();
+        let id = user["id"].as_u64().unwrap();
+
+        let response = server
+            .put(&format!("/users/{}", id))
+            .json(&json!({ "name": "John Doe Updated" }))
+            .await;
+        response.assert_status_ok();
+        response.assert_json_contains(&json!({ "name": "John Doe Updated" }));
+
+        let users: Vec<Value> = server.get("/users").await.json();
+        assert_eq!("johndoe@example.com", users[1]["email"]);
+
+        let response = server.delete(&format!("/users/{}", id)).await;
+        response.assert_status(StatusCode::NO_CONTENT);
+
+        let response = server.get(&format!("/users/{}", id)).await;
+        response.assert_status_not_found();
+    }
+
+    #[tokio::test]
+    async fn test_product_crud() {
+        let (server, _database) = database_server().await;
+
+        let response = server
+            .post("/products")
+            .json(&json!({
+                "name": "Laptop Pro",
+                "description": "Laptop canggih untuk profesional",
+                "price": 15000000,
+                "stock": 50
+            }))
+            .await;
+        response.assert_status(StatusCode::CREATED);
+        let product: Product = response.json();
+
+        let response = server
+            .put(&format!("/products/{}", product.id))
+            .json(&json!({ "price": 14500000, "stock": 45 }))
+            .await;
+        response.assert_status_ok();
+        let product: Product = response.json();
+        assert_eq!(14500000, product.price);
+        assert_eq!(45, product.stock);
+        assert_eq!(1, product.version);
+        assert_eq!("Laptop Pro", product.name);
+
+        let response = server.delete(&format!("/products/{}", product.id)).await;
+        response.assert_status(StatusCode::NO_CONTENT);
+        server
+            .get(&format!("/products/{}", product.id))
+            .await
+            .assert_status_not_found();
+    }
+
+    #[tokio::test]
+    async fn test_update_product_conflict() {
+        let (server, _database) = database_server().await;
+        let product: Product = server.get("/products/1").await.json();
+
+        let response = server
+            .put("/products/1")
+            .json(&json!({ "price": 14500000, "version": product.version }))
+            .await;
+        response.assert_status_ok();
+
+        let response = server
+            .put("/products/1")
+            .json(&json!({ "stock": 45, "version": product.version }))
+            .await;
+        response.assert_status(StatusCode::CONFLICT);
+        response.assert_json_contains(&json!({ "status": 409, "code": "version_conflict" }));
+
+        let product: Product = server.get("/products/1").await.json();
+        assert_eq!(
+            (14500000, 50, 1),
+            (product.price, product.stock, product.version)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_create_transaction() {
+        let (server, _database) = database_server().await;
+        let product: Product = server
+            .post("/products")
+            .json(
+                &json!({ "name": "Mouse", "description": "Wireless", "price": 150000, "stock": 3 }),
+            )
+            .await
+            .json();
+
+        let response = server
+            .post("/transactions")
+            .json(&json!({ "items": [{ "productId": product.id, "quantity": 2 }] }))
+            .await;
+        response.assert_status(StatusCode::CREATED);
+        let transaction: Transaction = response.json();
+        assert_eq!(300000, transaction.total);
+
+        let product: Product = server
+            .get(&format!("/products/{}", product.id))
+            .await
+            .json();
+        assert_eq!(1, product.stock);
+
+        server
+            .get(&format!("/transactions/{}", transaction.id))
+            .await
+            .assert_status_ok();
+
+        let response = server
+            .post("/transactions")
+            .json(&json!({ "items": [{ "productId": product.id, "quantity": 2 }] }))
+            .await;
+        response.assert_status(StatusCode::CONFLICT);
+
+        let response = server
+            .post("/transactions")
+            .json(&json!({ "items": [{ "productId": 99, "quantity": 1 }] }))
+            .await;
+        response.assert_status_not_found();
+    }
+
+    #[tokio::test]
+    async fn test_list_query() {
+        let (server, _database) = database_server().await;
+        for name in ["Laptop Air", "Mouse"] {
+            server
+                .post("/products")
+                .json(&json!({ "name": name, "description": "-", "price": 1000, "stock": 1 }))
+                .await
+                .assert_status(StatusCode::CREATED);
+        }
+
+        let products: Vec<Product> = server.get("/products?q=laptop").await.json();
+        assert_eq!(2, products.len());
+
+        let products: Vec<Product> = server.get("/products?page=2&size=3").await.json();
+        assert_eq!(1, products.len());
+        assert_eq!("Mouse", products[0].name);
+    }
+
+    #[tokio::test]
+    async fn test_error_envelope() {
+        let (server, _database) = database_server().await;
+
+        let response = server.get("/products/99").await;
+        response.assert_status_not_found();
+        let body: ErrorBody = response.json();
+        assert_eq!(404, body.status);
+        assert_eq!("not_found", body.code);
+
+        let response = server.get("/products/abc").await;
+        response.assert_status_bad_request();
+        response.assert_json_contains(&json!({ "status": 400, "code": "invalid_path" }));
+
+        let response = server
+            .post("/products")
+            .content_type("application/json")
+            .bytes("{ \"name\": ".into())
+            .await;
+        response.assert_status_bad_request();
+        response.assert_json_contains(&json!({ "code": "malformed_json" }));
+
+        let response = server
+            .post("/products")
+            .json(&json!({ "name": "Laptop", "price": "mahal" }))
+            .await;
+        response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+        response.assert_json_contains(&json!({ "code": "invalid_body" }));
+
+        let response = server.get("/products?page=satu").await;
+        response.assert_status_bad_request();
+        response.assert_json_contains(&json!({ "code": "invalid_query" }));
+    }
+
+    #[tokio::test]
+    async fn test_validation_errors() {
+        let server = server().await;
+
+        let response = server
+            .post("/users")
+            .json(&json!({ "name": "", "email": "bukan-email", "password": "pass" }))
+            .await;
+        response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+        let body: ErrorBody = response.json();
+        assert_eq!("validation_failed", body.code);
+        assert_eq!(
+            vec!["email", "name", "password"],
+            body.errors.keys().collect::<Vec<_>>()
+        );
+
+        let response = server
+            .post("/transactions")
+            .json(&json!({ "items": [
+                { "productId": 1, "quantity": 1 },
+                { "productId": 2, "quantity": 0 }
+            ] }))
+            .await;
+        response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+        let body: ErrorBody = response.json();
+        assert_eq!("range", body.errors["items[1].quantity"][0].code);
+
+        let response = server
+            .post("/transactions")
+            .json(&json!({ "items": [{ "productId": 1, "quantity": 5000 }] }))
+            .await;
+        response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+        let body: ErrorBody = response.json();
+        assert_eq!("max_quantity", body.errors["__all__"][0].code);
+    }
+
+    #[test]
+    fn test_cache_health() {
+        let health = CacheHealth::from(CacheStats {
+            hits: 3,
+            misses: 1,
+            errors: 2,
+        });
+        assert_eq!(
+            CacheHealth {
+                hits: 3,
+                misses: 1,
+                errors: 2,
+                hit_ratio: 0.75,
+            },
+            health
+        );
+    }
+
+    #[tokio::test]
+    async fn test_health_without_database() {
+        let server = server().await;
+        let response = server.get("/health").await;
+        response.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        response.assert_json_contains(&json!({
+            "code": "database_unavailable",
+            "message": "database is unavailable"
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_nearby_sellers_without_redis() {
+        let server = server().await;
+
+        let response = server
+            .get("/sellers/nearby?lat=-6.17759&lon=106.822702&radius=5&unit=km")
+            .await;
+        response.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        response.assert_json_contains(&json!({ "code": "geo_index_unavailable" }));
+
+        for page in ["51", "18446744073709551615"] {
+            let response = server
+                .get(&format!(
+                    "/sellers/nearby?lat=0&lon=0&radius=1&size=20&page={}",
+                    page
+                ))
+                .await;
+            response.assert_status(StatusCode::BAD_REQUEST);
+            response.assert_json_contains(&json!({ "code": "invalid_query" }));
+        }
+
+        let response = server.get("/sellers/nearby?lat=-89&lon=181&radius=0").await;
+        response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+        let body: ErrorBody = response.json();
+        assert_eq!(
+            vec!["lat", "lon", "radius"],
+            body.errors.keys().collect::<Vec<_>>()
+        );
+
+        let response = server
+            .get("/sellers/nearby?lat=-6.17759&lon=106.822702&radius=5&unit=yard")
+            .await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        response.assert_json_contains(&json!({ "code": "invalid_query" }));
+    }
+
+    #[tokio::test]
+    async fn test_export() {
+        let (server, _database) = database_server().await;
+
+        let response = server.get("/exports/products").await;
+        response.assert_status_ok();
+        response.assert_header(header::CONTENT_TYPE, "text/csv; charset=utf-8");
+        response.assert_header(
+            header::CONTENT_DISPOSITION,
+            "attachment; filename=\"products.csv\"",
+        );
+        let text = response.text();
+        let mut lines = text.lines();
+        assert_eq!(
+            Some("id,name,description,price,stock,version"),
+            lines.next()
+        );
+        assert_eq!(2, lines.count());
+
+        let response = server.get("/exports/categories?format=ndjson").await;
+        response.assert_status_ok();
+        response.assert_header(header::CONTENT_TYPE, "application/x-ndjson");
+        let names: Vec<String> = response
+            .text()
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap()["name"].to_string())
+            .collect();
+        assert_eq!(vec!["\"Elektronik\"", "\"Gadget\""], names);
+
+        let response = server.get("/exports/products?format=xml").await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        response.assert_json_contains(&json!({ "code": "invalid_query" }));
+
+        server
+            .get("/exports/users")
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_import() {
+        let (server, _database) = database_server().await;
+        let csv = "id,name,description,price,stock\n1,Laptop Pro,Laptop baru,17000000,40\n,Keyboard,,450000,25\n,,Tanpa nama,0,5\n";
+
+        let response = server
+            .post("/imports/products?dry_run=true")
+            .text(csv)
+            .await;
+        response.assert_status_ok();
+        response.assert_json_contains(&json!({
+            "dry_run": true,
+            "total": 3,
+            "valid": 2,
+            "imported": 0,
+        }));
+        let products: Vec<Product> = server.get("/products").await.json();
+        assert_eq!(2, products.len());
+
+        let response = server.post("/imports/products").text(csv).await;
+        response.assert_status_ok();
+        let report: Value = response.json();
+        assert_eq!(2, report["imported"]);
+        assert_eq!(4, report["rejected"][0]["line"]);
+        let products: Vec<Product> = server.get("/products").await.json();
+        assert_eq!(3, products.len());
+        assert_eq!(17000000, products[0].price);
+
+        let response = server
+            .post("/imports/categories")
+            .bytes(b"\xff\xfe".as_slice().into())
+            .await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        response.assert_json_contains(&json!({ "code": "invalid_csv" }));
+        server
+            .post("/imports/users")
+            .text(csv)
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+    }
+}
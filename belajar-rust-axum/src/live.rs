@@ -0,0 +1,228 @@
+use axum::{
+    extract::{
+        State,
+        ws::{Message as WsMessage, WebSocket, WebSocketUpgrade},
+    },
+    http::StatusCode,
+    response::{
+        Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+};
+use belajar_rust_redis::pubsub::Message;
+use futures::{Stream, StreamExt, stream};
+use log::warn;
+use tokio::sync::broadcast::{Receiver, Sender, error::RecvError};
+
+use crate::error::{AppError, AppResult};
+
+/*
+LIVE DASHBOARD
+- Pesan dari Redis Pub/Sub diterima oleh satu relay (`belajar_rust_redis::pubsub::Subscriber`),
+lalu setiap dashboard yang terhubung mendapatkan receiver sendiri dari broadcast channel
+- `/live/ws` mengirim pesan melalui WebSocket, setiap pesan dikirim sebagai text JSON
+`{ "channel": "...", "pattern": null, "payload": "..." }`
+- `/live/sse` mengirim pesan menggunakan Server-Sent Events, nama event nya adalah nama channel,
+dan komentar keep-alive dikirim secara berkala agar koneksi tidak ditutup proxy
+- Dashboard yang terlalu lambat akan melewatkan pesan lama, bukan menahan dashboard lain
+- Jika Redis tidak dikonfigurasi, kedua endpoint mengembalikan 503 Service Unavailable
+- Dashboard di browser mengirim token login melalui query, misal `new EventSource("/live/sse?token=<token>")`
+*/
+
+fn subscribe(live: Option<Sender<Message>>) -> AppResult<Receiver<Message>> {
+    live.map(|sender| sender.subscribe()).ok_or_else(|| {
+        AppError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "live_unavailable",
+            "live updates are not configured",
+        )
+    })
+}
+
+/// Messages from `receiver`, skipping the ones a slow client missed.
+fn messages(receiver: Receiver<Message>) -> impl Stream<Item = Message> {
+    stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(message) => return Some((message, receiver)),
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("Live client lagged, skipped {} messages", skipped)
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+}
+
+pub async fn websocket(
+    State(live): State<Option<Sender<Message>>>,
+    upgrade: WebSocketUpgrade,
+) -> AppResult<Response> {
+    let receiver = subscribe(live)?;
+    Ok(upgrade.on_upgrade(|socket| relay(socket, receiver)))
+}
+
+async fn relay(mut socket: WebSocket, receiver: Receiver<Message>) {
+    let messages = messages(receiver);
+    tokio::pin!(messages);
+    loop {
+        tokio::select! {
+            message = messages.next() => {
+                let Some(message) = message else { break };
+                let Ok(text) = serde_json::to_string(&message) else { continue };
+                if socket.send(WsMessage::Text(text.into())).await.is_err() {
+                    break;
+                }
+            }
+            incoming = socket.recv() => match incoming {
+                Some(Ok(WsMessage::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+pub async fn sse(
+    State(live): State<Option<Sender<Message>>>,
+) -> AppResult<Sse<impl Stream<Item = Result<Event, axum::Error>>>> {
+    let receiver = subscribe(live)?;
+    let events = messages(receiver)
+        .map(|message| Event::default().event(&message.channel).json_data(&message));
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        Router,
+        body::{Body, to_bytes},
+        extract::{Request, State},
+        http::StatusCode,
+        middleware,
+        response::IntoResponse,
+        routing::get,
+    };
+    use axum_test::TestServer;
+    use belajar_rust_redis::pubsub::Message;
+    use chrono::Duration;
+    use futures::StreamExt;
+    use tokio::sync::broadcast;
+    use tower::ServiceExt;
+
+    use super::{messages, sse};
+    use crate::{
+        app,
+        auth::{Auth, require_auth_or_query_token},
+        model::User,
+        state::AppState,
+    };
+
+    fn message(channel: &str, payload: &str) -> Message {
+        Message {
+            channel: channel.to_string(),
+            pattern: None,
+            payload: payload.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_messages_skip_lagged() {
+        let (sender, receiver) = broadcast::channel(2);
+        for payload in ["1", "2", "3"] {
+            sender.send(message("minipos:live", payload)).unwrap();
+        }
+        drop(sender);
+
+        let payloads: Vec<String> = messages(receiver)
+            .map(|message| message.payload)
+            .collect()
+            .await;
+        assert_eq!(vec!["2", "3"], payloads);
+    }
+
+    #[tokio::test]
+    async fn test_sse() {
+        let (sender, _) = broadcast::channel(16);
+        let response = sse(State(Some(sender.clone()))).await.into_response();
+        assert_eq!(StatusCode::OK, response.status());
+        sender.send(message("minipos:live", "Zhafir")).unwrap();
+        drop(sender);
+
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(
+            "event: minipos:live\ndata: {\"channel\":\"minipos:live\",\"pattern\":null,\"payload\":\"Zhafir\"}\n\n",
+            String::from_utf8(body.to_vec()).unwrap()
+        );
+
+        let response = sse(State(None)).await.into_response();
+        assert_eq!(StatusCode::SERVICE_UNAVAILABLE, response.status());
+    }
+
+    #[tokio::test]
+    async fn test_sse_with_query_token() {
+        let auth = Auth::new(b"secret", Duration::minutes(5));
+        let (sender, _) = broadcast::channel(16);
+        let app = Router::new()
+            .route("/live/sse", get(sse))
+            .route_layer(middleware::from_fn_with_state(
+                auth.clone(),
+                require_auth_or_query_token,
+            ))
+            .with_state(Some(sender.clone()));
+        let user = User {
+            id: 1,
+            name: "Admin".to_string(),
+            email: "admin@example.com".to_string(),
+            password: String::new(),
+        };
+        let (token, claims) = auth.issue(&user, None).await.unwrap();
+        let get = |uri: String| {
+            app.clone()
+                .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+        };
+
+        let response = get("/live/sse".to_string()).await.unwrap();
+        assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+        let response = get("/live/sse?token=not-a-jwt".to_string()).await.unwrap();
+        assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+
+        // Like a browser EventSource, without an Authorization header.
+        let response = get(format!("/live/sse?token={}", token)).await.unwrap();
+        assert_eq!(StatusCode::OK, response.status());
+        sender.send(message("minipos:live", "Zhafir")).unwrap();
+        let mut body = response.into_body().into_data_stream();
+        let event = body.next().await.unwrap().unwrap();
+        assert!(String::from_utf8_lossy(&event).contains("\"payload\":\"Zhafir\""));
+
+        auth.revoke(&claims).await.unwrap();
+        let response = get(format!("/live/sse?token={}", token)).await.unwrap();
+        assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+    }
+
+    #[tokio::test]
+    async fn test_live_routes_accept_query_token() {
+        let state = AppState::for_test();
+        let admin = User {
+            id: 1,
+            name: "Admin".to_string(),
+            email: "admin@example.com".to_string(),
+            password: String::new(),
+        };
+        let (token, _) = state.auth.issue(&admin, None).await.unwrap();
+        let server = TestServer::new(app(state)).unwrap();
+
+        for path in ["/live/sse", "/live/ws"] {
+            server.get(path).await.assert_status_unauthorized();
+        }
+        // Authenticated, Redis is not configured in tests.
+        server
+            .get(&format!("/live/sse?token={}", token))
+            .await
+            .assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        server
+            .get("/live/sse")
+            .authorization_bearer(&token)
+            .await
+            .assert_status(StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
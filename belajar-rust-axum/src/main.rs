@@ -13,25 +13,381 @@ SETUP
 - Selanjutnya, kita bisa menjalankan aplikasi Axum menggunakan method serve
 */
 
+mod analytics;
+mod auth;
+mod error;
+mod extract;
+mod handler;
+mod live;
+mod model;
+mod rate_limit;
+mod settings;
+mod state;
+mod validation;
+
+#[cfg(test)]
 fn init_logging() {
-    env_logger::init();
+    let _ = env_logger::builder().is_test(true).try_init();
 }
 
+use analytics::VisitorRecorder;
+#[cfg(test)]
+use axum::extract::Request;
 use axum::{
     Router,
-    extract::Request,
-    routing::{get, post},
+    handler::Handler,
+    middleware,
+    routing::{delete, get, post, put},
     serve,
 };
+#[cfg(test)]
 use axum_test::TestServer;
+use belajar_rust_database::{
+    migrate::{self, MIGRATOR, MigrateCommand},
+    repository::{MySqlSellerRepository, MySqlTransactionRepository},
+};
+use belajar_rust_redis::{
+    cache::RedisCache,
+    connection::RedisConnection,
+    event::Producer,
+    geo::SellerGeoIndex,
+    pubsub::{Subscriber, SubscriberOptions},
+    ranking::SalesRanking,
+    visitors::VisitorCounter,
+};
+use chrono::Utc;
+#[cfg(test)]
 use log::debug;
-use tokio::net::TcpListener;
+use log::{info, warn};
+use rate_limit::RateLimitKey;
+use settings::{RedisSettings, Settings};
+use sqlx::MySqlPool;
+use state::{AppState, Redis};
+use std::{net::SocketAddr, time::Duration};
+use tokio::{net::TcpListener, signal};
+
+/*
+MINIPOS
+- Router MiniPOS memetakan endpoint pada koleksi Postman `belajar-rust-database/test.json`
+- Satu path bisa memiliki beberapa method sekaligus, misal `get(list).post(create)`
+- Path parameter ditulis menggunakan kurung kurawal, misal `/users/{id}`
+- Endpoint yang membutuhkan login dipasang di router `protected`, yang dibungkus middleware `require_auth`
+menggunakan `route_layer`, sehingga request tanpa token valid langsung ditolak dengan status 401
+- Login dan checkout dibungkus `RateLimitLayer`, lihat module `rate_limit`
+- `/live/ws` dan `/live/sse` meneruskan pesan Redis Pub/Sub ke dashboard, lihat module `live`,
+keduanya ada di router `live` yang juga menerima token dari query `?token=`, karena browser tidak bisa mengirim header
+`Authorization` saat membuka WebSocket atau EventSource
+- `/imports/{table}` menerima file CSV di body request, dengan `?dry_run=true` semua baris hanya divalidasi tanpa disimpan
+- `/sellers/nearby` mencari seller terdekat menggunakan index Redis Geo
+- Semua route dibungkus middleware `count_visitors` untuk menghitung pengunjung unik, di router `protected` middleware ini
+dipasang sebelum `require_auth`, sehingga berjalan setelah nya dan request yang ditolak tidak ikut dihitung
+*/
+
+fn app(state: AppState) -> Router {
+    let public = Router::new()
+        .route("/", get(|| async { "Hello, World!" }))
+        .route("/health", get(handler::health))
+        .route(
+            "/auth/login",
+            post(auth::login).layer(state.rate_limit(
+                "login",
+                &state.rate_limits.login,
+                RateLimitKey::ClientIp,
+            )),
+        )
+        .route("/products", get(handler::list_products))
+        .route("/products/{id}", get(handler::get_product))
+        .route("/sellers/nearby", get(handler::nearby_sellers))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            analytics::count_visitors,
+        ));
+
+    let protected = Router::new()
+        .route("/auth/logout", post(auth::logout))
+        .route(
+            "/auth/sessions",
+            get(auth::list_sessions).delete(auth::revoke_all_sessions),
+        )
+        .route("/auth/sessions/{id}", delete(auth::revoke_session))
+        .route(
+            "/users",
+            get(handler::list_users).post(handler::create_user),
+        )
+        .route(
+            "/users/{id}",
+            get(handler::get_user)
+                .put(handler::update_user)
+                .delete(handler::delete_user),
+        )
+        .route("/products", post(handler::create_product))
+        .route(
+            "/products/{id}",
+            put(handler::update_product).delete(handler::delete_product),
+        )
+        .route(
+            "/transactions",
+            get(handler::list_transactions).post(handler::create_transaction.layer(
+                state.rate_limit("checkout", &state.rate_limits.checkout, RateLimitKey::User),
+            )),
+        )
+        .route("/transactions/{id}", get(handler::get_transaction))
+        .route("/exports/{table}", get(handler::export))
+        .route("/imports/{table}", post(handler::import))
+        .route("/analytics/visitors", get(analytics::visitors))
+        .route("/analytics/visitors/daily", get(analytics::daily_visitors))
+        .route("/analytics/rankings/{board}", get(analytics::top_products))
+        .route(
+            "/analytics/rankings/{board}/products/{id}",
+            get(analytics::product_rank),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            analytics::count_visitors,
+        ))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth::require_auth,
+        ));
+
+    let live = Router::new()
+        .route("/live/ws", get(live::websocket))
+        .route("/live/sse", get(live::sse))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            analytics::count_visitors,
+        ))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth::require_auth_or_query_token,
+        ));
+
+    public.merge(protected).merge(live).with_state(state)
+}
+
+/*
+GRACEFUL SHUTDOWN
+- Saat aplikasi dihentikan (SIGINT dari Ctrl+C atau SIGTERM dari sistem operasi), server berhenti menerima koneksi baru
+dan menunggu request yang sedang berjalan selesai
+- Setelah server berhenti, Database Pool ditutup menggunakan `pool.close()`, sehingga semua koneksi dikembalikan dengan benar
+*/
+
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+    info!("Shutdown signal received");
+}
+
+/*
+MIGRATION
+- Sebelum server berjalan, aplikasi memeriksa apakah skema database sesuai dengan migration yang ada di binary
+- Jika terjadi drift, aplikasi menolak berjalan, kecuali `database.allow_schema_drift` bernilai true
+- Setelah itu, migration yang belum dijalankan akan dijalankan jika `database.migrate_on_startup` bernilai true
+- Migration juga bisa dijalankan manual menggunakan subcommand, misal `belajar-rust-axum migrate up --target-version <version>`
+*/
+
+async fn migrate_on_startup(settings: &Settings, pool: &MySqlPool) -> anyhow::Result<()> {
+    let mut connection = pool.acquire().await?;
+    if let Err(error) = migrate::check_drift(&mut *connection, &MIGRATOR).await {
+        if !settings.database.allow_schema_drift {
+            anyhow::bail!("database schema drift detected: {}", error);
+        }
+        warn!("Database schema drift detected: {}", error);
+    }
+
+    if settings.database.migrate_on_startup {
+        for version in migrate::up(&mut *connection, &MIGRATOR, None).await? {
+            info!("Applied migration {}", version);
+        }
+    }
+    Ok(())
+}
+
+/*
+REDIS
+- Jika section `redis` ada di konfigurasi, aplikasi membuat producer untuk domain event
+dan relay Pub/Sub yang berjalan di background task selama aplikasi hidup
+- Semua komponen Redis berbagi satu `RedisConnection`, yang membuat koneksi ulang sendiri jika terputus atau terjadi failover,
+dan dicek secara berkala oleh health check di background task setiap `redis.connection.health_check_secs`
+- Relay membuat koneksi ulang sendiri jika koneksi Pub/Sub ke Redis terputus
+- Product dan seller yang dibaca berdasarkan id disimpan di cache Redis selama `redis.cache_ttl_secs`,
+perubahan dari aplikasi lain atau dari import CSV baru terlihat setelah cache nya kadaluarsa
+- Index lokasi seller bisa dibangun ulang dari database menggunakan subcommand `belajar-rust-axum geo rebuild`,
+misal setelah Redis di-flush atau setelah Redis mati saat seller diubah,
+begitu juga papan peringkat penjualan menggunakan subcommand `belajar-rust-axum ranking rebuild`
+- Kunjungan dicatat ke Redis oleh satu worker di background task, antrian nya dibatasi `redis.visitors_queue_capacity`
+- Hitungan pengunjung unik harian disimpan ke database oleh job di background task setiap `redis.visitors_persist_secs`
+*/
+
+async fn connect_redis(settings: &RedisSettings) -> anyhow::Result<(Redis, Subscriber)> {
+    let connection = RedisConnection::connect(&settings.connection).await?;
+    info!(
+        "Publishing events to Redis stream {}",
+        settings.events_stream
+    );
+    let events = Producer::new(
+        connection.clone(),
+        &settings.events_stream,
+        settings.events_maxlen,
+    );
+
+    let channels: Vec<&str> = settings.live_channels.iter().map(String::as_str).collect();
+    let patterns: Vec<&str> = settings.live_patterns.iter().map(String::as_str).collect();
+    let subscriber = Subscriber::new(
+        connection.clone(),
+        SubscriberOptions::new(&channels, &patterns),
+    );
+    let live = subscriber.sender();
+    let cache = RedisCache::new(
+        connection.clone(),
+        "minipos:cache",
+        Duration::from_secs(settings.cache_ttl_secs),
+    );
+    let sellers = SellerGeoIndex::new(connection.clone(), &settings.sellers_geo_key);
+    let sales = SalesRanking::new(connection.clone(), "minipos");
+    let visitors = VisitorCounter::new(
+        connection.clone(),
+        "minipos",
+        Duration::from_secs(settings.visitors_ttl_days * 24 * 60 * 60),
+    );
+    let visits = VisitorRecorder::spawn(visitors.clone(), settings.visitors_queue_capacity);
+    Ok((
+        Redis {
+            connection,
+            events,
+            live,
+            cache,
+            sellers,
+            sales,
+            visitors,
+            visits,
+        },
+        subscriber,
+    ))
+}
+
+async fn rebuild(settings: &Settings, target: &str) -> anyhow::Result<()> {
+    let Some(redis) = &settings.redis else {
+        anyhow::bail!("redis is not configured");
+    };
+    let pool = settings
+        .database
+        .pool_options()
+        .min_connections(0)
+        .connect(&settings.database.url)
+        .await?;
+    let (redis, _) = connect_redis(redis).await?;
+    if target == "geo" {
+        let indexed = redis
+            .sellers
+            .rebuild(&MySqlSellerRepository::new(pool.clone()), 500)
+            .await?;
+        info!("Indexed {} seller locations", indexed);
+    } else {
+        redis
+            .sales
+            .rebuild(&MySqlTransactionRepository::new(pool.clone()), Utc::now())
+            .await?;
+        info!("Rebuilt sales rankings");
+    }
+    pool.close().await;
+    Ok(())
+}
 
 #[tokio::main]
-async fn main() {
-    let app = Router::new().route("/", get(|| async { "Hello, World!" }));
-    let listener = TcpListener::bind("0.0.0.0:3000").await.unwrap();
-    serve(listener, app).await.unwrap();
+async fn main() -> anyhow::Result<()> {
+    env_logger::init();
+
+    let settings = Settings::load()?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("migrate") {
+        let command = MigrateCommand::parse(&args[1..]).map_err(anyhow::Error::msg)?;
+        let pool = settings
+            .database
+            .pool_options()
+            .min_connections(0)
+            .connect(&settings.database.url)
+            .await?;
+        command
+            .execute(&mut *pool.acquire().await?, &MIGRATOR)
+            .await?;
+        pool.close().await;
+        return Ok(());
+    }
+    if let Some(target @ ("geo" | "ranking")) = args.first().map(String::as_str) {
+        if args.get(1).map(String::as_str) != Some("rebuild") {
+            anyhow::bail!("usage: {} rebuild", target);
+        }
+        return rebuild(&settings, target).await;
+    }
+    settings.auth.validate()?;
+
+    let pool = settings
+        .database
+        .pool_options()
+        .connect(&settings.database.url)
+        .await?;
+    migrate_on_startup(&settings, &pool).await?;
+    let redis = match &settings.redis {
+        Some(redis) => {
+            let (redis, subscriber) = connect_redis(redis).await?;
+            tokio::spawn(subscriber.run());
+            tokio::spawn(
+                redis
+                    .connection
+                    .clone()
+                    .health_check(redis.connection.settings().health_check_interval()),
+            );
+            Some(redis)
+        }
+        None => None,
+    };
+    let state = AppState::new(&settings, pool.clone(), redis);
+    if let (Some(email), Some(password)) =
+        (&settings.auth.admin_email, &settings.auth.admin_password)
+    {
+        let admin = auth::seed_admin(&*state.users, "Admin", email, password).await?;
+        info!("Admin account {} is ready", admin.email);
+    }
+    if let (Some(redis), Some(redis_settings)) = (&state.redis, &settings.redis) {
+        tokio::spawn(analytics::persist_visitors(
+            redis.visitors.clone(),
+            state.visitors.clone(),
+            Duration::from_secs(redis_settings.visitors_persist_secs),
+        ));
+    }
+
+    let listener = TcpListener::bind(settings.address()).await?;
+    info!("Listening on {}", listener.local_addr()?);
+    serve(
+        listener,
+        app(state).into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown_signal())
+    .await?;
+
+    pool.close().await;
+    info!("Database pool closed");
+    Ok(())
 }
 
 /*
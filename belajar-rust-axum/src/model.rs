@@ -0,0 +1,284 @@
+use belajar_rust_database::model as entity;
+use belajar_rust_redis::{geo::DistanceUnit, visitors::Period};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::validation::TransactionRules;
+
+/*
+MODEL
+- Model MiniPOS mengikuti koleksi Postman di `belajar-rust-database/test.json`
+- Struct dengan akhiran Request adalah body yang dikirim client, sedangkan struct lainnya adalah response
+- Struct Request divalidasi menggunakan derive Validate sebelum diproses oleh handler
+- User, Product dan Transaction dibaca dari database, entity nya diubah menjadi struct response menggunakan `From`
+*/
+
+#[derive(Debug, Clone, Serialize)]
+pub struct User {
+    pub id: u64,
+    pub name: String,
+    pub email: String,
+    #[serde(skip_serializing)]
+    pub password: String,
+}
+
+impl From<entity::User> for User {
+    fn from(user: entity::User) -> Self {
+        User {
+            id: user.id.into(),
+            name: user.name,
+            email: user.email,
+            password: user.password,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateUserRequest {
+    #[validate(length(
+        min = 1,
+        max = 100,
+        message = "Name must be between 1 and 100 characters"
+    ))]
+    pub name: String,
+    #[validate(email(message = "Email must be a valid email address"))]
+    pub email: String,
+    #[validate(length(min = 8, message = "Password must be at least 8 characters"))]
+    pub password: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateUserRequest {
+    #[validate(length(
+        min = 1,
+        max = 100,
+        message = "Name must be between 1 and 100 characters"
+    ))]
+    pub name: Option<String>,
+    #[validate(email(message = "Email must be a valid email address"))]
+    pub email: Option<String>,
+    #[validate(length(min = 8, message = "Password must be at least 8 characters"))]
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Product {
+    pub id: u64,
+    pub name: String,
+    pub description: String,
+    pub price: u64,
+    pub stock: u32,
+    pub version: u32,
+}
+
+impl From<entity::Product> for Product {
+    fn from(product: entity::Product) -> Self {
+        Product {
+            id: product.id.into(),
+            name: product.name,
+            description: product.description,
+            price: product.price,
+            stock: product.stock,
+            version: product.version,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateProductRequest {
+    #[validate(length(
+        min = 1,
+        max = 255,
+        message = "Name must be between 1 and 255 characters"
+    ))]
+    pub name: String,
+    pub description: String,
+    #[validate(range(min = 1, message = "Price must be at least 1"))]
+    pub price: u64,
+    pub stock: u32,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateProductRequest {
+    #[validate(length(
+        min = 1,
+        max = 255,
+        message = "Name must be between 1 and 255 characters"
+    ))]
+    pub name: Option<String>,
+    pub description: Option<String>,
+    #[validate(range(min = 1, message = "Price must be at least 1"))]
+    pub price: Option<u64>,
+    pub stock: Option<u32>,
+    /// Version the client read, the update is rejected with 409 when the
+    /// product has changed since. Without it the latest version is updated.
+    pub version: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Transaction {
+    pub id: u64,
+    pub items: Vec<TransactionItem>,
+    pub total: u64,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<entity::Transaction> for Transaction {
+    fn from(transaction: entity::Transaction) -> Self {
+        Transaction {
+            id: transaction.id.into(),
+            items: transaction.items.into_iter().map(Into::into).collect(),
+            total: transaction.total,
+            created_at: transaction.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionItem {
+    pub product_id: u64,
+    pub quantity: u32,
+    pub price: u64,
+    pub subtotal: u64,
+}
+
+impl From<entity::TransactionItem> for TransactionItem {
+    fn from(item: entity::TransactionItem) -> Self {
+        TransactionItem {
+            product_id: item.product_id.into(),
+            quantity: item.quantity,
+            price: item.price,
+            subtotal: item.subtotal,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+#[validate(
+    context = TransactionRules,
+    schema(
+        function = "crate::validation::within_transaction_rules",
+        use_context
+    )
+)]
+pub struct CreateTransactionRequest {
+    #[validate(nested, length(min = 1, message = "Items must not be empty"))]
+    pub items: Vec<TransactionItemRequest>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionItemRequest {
+    pub product_id: u64,
+    #[validate(range(min = 1, message = "Quantity must be at least 1"))]
+    pub quantity: u32,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct NearbySellersQuery {
+    #[validate(range(
+        min = -85.05112878,
+        max = 85.05112878,
+        message = "Latitude must be between -85.05112878 and 85.05112878"
+    ))]
+    pub lat: f64,
+    #[validate(range(
+        min = -180.0,
+        max = 180.0,
+        message = "Longitude must be between -180 and 180"
+    ))]
+    pub lon: f64,
+    #[validate(range(exclusive_min = 0.0, message = "Radius must be greater than 0"))]
+    pub radius: f64,
+    /// `m`, `km` (default), `mi` or `ft`.
+    #[serde(default)]
+    pub unit: DistanceUnit,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NearbySeller {
+    pub id: u64,
+    pub name: String,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    /// Distance from the query point, in the unit of the query.
+    pub distance: f64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VisitorsQuery {
+    /// `day` (default), `week` or `month`.
+    #[serde(default)]
+    pub period: Period,
+    /// Any day of the period, today when missing.
+    pub date: Option<NaiveDate>,
+    /// For example `GET /products`, every route when missing.
+    pub route: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VisitorCount {
+    pub period: Period,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub route: Option<String>,
+    pub visitors: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DailyVisitorsQuery {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub route: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyVisitors {
+    pub date: NaiveDate,
+    pub visitors: u64,
+}
+
+impl From<entity::DailyVisitors> for DailyVisitors {
+    fn from(count: entity::DailyVisitors) -> Self {
+        DailyVisitors {
+            date: count.date,
+            visitors: count.visitors,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RankingQuery {
+    /// Products on the board, 10 by default and at most 100.
+    #[serde(default = "RankingQuery::default_limit")]
+    pub limit: usize,
+    /// Any day of the board, today when missing.
+    pub date: Option<NaiveDate>,
+}
+
+impl RankingQuery {
+    pub const MAX_LIMIT: usize = 100;
+
+    fn default_limit() -> usize {
+        10
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RankQuery {
+    /// Any day of the board, today when missing.
+    pub date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProductRank {
+    pub rank: u64,
+    pub product_id: u64,
+    /// `None` when the product has been deleted.
+    pub name: Option<String>,
+    pub units_sold: u64,
+}
@@ -0,0 +1,336 @@
+use std::{
+    convert::Infallible,
+    net::SocketAddr,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use axum::{
+    extract::{ConnectInfo, Request},
+    http::{HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use belajar_rust_redis::rate_limit::{RateLimiter, Rule};
+use futures::future::BoxFuture;
+use log::warn;
+use serde::Deserialize;
+use tower::{Layer, Service};
+
+use crate::{auth::Claims, error::AppError};
+
+/*
+RATE LIMIT
+- Rate limit dipasang sebagai tower Layer di route tertentu, misal login dan checkout
+- Key nya dibuat dari scope dan client IP atau user ID, misal `login:127.0.0.1` atau `checkout:user:1`
+- Jika Redis dikonfigurasi, hitungan disimpan di Redis, sehingga batas nya berlaku untuk semua instance aplikasi,
+jika tidak, hitungan disimpan di memory masing - masing instance
+- Login dibatasi per client IP untuk mencegah brute force password,
+checkout dibatasi per user untuk mencegah transaksi terkirim dua kali karena tombol ditekan berulang
+- Request yang melebihi batas ditolak dengan status 429 dan header `Retry-After`
+- Jika Redis error, request tetap dilanjutkan (fail open), agar Redis yang mati tidak membuat login dan checkout ikut mati
+- `X-Forwarded-For` hanya dipercaya jika `rate_limit.trust_forwarded_for` bernilai true,
+yaitu jika aplikasi berjalan di belakang reverse proxy, karena header ini bisa diisi bebas oleh client
+- Reverse proxy menambahkan alamat client di sebelah kanan header, sedangkan isi di sebelah kiri berasal dari client,
+sehingga yang dipakai adalah alamat paling kanan, client yang mengirim `X-Forwarded-For` palsu tetap mendapat key yang sama
+*/
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct RateLimitRule {
+    pub limit: u64,
+    pub window_secs: u64,
+}
+
+impl RateLimitRule {
+    pub fn rule(&self) -> Rule {
+        Rule {
+            limit: self.limit,
+            window: Duration::from_secs(self.window_secs),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RateLimitSettings {
+    pub login: RateLimitRule,
+    pub checkout: RateLimitRule,
+    pub trust_forwarded_for: bool,
+}
+
+impl Default for RateLimitSettings {
+    fn default() -> Self {
+        RateLimitSettings {
+            login: RateLimitRule {
+                limit: 5,
+                window_secs: 60,
+            },
+            checkout: RateLimitRule {
+                limit: 1,
+                window_secs: 2,
+            },
+            trust_forwarded_for: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitKey {
+    ClientIp,
+    /// The authenticated user, or the client IP when there is no user.
+    User,
+}
+
+#[derive(Clone)]
+pub struct RateLimitLayer {
+    limiter: Arc<dyn RateLimiter>,
+    scope: &'static str,
+    rule: Rule,
+    key: RateLimitKey,
+    trust_forwarded_for: bool,
+}
+
+impl RateLimitLayer {
+    pub fn new(
+        limiter: Arc<dyn RateLimiter>,
+        scope: &'static str,
+        rule: Rule,
+        key: RateLimitKey,
+    ) -> Self {
+        RateLimitLayer {
+            limiter,
+            scope,
+            rule,
+            key,
+            trust_forwarded_for: false,
+        }
+    }
+
+    pub fn trust_forwarded_for(mut self, trust: bool) -> Self {
+        self.trust_forwarded_for = trust;
+        self
+    }
+
+    fn key_for(&self, request: &Request) -> String {
+        let user = match self.key {
+            RateLimitKey::User => request.extensions().get::<Claims>(),
+            RateLimitKey::ClientIp => None,
+        };
+        match user {
+            Some(claims) => format!("{}:user:{}", self.scope, claims.sub),
+            None => format!(
+                "{}:{}",
+                self.scope,
+                client_ip(request, self.trust_forwarded_for)
+            ),
+        }
+    }
+}
+
+/// The client address, or the last `X-Forwarded-For` address when
+/// `trust_forwarded_for` is set and the header is present. The last address
+/// is the one added by the reverse proxy, the ones before it can be spoofed.
+pub fn client_ip(request: &Request, trust_forwarded_for: bool) -> String {
+    let forwarded = trust_forwarded_for
+        .then(|| {
+            request
+                .headers()
+                .get_all("x-forwarded-for")
+                .iter()
+                .next_back()
+        })
+        .flatten()
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.rsplit(',').next())
+        .map(|ip| ip.trim().to_string())
+        .filter(|ip| !ip.is_empty());
+    forwarded
+        .or_else(|| {
+            request
+                .extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(address)| address.ip().to_string())
+        })
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimitService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimitService {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RateLimitService<S> {
+    inner: S,
+    layer: RateLimitLayer,
+}
+
+impl<S> Service<Request> for RateLimitService<S>
+where
+    S: Service<Request, Response = Response, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = BoxFuture<'static, Result<Response, Infallible>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        // The clone is not ready yet, so the service that was polled is used for this request.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let layer = self.layer.clone();
+        Box::pin(async move {
+            let key = layer.key_for(&request);
+            match layer.limiter.check(&key, &layer.rule).await {
+                Ok(limit) if !limit.allowed => return Ok(too_many_requests(limit.retry_after)),
+                Ok(_) => {}
+                Err(error) => warn!("Rate limiter unavailable, allowing {}: {}", key, error),
+            }
+            inner.call(request).await
+        })
+    }
+}
+
+fn too_many_requests(retry_after: Duration) -> Response {
+    let seconds = retry_after.as_millis().div_ceil(1000).max(1);
+    let mut response = AppError::new(
+        StatusCode::TOO_MANY_REQUESTS,
+        "too_many_requests",
+        format!("too many requests, retry in {} seconds", seconds),
+    )
+    .into_response();
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, HeaderValue::from(seconds as u64));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, sync::Arc};
+
+    use axum::{
+        Router,
+        body::Body,
+        extract::{ConnectInfo, Request},
+        http::{StatusCode, header},
+        routing::post,
+    };
+    use axum_test::TestServer;
+    use belajar_rust_database::testing::TestDatabase;
+    use belajar_rust_redis::rate_limit::MemoryRateLimiter;
+    use serde_json::json;
+
+    use super::{RateLimitKey, RateLimitLayer, RateLimitRule};
+    use crate::{app, auth::Claims, error::ErrorBody, state::AppState};
+
+    fn layer(key: RateLimitKey) -> RateLimitLayer {
+        let rule = RateLimitRule {
+            limit: 1,
+            window_secs: 60,
+        };
+        RateLimitLayer::new(
+            Arc::new(MemoryRateLimiter::default()),
+            "test",
+            rule.rule(),
+            key,
+        )
+    }
+
+    fn request(forwarded_for: Option<&str>, user: Option<u64>) -> Request {
+        let mut request = Request::builder().uri("/");
+        if let Some(forwarded_for) = forwarded_for {
+            request = request.header("x-forwarded-for", forwarded_for);
+        }
+        let mut request = request.body(Body::empty()).unwrap();
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(SocketAddr::from(([10, 0, 0, 1], 4000))));
+        if let Some(sub) = user {
+            request.extensions_mut().insert(Claims {
+                sub,
+                email: "admin@example.com".to_string(),
+                jti: "jti".to_string(),
+                iat: 0,
+                exp: 0,
+            });
+        }
+        request
+    }
+
+    #[test]
+    fn test_key_for() {
+        let by_ip = layer(RateLimitKey::ClientIp);
+        assert_eq!("test:10.0.0.1", by_ip.key_for(&request(None, Some(1))));
+        assert_eq!(
+            "test:10.0.0.1",
+            by_ip.key_for(&request(Some("192.168.1.1"), None))
+        );
+        let by_ip = by_ip.trust_forwarded_for(true);
+        assert_eq!(
+            "test:192.168.1.1",
+            by_ip.key_for(&request(Some("192.168.1.1"), None))
+        );
+        // A spoofed leftmost address does not change the key.
+        for spoofed in ["1.2.3.4, 192.168.1.1", "5.6.7.8,192.168.1.1"] {
+            assert_eq!(
+                "test:192.168.1.1",
+                by_ip.key_for(&request(Some(spoofed), None))
+            );
+        }
+
+        let by_user = layer(RateLimitKey::User);
+        assert_eq!("test:user:7", by_user.key_for(&request(None, Some(7))));
+        assert_eq!("test:10.0.0.1", by_user.key_for(&request(None, None)));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_layer() {
+        let router = Router::new().route(
+            "/checkout",
+            post(|| async { "OK" }).layer(layer(RateLimitKey::ClientIp)),
+        );
+        let server = TestServer::new(router).unwrap();
+
+        server.post("/checkout").await.assert_status_ok();
+        let response = server.post("/checkout").await;
+        response.assert_status(StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!("60", response.header(header::RETRY_AFTER));
+        let body: ErrorBody = response.json();
+        assert_eq!("too_many_requests", body.code);
+    }
+
+    #[tokio::test]
+    async fn test_login_rate_limit() {
+        let database = TestDatabase::sqlite().await.unwrap();
+        let mut state = AppState::for_database(&database);
+        state.rate_limits.login = RateLimitRule {
+            limit: 2,
+            window_secs: 60,
+        };
+        let server = TestServer::new(app(state)).unwrap();
+
+        for _ in 0..2 {
+            server
+                .post("/auth/login")
+                .json(&json!({ "email": "admin@example.com", "password": "wrong-password" }))
+                .await
+                .assert_status_unauthorized();
+        }
+        server
+            .post("/auth/login")
+            .json(&json!({ "email": "admin@example.com", "password": "password123" }))
+            .await
+            .assert_status(StatusCode::TOO_MANY_REQUESTS);
+    }
+}
@@ -0,0 +1,269 @@
+use std::time::Duration;
+
+use belajar_rust_redis::connection::ConnectionSettings;
+use config::{Config, ConfigError, Environment, File, FileFormat};
+use serde::Deserialize;
+use sqlx::mysql::MySqlPoolOptions;
+
+use crate::{rate_limit::RateLimitSettings, validation::TransactionRules};
+
+/*
+SETTINGS
+- Konfigurasi aplikasi dibaca menggunakan library Config, sama seperti di project belajar-rust-config
+- Sumber pertama adalah file `application.yaml`, lalu ditimpa oleh environment variable dengan prefix `APP`
+- Nested key menggunakan pemisah `__`, misal `APP_DATABASE__URL` untuk `database.url`
+- `database.migrate_on_startup` menjalankan embedded migration saat aplikasi start,
+dan `database.allow_schema_drift` mengizinkan aplikasi tetap start walaupun skema database tidak sesuai
+- Section `redis` bersifat opsional, jika tidak ada, aplikasi berjalan tanpa mengirim domain event
+dan tanpa relay Pub/Sub ke dashboard, pencarian seller terdekat juga tidak tersedia
+- Secret tidak disimpan di `application.yaml`, `auth.jwt_secret` wajib diisi dari environment variable `APP_AUTH__JWT_SECRET`,
+begitu juga `APP_AUTH__ADMIN_PASSWORD` jika akun admin ingin dibuat saat start
+- Aplikasi menolak start jika `auth.jwt_secret` kosong atau masih berisi contoh seperti `change-me`,
+karena siapa pun yang mengetahui secret tersebut bisa membuat token sendiri
+- Pemeriksaan ini hanya dilakukan saat server dijalankan, subcommand `migrate`, `geo rebuild` dan `ranking rebuild`
+tidak memakai JWT, sehingga tetap bisa dijalankan tanpa secret
+- `redis.connection` memilih topology (`standalone`, `sentinel` atau `cluster`), alamat server, timeout dan backoff,
+lihat `belajar_rust_redis::connection`
+*/
+
+#[derive(Debug, Deserialize)]
+pub struct Settings {
+    pub server: ServerSettings,
+    pub database: DatabaseSettings,
+    pub auth: AuthSettings,
+    #[serde(default)]
+    pub transaction: TransactionRules,
+    pub redis: Option<RedisSettings>,
+    #[serde(default)]
+    pub rate_limit: RateLimitSettings,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ServerSettings {
+    pub host: String,
+    pub port: u16,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DatabaseSettings {
+    pub url: String,
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub acquire_timeout_secs: u64,
+    pub idle_timeout_secs: u64,
+    #[serde(default = "default_true")]
+    pub migrate_on_startup: bool,
+    #[serde(default)]
+    pub allow_schema_drift: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuthSettings {
+    /// Required, from `APP_AUTH__JWT_SECRET`.
+    #[serde(default)]
+    pub jwt_secret: String,
+    pub token_ttl_minutes: i64,
+    pub admin_email: Option<String>,
+    pub admin_password: Option<String>,
+}
+
+impl AuthSettings {
+    /// Example values from documentation and old config files.
+    const PLACEHOLDERS: [&str; 7] = [
+        "change-me",
+        "changeme",
+        "secret",
+        "jwt-secret",
+        "password",
+        "password123",
+        "admin",
+    ];
+
+    fn is_placeholder(value: &str) -> bool {
+        let value = value.trim();
+        value.is_empty()
+            || Self::PLACEHOLDERS
+                .iter()
+                .any(|placeholder| value.eq_ignore_ascii_case(placeholder))
+    }
+
+    /// Rejects an empty or example JWT secret and admin password.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if Self::is_placeholder(&self.jwt_secret) {
+            return Err(ConfigError::Message(
+                "auth.jwt_secret is empty or a placeholder, set APP_AUTH__JWT_SECRET".to_string(),
+            ));
+        }
+        if self
+            .admin_password
+            .as_deref()
+            .is_some_and(Self::is_placeholder)
+        {
+            return Err(ConfigError::Message(
+                "auth.admin_password is empty or a placeholder, set APP_AUTH__ADMIN_PASSWORD"
+                    .to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RedisSettings {
+    #[serde(default)]
+    pub connection: ConnectionSettings,
+    #[serde(default = "default_events_stream")]
+    pub events_stream: String,
+    #[serde(default = "default_events_maxlen")]
+    pub events_maxlen: usize,
+    /// Pub/Sub channels relayed to dashboards.
+    #[serde(default = "default_live_channels")]
+    pub live_channels: Vec<String>,
+    #[serde(default)]
+    pub live_patterns: Vec<String>,
+    /// Geo set with the location of every seller.
+    #[serde(default = "default_sellers_geo_key")]
+    pub sellers_geo_key: String,
+    /// How long daily unique visitor counts stay in Redis.
+    #[serde(default = "default_visitors_ttl_days")]
+    pub visitors_ttl_days: u64,
+    /// How often daily unique visitor counts are saved to the database.
+    #[serde(default = "default_visitors_persist_secs")]
+    pub visitors_persist_secs: u64,
+    /// How long products and sellers stay in the read through cache.
+    #[serde(default = "default_cache_ttl_secs")]
+    pub cache_ttl_secs: u64,
+    /// How many visits may wait to be recorded, more are dropped.
+    #[serde(default = "default_visitors_queue_capacity")]
+    pub visitors_queue_capacity: usize,
+}
+
+fn default_events_stream() -> String {
+    "minipos:events".to_string()
+}
+
+fn default_events_maxlen() -> usize {
+    10_000
+}
+
+fn default_live_channels() -> Vec<String> {
+    vec!["minipos:live".to_string()]
+}
+
+fn default_sellers_geo_key() -> String {
+    "minipos:sellers:geo".to_string()
+}
+
+fn default_cache_ttl_secs() -> u64 {
+    300
+}
+
+fn default_visitors_ttl_days() -> u64 {
+    35
+}
+
+fn default_visitors_persist_secs() -> u64 {
+    3600
+}
+
+fn default_visitors_queue_capacity() -> usize {
+    1024
+}
+
+impl Settings {
+    pub fn load() -> Result<Self, ConfigError> {
+        let settings: Settings = Config::builder()
+            .add_source(File::new("application", FileFormat::Yaml).required(false))
+            .add_source(Environment::with_prefix("APP").separator("__"))
+            .build()?
+            .try_deserialize()?;
+        Ok(settings)
+    }
+
+    pub fn address(&self) -> String {
+        format!("{}:{}", self.server.host, self.server.port)
+    }
+}
+
+impl DatabaseSettings {
+    pub fn pool_options(&self) -> MySqlPoolOptions {
+        MySqlPoolOptions::new()
+            .max_connections(self.max_connections)
+            .min_connections(self.min_connections)
+            .acquire_timeout(Duration::from_secs(self.acquire_timeout_secs))
+            .idle_timeout(Duration::from_secs(self.idle_timeout_secs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use belajar_rust_redis::connection::Topology;
+    use config::{Config, File, FileFormat};
+
+    use super::{AuthSettings, Settings};
+
+    fn auth(jwt_secret: &str, admin_password: Option<&str>) -> AuthSettings {
+        AuthSettings {
+            jwt_secret: jwt_secret.to_string(),
+            token_ttl_minutes: 60,
+            admin_email: Some("admin@example.com".to_string()),
+            admin_password: admin_password.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_settings_from_yaml() {
+        let settings: Settings = Config::builder()
+            .add_source(File::new("application.yaml", FileFormat::Yaml))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+
+        assert_eq!("0.0.0.0:3000", settings.address());
+        assert_eq!(10, settings.database.max_connections);
+        assert!(settings.database.migrate_on_startup);
+        assert!(!settings.database.allow_schema_drift);
+        assert_eq!(60, settings.auth.token_ttl_minutes);
+        // Secrets only come from the environment.
+        assert_eq!("", settings.auth.jwt_secret);
+        assert_eq!(None, settings.auth.admin_password);
+        assert!(settings.auth.validate().is_err());
+        assert_eq!(50, settings.transaction.max_items);
+        let redis = settings.redis.unwrap();
+        assert_eq!(Topology::Standalone, redis.connection.topology);
+        assert_eq!(vec!["redis://localhost:6379"], redis.connection.urls);
+        assert_eq!(1000, redis.connection.command_timeout_ms);
+        assert_eq!("minipos:events", redis.events_stream);
+        assert_eq!(10000, redis.events_maxlen);
+        assert_eq!(vec!["minipos:live"], redis.live_channels);
+        assert_eq!(vec!["minipos:stores:*"], redis.live_patterns);
+        assert_eq!("minipos:sellers:geo", redis.sellers_geo_key);
+        assert_eq!(300, redis.cache_ttl_secs);
+        assert_eq!(35, redis.visitors_ttl_days);
+        assert_eq!(1024, redis.visitors_queue_capacity);
+        assert_eq!(5, settings.rate_limit.login.limit);
+        assert_eq!(2, settings.rate_limit.checkout.window_secs);
+    }
+
+    #[test]
+    fn test_auth_settings_validate() {
+        let secret = "3f9c1e7a5b2d8c4e6f0a1b3c5d7e9f21";
+        assert!(auth(secret, None).validate().is_ok());
+        assert!(
+            auth(secret, Some("a-long-admin-password"))
+                .validate()
+                .is_ok()
+        );
+
+        for placeholder in ["", "  ", "change-me", "CHANGE-ME", "secret"] {
+            assert!(auth(placeholder, None).validate().is_err());
+        }
+        assert!(auth(secret, Some("password123")).validate().is_err());
+        assert!(auth(secret, Some("")).validate().is_err());
+    }
+}
@@ -0,0 +1,325 @@
+use std::sync::Arc;
+
+use axum::extract::FromRef;
+use belajar_rust_database::{
+    export::{Exporter, MySqlExporter},
+    import::{Importer, MySqlImporter},
+    repository::{
+        MySqlProductRepository, MySqlSellerRepository, MySqlTransactionRepository,
+        MySqlUserRepository, MySqlVisitorRepository, ProductRepository, SellerRepository,
+        TransactionRepository, UserRepository, VisitorRepository,
+    },
+};
+use belajar_rust_redis::{
+    cache::{Cached, RedisCache},
+    connection::RedisConnection,
+    event::Producer,
+    geo::{GeoIndexed, SellerGeoIndex},
+    pubsub::Message,
+    ranking::{Ranked, SalesRanking},
+    rate_limit::{MemoryRateLimiter, RateLimiter, RedisRateLimiter},
+    session::RedisSessionStore,
+    visitors::VisitorCounter,
+};
+use chrono::Duration;
+use sqlx::MySqlPool;
+use tokio::sync::broadcast;
+
+use crate::{
+    analytics::VisitorRecorder,
+    auth::Auth,
+    rate_limit::{RateLimitKey, RateLimitLayer, RateLimitRule, RateLimitSettings},
+    settings::Settings,
+    validation::TransactionRules,
+};
+
+/*
+STATE
+- AppState berisi semua object yang dibutuhkan handler, dan di-clone untuk tiap request
+- Dengan implementasi `FromRef`, handler cukup mengambil bagian yang dibutuhkan, misal `State<Auth>` atau `State<MySqlPool>`
+- MySqlPool sudah menggunakan `Arc` di dalamnya, sehingga clone pool tidak membuat koneksi baru
+- Exporter, importer dan repository disimpan sebagai `Arc<dyn ...>`, sehingga test bisa menggantinya dengan implementasi SQLite
+- `redis` bernilai `None` jika Redis tidak dikonfigurasi, berisi producer Redis Stream untuk domain event
+dan broadcast sender dari relay Pub/Sub untuk dashboard
+- Rate limiter dan session login disimpan di Redis jika tersedia, jika tidak, di memory
+- Jika Redis tersedia, repository product, seller dan transaksi dibungkus `Cached`, sehingga product dan seller dibaca dari cache,
+dan cache product yang dibeli dihapus saat checkout, statistik cache nya ditampilkan di `/health`
+- Repository seller juga dibungkus `GeoIndexed`, sehingga index lokasi seller ikut berubah,
+dan repository transaksi dibungkus `Ranked`, sehingga checkout yang berhasil masuk ke papan peringkat penjualan
+- Pengunjung unik dihitung di Redis, hitungan harian yang sudah disimpan dibaca dari repository `visitors`
+*/
+
+#[derive(Clone)]
+pub struct AppState {
+    pub pool: MySqlPool,
+    pub users: Arc<dyn UserRepository>,
+    pub products: Arc<dyn ProductRepository>,
+    pub sellers: Arc<dyn SellerRepository>,
+    pub transactions: Arc<dyn TransactionRepository>,
+    pub visitors: Arc<dyn VisitorRepository>,
+    pub exporter: Arc<dyn Exporter>,
+    pub importer: Arc<dyn Importer>,
+    pub auth: Auth,
+    pub transaction_rules: TransactionRules,
+    pub redis: Option<Redis>,
+    pub rate_limiter: Arc<dyn RateLimiter>,
+    pub rate_limits: RateLimitSettings,
+}
+
+#[derive(Clone)]
+pub struct Redis {
+    pub connection: RedisConnection,
+    pub events: Producer,
+    pub live: broadcast::Sender<Message>,
+    pub cache: RedisCache,
+    pub sellers: SellerGeoIndex,
+    pub sales: SalesRanking,
+    pub visitors: VisitorCounter,
+    pub visits: VisitorRecorder,
+}
+
+impl AppState {
+    pub fn new(settings: &Settings, pool: MySqlPool, redis: Option<Redis>) -> Self {
+        let rate_limiter: Arc<dyn RateLimiter> = match &redis {
+            Some(redis) => Arc::new(RedisRateLimiter::new(
+                redis.connection.clone(),
+                "minipos:rate",
+            )),
+            None => Arc::new(MemoryRateLimiter::default()),
+        };
+        let mut auth = Auth::new(
+            settings.auth.jwt_secret.as_bytes(),
+            Duration::minutes(settings.auth.token_ttl_minutes),
+        );
+        if let Some(redis) = &redis {
+            auth = auth.with_sessions(Arc::new(RedisSessionStore::new(
+                redis.connection.clone(),
+                "minipos",
+            )));
+        }
+
+        let products: Arc<dyn ProductRepository> = match &redis {
+            Some(redis) => Arc::new(Cached::new(
+                MySqlProductRepository::new(pool.clone()),
+                redis.cache.clone(),
+            )),
+            None => Arc::new(MySqlProductRepository::new(pool.clone())),
+        };
+        let sellers: Arc<dyn SellerRepository> = match &redis {
+            Some(redis) => Arc::new(GeoIndexed::new(
+                Cached::new(
+                    MySqlSellerRepository::new(pool.clone()),
+                    redis.cache.clone(),
+                ),
+                redis.sellers.clone(),
+            )),
+            None => Arc::new(MySqlSellerRepository::new(pool.clone())),
+        };
+        let transactions: Arc<dyn TransactionRepository> = match &redis {
+            Some(redis) => Arc::new(Ranked::new(
+                Cached::new(
+                    MySqlTransactionRepository::new(pool.clone()),
+                    redis.cache.clone(),
+                ),
+                redis.sales.clone(),
+            )),
+            None => Arc::new(MySqlTransactionRepository::new(pool.clone())),
+        };
+
+        AppState {
+            users: Arc::new(MySqlUserRepository::new(pool.clone())),
+            products,
+            sellers,
+            transactions,
+            visitors: Arc::new(MySqlVisitorRepository::new(pool.clone())),
+            exporter: Arc::new(MySqlExporter::new(pool.clone())),
+            importer: Arc::new(MySqlImporter::new(pool.clone())),
+            pool,
+            auth,
+            transaction_rules: settings.transaction.clone(),
+            redis,
+            rate_limiter,
+            rate_limits: settings.rate_limit.clone(),
+        }
+    }
+
+    pub fn rate_limit(
+        &self,
+        scope: &'static str,
+        rule: &RateLimitRule,
+        key: RateLimitKey,
+    ) -> RateLimitLayer {
+        RateLimitLayer::new(self.rate_limiter.clone(), scope, rule.rule(), key)
+            .trust_forwarded_for(self.rate_limits.trust_forwarded_for)
+    }
+}
+
+impl FromRef<AppState> for MySqlPool {
+    fn from_ref(state: &AppState) -> Self {
+        state.pool.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<dyn UserRepository> {
+    fn from_ref(state: &AppState) -> Self {
+        state.users.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<dyn ProductRepository> {
+    fn from_ref(state: &AppState) -> Self {
+        state.products.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<dyn SellerRepository> {
+    fn from_ref(state: &AppState) -> Self {
+        state.sellers.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<dyn TransactionRepository> {
+    fn from_ref(state: &AppState) -> Self {
+        state.transactions.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<dyn VisitorRepository> {
+    fn from_ref(state: &AppState) -> Self {
+        state.visitors.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<dyn Exporter> {
+    fn from_ref(state: &AppState) -> Self {
+        state.exporter.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<dyn Importer> {
+    fn from_ref(state: &AppState) -> Self {
+        state.importer.clone()
+    }
+}
+
+impl FromRef<AppState> for Auth {
+    fn from_ref(state: &AppState) -> Self {
+        state.auth.clone()
+    }
+}
+
+impl FromRef<AppState> for TransactionRules {
+    fn from_ref(state: &AppState) -> Self {
+        state.transaction_rules.clone()
+    }
+}
+
+impl FromRef<AppState> for Option<RedisConnection> {
+    fn from_ref(state: &AppState) -> Self {
+        state.redis.as_ref().map(|redis| redis.connection.clone())
+    }
+}
+
+impl FromRef<AppState> for Option<Producer> {
+    fn from_ref(state: &AppState) -> Self {
+        state.redis.as_ref().map(|redis| redis.events.clone())
+    }
+}
+
+impl FromRef<AppState> for Option<broadcast::Sender<Message>> {
+    fn from_ref(state: &AppState) -> Self {
+        state.redis.as_ref().map(|redis| redis.live.clone())
+    }
+}
+
+impl FromRef<AppState> for Option<RedisCache> {
+    fn from_ref(state: &AppState) -> Self {
+        state.redis.as_ref().map(|redis| redis.cache.clone())
+    }
+}
+
+impl FromRef<AppState> for Option<SellerGeoIndex> {
+    fn from_ref(state: &AppState) -> Self {
+        state.redis.as_ref().map(|redis| redis.sellers.clone())
+    }
+}
+
+impl FromRef<AppState> for Option<SalesRanking> {
+    fn from_ref(state: &AppState) -> Self {
+        state.redis.as_ref().map(|redis| redis.sales.clone())
+    }
+}
+
+impl FromRef<AppState> for Option<VisitorCounter> {
+    fn from_ref(state: &AppState) -> Self {
+        state.redis.as_ref().map(|redis| redis.visitors.clone())
+    }
+}
+
+#[cfg(test)]
+use belajar_rust_database::{
+    export::SqliteExporter,
+    import::SqliteImporter,
+    repository::{
+        SqliteProductRepository, SqliteSellerRepository, SqliteTransactionRepository,
+        SqliteUserRepository, SqliteVisitorRepository,
+    },
+    testing::TestDatabase,
+};
+#[cfg(test)]
+use sqlx::Sqlite;
+
+#[cfg(test)]
+impl AppState {
+    /// State with a lazy pool pointing to a closed port, so handlers that
+    /// never touch the database work without a running MySQL server.
+    pub fn for_test() -> Self {
+        let pool = sqlx::mysql::MySqlPoolOptions::new()
+            .acquire_timeout(std::time::Duration::from_secs(1))
+            .connect_lazy("mysql://root@127.0.0.1:1/minipos")
+            .unwrap();
+
+        AppState {
+            users: Arc::new(MySqlUserRepository::new(pool.clone())),
+            products: Arc::new(MySqlProductRepository::new(pool.clone())),
+            sellers: Arc::new(MySqlSellerRepository::new(pool.clone())),
+            transactions: Arc::new(MySqlTransactionRepository::new(pool.clone())),
+            visitors: Arc::new(MySqlVisitorRepository::new(pool.clone())),
+            exporter: Arc::new(MySqlExporter::new(pool.clone())),
+            importer: Arc::new(MySqlImporter::new(pool.clone())),
+            pool,
+            auth: Auth::new(b"secret", Duration::minutes(5)),
+            transaction_rules: TransactionRules::default(),
+            redis: None,
+            rate_limiter: Arc::new(MemoryRateLimiter::default()),
+            // Tests send many requests from the same client within a second.
+            rate_limits: RateLimitSettings {
+                login: RateLimitRule {
+                    limit: 1000,
+                    window_secs: 1,
+                },
+                checkout: RateLimitRule {
+                    limit: 1000,
+                    window_secs: 1,
+                },
+                trust_forwarded_for: false,
+            },
+        }
+    }
+
+    /// State whose repositories, exporter and importer use the given SQLite
+    /// database.
+    pub fn for_database(database: &TestDatabase<Sqlite>) -> Self {
+        let pool = database.pool().clone();
+        AppState {
+            users: Arc::new(SqliteUserRepository::new(pool.clone())),
+            products: Arc::new(SqliteProductRepository::new(pool.clone())),
+            sellers: Arc::new(SqliteSellerRepository::new(pool.clone())),
+            transactions: Arc::new(SqliteTransactionRepository::new(pool.clone())),
+            visitors: Arc::new(SqliteVisitorRepository::new(pool.clone())),
+            exporter: Arc::new(SqliteExporter::new(pool.clone())),
+            importer: Arc::new(SqliteImporter::new(pool)),
+            ..AppState::for_test()
+        }
+    }
+}
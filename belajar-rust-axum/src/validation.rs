@@ -0,0 +1,176 @@
+use std::{borrow::Cow, collections::BTreeMap};
+
+use serde::Deserialize;
+use validator::{ValidationError, ValidationErrors, ValidationErrorsKind};
+
+use crate::{error::FieldError, model::CreateTransactionRequest};
+
+/*
+VALIDATION
+- Request body divalidasi menggunakan library Validator, sama seperti di project belajar-rust-validation
+- ValidationErrors dari Validator berbentuk tree (Struct, List, dan Field), sehingga perlu diratakan menjadi
+map path field ke daftar error, misal `items[0].quantity`
+- Error di level Struct (schema) disimpan dengan key `__all__`, mengikuti penamaan dari Validator
+
+CONTEXT
+- Beberapa aturan validasi membutuhkan data dari luar request, misal batas jumlah item per transaksi
+- Data tersebut dikirim sebagai context menggunakan `validate_with_args`, dan diambil dari AppState
+*/
+
+/// Flattens nested validation errors into `path -> errors`, where nested
+/// structs are joined with `.` and list items with `[index]`.
+pub fn flatten_errors(errors: &ValidationErrors) -> BTreeMap<String, Vec<FieldError>> {
+    let mut result = BTreeMap::new();
+    collect_errors(errors, None, &mut result);
+    result
+}
+
+fn collect_errors(
+    errors: &ValidationErrors,
+    prefix: Option<&str>,
+    result: &mut BTreeMap<String, Vec<FieldError>>,
+) {
+    for (field, kind) in errors.errors() {
+        let path = match prefix {
+            Some(prefix) if field == "__all__" => prefix.to_string(),
+            Some(prefix) => format!("{}.{}", prefix, field),
+            None => field.to_string(),
+        };
+
+        match kind {
+            ValidationErrorsKind::Field(field_errors) => {
+                result
+                    .entry(path)
+                    .or_default()
+                    .extend(field_errors.iter().map(|error| {
+                        FieldError {
+                            code: error.code.to_string(),
+                            message: error
+                                .message
+                                .as_ref()
+                                .map(|message| message.to_string())
+                                .unwrap_or_else(|| error.code.to_string()),
+                        }
+                    }));
+            }
+            ValidationErrorsKind::Struct(errors) => collect_errors(errors, Some(&path), result),
+            ValidationErrorsKind::List(items) => {
+                for (index, errors) in items {
+                    collect_errors(errors, Some(&format!("{}[{}]", path, index)), result);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TransactionRules {
+    pub max_items: usize,
+    pub max_quantity: u32,
+}
+
+impl Default for TransactionRules {
+    fn default() -> Self {
+        TransactionRules {
+            max_items: 50,
+            max_quantity: 1000,
+        }
+    }
+}
+
+pub fn within_transaction_rules(
+    request: &CreateTransactionRequest,
+    rules: &TransactionRules,
+) -> Result<(), ValidationError> {
+    if request.items.len() > rules.max_items {
+        return Err(
+            ValidationError::new("max_items").with_message(Cow::from(format!(
+                "Transaction must not have more than {} items",
+                rules.max_items
+            ))),
+        );
+    }
+    if request
+        .items
+        .iter()
+        .any(|item| item.quantity > rules.max_quantity)
+    {
+        return Err(
+            ValidationError::new("max_quantity").with_message(Cow::from(format!(
+                "Quantity per item must not be more than {}",
+                rules.max_quantity
+            ))),
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Serialize;
+    use validator::Validate;
+
+    use super::flatten_errors;
+
+    #[derive(Debug, Validate)]
+    struct Product {
+        #[validate(length(min = 1, message = "Name must not be empty"))]
+        name: String,
+        #[validate(nested, length(min = 1, message = "Variants must not be empty"))]
+        variants: Vec<ProductVariant>,
+        #[validate(nested)]
+        supplier: Supplier,
+    }
+
+    #[derive(Debug, Validate, Serialize)]
+    struct ProductVariant {
+        #[validate(length(min = 1, message = "Name must not be empty"))]
+        name: String,
+        #[validate(range(min = 1, message = "Price must be at least 1"))]
+        price: u32,
+    }
+
+    #[derive(Debug, Validate)]
+    struct Supplier {
+        #[validate(email)]
+        email: String,
+    }
+
+    #[test]
+    fn test_flatten_nested_errors() {
+        let product = Product {
+            name: "".to_string(),
+            variants: vec![
+                ProductVariant {
+                    name: "Variant 1".to_string(),
+                    price: 100,
+                },
+                ProductVariant {
+                    name: "".to_string(),
+                    price: 0,
+                },
+            ],
+            supplier: Supplier {
+                email: "bukan-email".to_string(),
+            },
+        };
+
+        let errors = flatten_errors(&product.validate().unwrap_err());
+        assert_eq!(
+            vec![
+                "name",
+                "supplier.email",
+                "variants[1].name",
+                "variants[1].price"
+            ],
+            errors.keys().collect::<Vec<_>>()
+        );
+        assert_eq!("range", errors["variants[1].price"][0].code);
+        assert_eq!(
+            "Price must be at least 1",
+            errors["variants[1].price"][0].message
+        );
+        assert_eq!("email", errors["supplier.email"][0].message);
+    }
+}
@@ -0,0 +1 @@
+drop table customers;
\ No newline at end of file
@@ -0,0 +1 @@
+create table customers (id integer primary key, name text not null);
\ No newline at end of file
@@ -0,0 +1 @@
+create table orders (id integer primary key, customer_id integer not null references customers(id));
\ No newline at end of file
@@ -0,0 +1 @@
+alter table customers drop column email;
\ No newline at end of file
@@ -0,0 +1 @@
+alter table customers add column email text;
\ No newline at end of file
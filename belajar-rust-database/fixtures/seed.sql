@@ -0,0 +1,10 @@
+insert into category(id, name, description) values ('C', 'Elektronik', 'Kategori elektronik');
+insert into category(id, name, description) values ('G', 'Gadget', 'Kategori gadget');
+
+insert into brands(id, name, description, created_at, updated_at) values ('B', 'Brand B', 'Brand seed B', '2025-09-01 00:00:00', '2025-09-01 00:00:00');
+insert into brands(id, name, description, created_at, updated_at) values ('C', 'Brand C', 'Brand seed C', '2025-09-01 00:00:00', '2025-09-01 00:00:00');
+
+insert into sellers(name) values ('Seller Seed');
+
+insert into products(name, description, price, stock) values ('Laptop Pro', 'Laptop canggih untuk profesional', 15000000, 50);
+insert into products(name, description, price, stock) values ('Mouse Wireless', 'Mouse tanpa kabel', 150000, 100);
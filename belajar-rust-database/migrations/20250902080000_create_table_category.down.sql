@@ -0,0 +1,10 @@
+-- Hanya category dengan id angka yang bisa kembali ke `categories`, sisanya serta description ikut hilang.
+CREATE TABLE IF NOT EXISTS `categories` (
+    `id` serial primary key,
+    `name` varchar(255) not null
+);
+
+INSERT IGNORE INTO `categories` (`id`, `name`)
+SELECT CAST(`id` AS UNSIGNED), `name` FROM `category` WHERE `id` REGEXP '^[0-9]+$';
+
+DROP TABLE IF EXISTS `category`;
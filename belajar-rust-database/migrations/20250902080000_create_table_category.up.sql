@@ -0,0 +1,12 @@
+-- `categories` lama (id angka) diganti `category` (id varchar), data nya disalin, bukan dibuang.
+-- id lama disimpan sebagai teks, description diisi string kosong.
+CREATE TABLE IF NOT EXISTS `category` (
+    `id` varchar(100) not null primary key,
+    `name` varchar(100) not null,
+    `description` text not null
+);
+
+INSERT IGNORE INTO `category` (`id`, `name`, `description`)
+SELECT CAST(`id` AS CHAR), `name`, '' FROM `categories`;
+
+DROP TABLE IF EXISTS `categories`;
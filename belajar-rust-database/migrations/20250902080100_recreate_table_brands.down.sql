@@ -0,0 +1,12 @@
+-- Hanya brand dengan id angka yang bisa kembali ke tabel lama, sisanya serta description ikut hilang.
+RENAME TABLE `brands` TO `brands_varchar`;
+
+CREATE TABLE `brands` (
+    `id` serial primary key,
+    `name` varchar(255) not null
+);
+
+INSERT INTO `brands` (`id`, `name`)
+SELECT CAST(`id` AS UNSIGNED), `name` FROM `brands_varchar` WHERE `id` REGEXP '^[0-9]+$';
+
+DROP TABLE `brands_varchar`;
@@ -0,0 +1,16 @@
+-- Tipe id berubah dari angka ke varchar, jadi tabel lama di rename dulu lalu data nya disalin ke tabel baru.
+-- id lama disimpan sebagai teks, description diisi string kosong.
+RENAME TABLE `brands` TO `brands_serial`;
+
+CREATE TABLE `brands` (
+    `id` varchar(100) not null primary key,
+    `name` varchar(100) not null,
+    `description` text not null,
+    `created_at` timestamp not null default current_timestamp,
+    `updated_at` timestamp not null default current_timestamp on update current_timestamp
+);
+
+INSERT INTO `brands` (`id`, `name`, `description`)
+SELECT CAST(`id` AS CHAR), `name`, '' FROM `brands_serial`;
+
+DROP TABLE `brands_serial`;
@@ -0,0 +1 @@
+DROP TABLE `sellers`;
\ No newline at end of file
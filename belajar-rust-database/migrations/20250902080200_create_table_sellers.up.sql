@@ -0,0 +1,4 @@
+CREATE TABLE `sellers` (
+    `id` bigint unsigned not null auto_increment primary key,
+    `name` varchar(100) not null
+);
\ No newline at end of file
@@ -0,0 +1,9 @@
+CREATE TABLE `users` (
+    `id` bigint unsigned not null auto_increment primary key,
+    `name` varchar(100) not null,
+    `email` varchar(255) not null,
+    `password` varchar(255) not null,
+    `created_at` timestamp not null default current_timestamp,
+    `updated_at` timestamp not null default current_timestamp on update current_timestamp,
+    constraint `users_email_unique` unique (`email`)
+);
\ No newline at end of file
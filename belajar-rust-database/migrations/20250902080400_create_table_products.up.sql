@@ -0,0 +1,9 @@
+CREATE TABLE `products` (
+    `id` bigint unsigned not null auto_increment primary key,
+    `name` varchar(255) not null,
+    `description` text not null,
+    `price` bigint unsigned not null,
+    `stock` int unsigned not null default 0,
+    `created_at` timestamp not null default current_timestamp,
+    `updated_at` timestamp not null default current_timestamp on update current_timestamp
+);
\ No newline at end of file
@@ -0,0 +1,3 @@
+DROP TABLE `transaction_items`;
+
+DROP TABLE `transactions`;
\ No newline at end of file
@@ -0,0 +1,16 @@
+CREATE TABLE `transactions` (
+    `id` bigint unsigned not null auto_increment primary key,
+    `total` bigint unsigned not null,
+    `created_at` timestamp not null default current_timestamp
+);
+
+CREATE TABLE `transaction_items` (
+    `transaction_id` bigint unsigned not null,
+    `product_id` bigint unsigned not null,
+    `quantity` int unsigned not null,
+    `price` bigint unsigned not null,
+    `subtotal` bigint unsigned not null,
+    primary key (`transaction_id`, `product_id`),
+    constraint `transaction_items_transaction_fk` foreign key (`transaction_id`) references `transactions` (`id`) on delete cascade,
+    constraint `transaction_items_product_fk` foreign key (`product_id`) references `products` (`id`)
+);
\ No newline at end of file
@@ -0,0 +1,4 @@
+ALTER TABLE `category` DROP COLUMN `deleted_at`;
+ALTER TABLE `brands` DROP COLUMN `deleted_at`;
+ALTER TABLE `sellers` DROP COLUMN `deleted_at`;
+ALTER TABLE `products` DROP COLUMN `deleted_at`;
\ No newline at end of file
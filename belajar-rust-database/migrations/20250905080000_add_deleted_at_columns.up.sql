@@ -0,0 +1,4 @@
+ALTER TABLE `category` ADD COLUMN `deleted_at` timestamp null;
+ALTER TABLE `brands` ADD COLUMN `deleted_at` timestamp null;
+ALTER TABLE `sellers` ADD COLUMN `deleted_at` timestamp null;
+ALTER TABLE `products` ADD COLUMN `deleted_at` timestamp null;
\ No newline at end of file
@@ -0,0 +1,10 @@
+CREATE TABLE `audit_log` (
+    `id` bigint unsigned not null auto_increment primary key,
+    `actor` varchar(100) not null,
+    `action` varchar(10) not null,
+    `entity` varchar(50) not null,
+    `entity_id` varchar(100) not null,
+    `changes` json not null,
+    `created_at` timestamp not null default current_timestamp,
+    index `audit_log_entity` (`entity`, `entity_id`)
+);
\ No newline at end of file
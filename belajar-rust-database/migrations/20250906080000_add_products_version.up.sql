@@ -0,0 +1 @@
+ALTER TABLE `products` ADD COLUMN `version` int unsigned not null default 0;
\ No newline at end of file
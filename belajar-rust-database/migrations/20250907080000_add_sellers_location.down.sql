@@ -0,0 +1,2 @@
+ALTER TABLE `sellers` DROP COLUMN `longitude`;
+ALTER TABLE `sellers` DROP COLUMN `latitude`;
@@ -0,0 +1,2 @@
+ALTER TABLE `sellers` ADD COLUMN `latitude` double null;
+ALTER TABLE `sellers` ADD COLUMN `longitude` double null;
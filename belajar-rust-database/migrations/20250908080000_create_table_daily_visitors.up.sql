@@ -0,0 +1,6 @@
+CREATE TABLE `daily_visitors` (
+    `date` date not null,
+    `route` varchar(255) not null,
+    `visitors` bigint unsigned not null,
+    primary key (`date`, `route`)
+);
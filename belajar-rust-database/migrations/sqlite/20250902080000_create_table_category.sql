@@ -0,0 +1,5 @@
+CREATE TABLE `category` (
+    `id` text not null primary key,
+    `name` text not null,
+    `description` text not null
+);
\ No newline at end of file
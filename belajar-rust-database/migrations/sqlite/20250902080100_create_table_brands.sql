@@ -0,0 +1,7 @@
+CREATE TABLE `brands` (
+    `id` text not null primary key,
+    `name` text not null,
+    `description` text not null,
+    `created_at` timestamp not null default current_timestamp,
+    `updated_at` timestamp not null default current_timestamp
+);
\ No newline at end of file
@@ -0,0 +1,4 @@
+CREATE TABLE `sellers` (
+    `id` integer not null primary key autoincrement,
+    `name` text not null
+);
\ No newline at end of file
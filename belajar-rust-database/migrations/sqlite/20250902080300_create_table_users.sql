@@ -0,0 +1,8 @@
+CREATE TABLE `users` (
+    `id` integer not null primary key autoincrement,
+    `name` text not null,
+    `email` text not null unique,
+    `password` text not null,
+    `created_at` timestamp not null default current_timestamp,
+    `updated_at` timestamp not null default current_timestamp
+);
\ No newline at end of file
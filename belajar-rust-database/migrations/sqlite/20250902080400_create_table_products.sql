@@ -0,0 +1,9 @@
+CREATE TABLE `products` (
+    `id` integer not null primary key autoincrement,
+    `name` text not null,
+    `description` text not null,
+    `price` integer not null,
+    `stock` integer not null default 0,
+    `created_at` timestamp not null default current_timestamp,
+    `updated_at` timestamp not null default current_timestamp
+);
\ No newline at end of file
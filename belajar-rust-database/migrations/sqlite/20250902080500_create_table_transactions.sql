@@ -0,0 +1,14 @@
+CREATE TABLE `transactions` (
+    `id` integer not null primary key autoincrement,
+    `total` integer not null,
+    `created_at` timestamp not null default current_timestamp
+);
+
+CREATE TABLE `transaction_items` (
+    `transaction_id` integer not null references `transactions` (`id`) on delete cascade,
+    `product_id` integer not null references `products` (`id`),
+    `quantity` integer not null,
+    `price` integer not null,
+    `subtotal` integer not null,
+    primary key (`transaction_id`, `product_id`)
+);
\ No newline at end of file
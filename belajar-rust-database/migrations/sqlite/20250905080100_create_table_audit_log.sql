@@ -0,0 +1,10 @@
+CREATE TABLE `audit_log` (
+    `id` integer not null primary key autoincrement,
+    `actor` text not null,
+    `action` text not null,
+    `entity` text not null,
+    `entity_id` text not null,
+    `changes` text not null,
+    `created_at` timestamp not null default current_timestamp
+);
+CREATE INDEX `audit_log_entity` ON `audit_log` (`entity`, `entity_id`);
\ No newline at end of file
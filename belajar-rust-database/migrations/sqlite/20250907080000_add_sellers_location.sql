@@ -0,0 +1,2 @@
+ALTER TABLE `sellers` ADD COLUMN `latitude` real null;
+ALTER TABLE `sellers` ADD COLUMN `longitude` real null;
@@ -0,0 +1,6 @@
+CREATE TABLE `daily_visitors` (
+    `date` date not null,
+    `route` text not null,
+    `visitors` integer not null,
+    primary key (`date`, `route`)
+);
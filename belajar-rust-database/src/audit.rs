@@ -0,0 +1,301 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{Map, Value, json};
+use sqlx::{
+    Error, MySqlConnection, MySqlPool, QueryBuilder, SqliteConnection, SqlitePool, prelude::FromRow,
+};
+
+use crate::model::{Brand, Category, Product, Seller, Transaction, User};
+
+/*
+AUDIT LOG
+- Setiap insert, update dan delete dicatat di tabel `audit_log`: siapa yang mengubah (actor), jenis perubahan (action),
+entity dan id nya, serta perubahan dalam bentuk JSON
+- Audit log ditulis di transaction yang sama dengan perubahan datanya, sehingga jika perubahan di rollback,
+audit log nya juga ikut di rollback, dan tidak ada perubahan yang tidak tercatat
+- Perubahan disimpan per field dalam format `{"field": {"old": ..., "new": ...}}`, field yang tidak berubah tidak disimpan
+
+SOFT DELETE
+- Data yang dihapus tidak benar - benar dihapus dari tabel, melainkan hanya diisi kolom `deleted_at` nya
+- Semua query bawaan repository menambahkan `deleted_at is null`, sehingga data yang sudah dihapus tidak terlihat lagi
+- Karena barisnya masih ada, id yang sama tidak bisa digunakan lagi untuk insert, begitu juga email user yang sudah dihapus
+- Soft delete digunakan oleh category, brands, sellers, products dan users,
+sedangkan transactions tidak, karena transaksi hanya bisa ditambah dan tidak pernah diubah atau dihapus
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditAction {
+    Insert,
+    Update,
+    Delete,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::Insert => "insert",
+            AuditAction::Update => "update",
+            AuditAction::Delete => "delete",
+        }
+    }
+}
+
+/// Entity whose changes are written to the audit log.
+pub trait Audited: Serialize {
+    /// Name of the entity, the same as its table.
+    const ENTITY: &'static str;
+
+    fn audit_id(&self) -> String;
+}
+
+impl Audited for Category {
+    const ENTITY: &'static str = "category";
+
+    fn audit_id(&self) -> String {
+        self.id.clone()
+    }
+}
+
+impl Audited for Brand {
+    const ENTITY: &'static str = "brands";
+
+    fn audit_id(&self) -> String {
+        self.id.clone()
+    }
+}
+
+impl Audited for Seller {
+    const ENTITY: &'static str = "sellers";
+
+    fn audit_id(&self) -> String {
+        self.id.to_string()
+    }
+}
+
+impl Audited for User {
+    const ENTITY: &'static str = "users";
+
+    fn audit_id(&self) -> String {
+        self.id.to_string()
+    }
+}
+
+impl Audited for Product {
+    const ENTITY: &'static str = "products";
+
+    fn audit_id(&self) -> String {
+        self.id.to_string()
+    }
+}
+
+impl Audited for Transaction {
+    const ENTITY: &'static str = "transactions";
+
+    fn audit_id(&self) -> String {
+        self.id.to_string()
+    }
+}
+
+/// Field by field changes between two versions of an entity, as
+/// `{"field": {"old": .., "new": ..}}`. Unchanged fields are left out, and
+/// a missing version (before an insert, after a delete) counts as null.
+pub fn diff<T: Serialize>(old: Option<&T>, new: Option<&T>) -> Value {
+    fn fields<T: Serialize>(value: Option<&T>) -> Map<String, Value> {
+        match value.map(serde_json::to_value) {
+            Some(Ok(Value::Object(fields))) => fields,
+            _ => Map::new(),
+        }
+    }
+
+    let old = fields(old);
+    let new = fields(new);
+    let mut changes = Map::new();
+    for key in old.keys().chain(new.keys()) {
+        let before = old.get(key).unwrap_or(&Value::Null);
+        let after = new.get(key).unwrap_or(&Value::Null);
+        if before != after && !changes.contains_key(key) {
+            changes.insert(key.clone(), json!({ "old": before, "new": after }));
+        }
+    }
+    Value::Object(changes)
+}
+
+/// A change that is about to be written to the audit log.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditRecord {
+    pub actor: String,
+    pub action: AuditAction,
+    pub entity: &'static str,
+    pub entity_id: String,
+    pub changes: Value,
+}
+
+impl AuditRecord {
+    pub fn new<T: Audited>(
+        actor: &str,
+        action: AuditAction,
+        old: Option<&T>,
+        new: Option<&T>,
+    ) -> Self {
+        AuditRecord {
+            actor: actor.to_string(),
+            action,
+            entity: T::ENTITY,
+            entity_id: old.or(new).map(Audited::audit_id).unwrap_or_default(),
+            changes: diff(old, new),
+        }
+    }
+}
+
+/// Connection that can write to the audit log.
+#[async_trait]
+pub trait AuditWriter: Send {
+    async fn write_audit(&mut self, records: &[AuditRecord]) -> Result<(), Error>;
+}
+
+macro_rules! audit_writer {
+    ($connection:ty) => {
+        #[async_trait]
+        impl AuditWriter for $connection {
+            async fn write_audit(&mut self, records: &[AuditRecord]) -> Result<(), Error> {
+                if records.is_empty() {
+                    return Ok(());
+                }
+
+                QueryBuilder::new(
+                    "insert into audit_log(actor, action, entity, entity_id, changes) ",
+                )
+                .push_values(records, |mut row, record| {
+                    row.push_bind(&record.actor)
+                        .push_bind(record.action.as_str())
+                        .push_bind(record.entity)
+                        .push_bind(&record.entity_id)
+                        .push_bind(record.changes.to_string());
+                })
+                .build()
+                .execute(&mut *self)
+                .await?;
+                Ok(())
+            }
+        }
+    };
+}
+
+audit_writer!(MySqlConnection);
+audit_writer!(SqliteConnection);
+
+/// Writes all records in one insert. Pass the connection of the transaction
+/// that made the changes, so the log is committed or rolled back with them.
+pub async fn write<C>(connection: &mut C, records: &[AuditRecord]) -> Result<(), Error>
+where
+    C: AuditWriter + ?Sized,
+{
+    connection.write_audit(records).await
+}
+
+/// One row of the audit log. `changes` holds the JSON text written by [`diff`].
+#[derive(FromRow, Serialize, Debug, Clone, PartialEq)]
+pub struct AuditEntry {
+    pub id: u64,
+    pub actor: String,
+    pub action: String,
+    pub entity: String,
+    pub entity_id: String,
+    pub changes: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[async_trait]
+pub trait AuditLogRepository: Send + Sync {
+    /// Returns the history of one entity, oldest change first.
+    async fn find_by_entity(&self, entity: &str, id: &str) -> Result<Vec<AuditEntry>, Error>;
+}
+
+/// Implements the repository for one backend, MySQL and SQLite share the same SQL.
+macro_rules! audit_log_repository {
+    ($name:ident, $pool:ty) => {
+        #[derive(Clone)]
+        pub struct $name {
+            pool: $pool,
+        }
+
+        impl $name {
+            pub fn new(pool: $pool) -> Self {
+                $name { pool }
+            }
+        }
+
+        #[async_trait]
+        impl AuditLogRepository for $name {
+            async fn find_by_entity(
+                &self,
+                entity: &str,
+                id: &str,
+            ) -> Result<Vec<AuditEntry>, Error> {
+                // MySQL returns json columns as binary, so they are read as text
+                sqlx::query_as(
+                    "select id, actor, action, entity, entity_id, cast(changes as char) as changes, created_at \
+                    from audit_log where entity = ? and entity_id = ? order by id",
+                )
+                .bind(entity)
+                .bind(id)
+                .fetch_all(&self.pool)
+                .await
+            }
+        }
+    };
+}
+
+audit_log_repository!(MySqlAuditLogRepository, MySqlPool);
+audit_log_repository!(SqliteAuditLogRepository, SqlitePool);
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{AuditAction, AuditRecord, diff};
+    use crate::model::{Category, Seller, SellerId};
+
+    fn category(name: &str) -> Category {
+        Category {
+            id: "C".to_string(),
+            name: name.to_string(),
+            description: "Kategori".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = category("Elektronik");
+        let new = category("Gadget");
+        assert_eq!(
+            json!({ "name": { "old": "Elektronik", "new": "Gadget" } }),
+            diff(Some(&old), Some(&new))
+        );
+        assert_eq!(json!({}), diff(Some(&old), Some(&old)));
+        assert_eq!(
+            json!({
+                "id": { "old": null, "new": "C" },
+                "name": { "old": null, "new": "Gadget" },
+                "description": { "old": null, "new": "Kategori" },
+            }),
+            diff(None, Some(&new))
+        );
+    }
+
+    #[test]
+    fn test_audit_record() {
+        let seller = Seller {
+            id: SellerId(7),
+            name: "Toko".to_string(),
+            latitude: None,
+            longitude: None,
+        };
+        let record = AuditRecord::new("admin", AuditAction::Delete, Some(&seller), None);
+        assert_eq!("sellers", record.entity);
+        assert_eq!("7", record.entity_id);
+        assert_eq!(json!({ "old": 7, "new": null }), record.changes["id"]);
+    }
+}
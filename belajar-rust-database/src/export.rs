@@ -0,0 +1,470 @@
+use std::{fmt, pin::pin, time::Duration};
+
+use futures::{Stream, StreamExt, TryStreamExt, stream::BoxStream};
+use serde::{Deserialize, Serialize};
+use sqlx::{Error, FromRow, MySqlPool, SqlitePool, mysql::MySqlRow, sqlite::SqliteRow};
+use tokio::{sync::mpsc, time::timeout};
+
+use crate::model::{Brand, Category, Product, SaleLine, Seller};
+
+/*
+EXPORT
+- `fetch()` mengembalikan Stream, sehingga baris dari database bisa diproses satu per satu tanpa harus
+menampung seluruh isi tabel di memory seperti `fetch_all()`
+- Setiap baris di-encode menjadi CSV atau NDJSON (satu object JSON per baris), lalu dikumpulkan menjadi chunk
+berukuran sekitar `CHUNK_SIZE` byte, sehingga bisa langsung dikirim sebagai body HTTP chunked
+- Header CSV diambil dari nama field struct, dan ditulis sebelum baris pertama
+
+EXPORTER
+- Stream dari `fetch()` meminjam pool, sehingga tidak bisa dikembalikan begitu saja dari function
+- Exporter menjalankan query di task terpisah (`tokio::spawn`), dan mengirim chunk melalui channel dengan kapasitas terbatas,
+sehingga query hanya berjalan secepat client membaca, dan berhenti jika client memutus koneksi
+- Selama query berjalan, satu koneksi dari pool terpakai, sehingga client yang berhenti membaca tanpa memutus koneksi
+bisa menahan koneksi tersebut selamanya
+- Karena itu, jika client tidak mengambil chunk selama `send_timeout` (default `SEND_TIMEOUT`), query dihentikan
+dan koneksi dikembalikan ke pool, lalu client menerima `ExportError::Stalled` sehingga download nya gagal, bukan terpotong diam - diam
+*/
+
+/// Approximate size in bytes of every chunk produced by [`export`].
+pub const CHUNK_SIZE: usize = 8 * 1024;
+
+/// Number of chunks an exporter may produce ahead of its reader.
+const CHANNEL_CAPACITY: usize = 4;
+
+/// How long an exporter waits for its reader to take a chunk before it
+/// releases the database connection.
+pub const SEND_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    #[serde(alias = "jsonl")]
+    NdJson,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::NdJson => "application/x-ndjson",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::NdJson => "ndjson",
+        }
+    }
+}
+
+/// Tables that can be exported, named after their export file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportTable {
+    Categories,
+    Brands,
+    Sellers,
+    Products,
+    /// One line per transaction item, joined with its transaction and product.
+    /// Sales of soft deleted products are kept.
+    Sales,
+}
+
+impl ExportTable {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportTable::Categories => "categories",
+            ExportTable::Brands => "brands",
+            ExportTable::Sellers => "sellers",
+            ExportTable::Products => "products",
+            ExportTable::Sales => "sales",
+        }
+    }
+
+    /// File name for a download, e.g. `products.csv`.
+    pub fn file_name(&self, format: ExportFormat) -> String {
+        format!("{}.{}", self.name(), format.extension())
+    }
+}
+
+#[derive(Debug)]
+pub enum ExportError {
+    Database(Error),
+    Csv(csv::Error),
+    Json(serde_json::Error),
+    /// The reader did not take a chunk within the send timeout.
+    Stalled(Duration),
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportError::Database(error) => write!(f, "{}", error),
+            ExportError::Csv(error) => write!(f, "{}", error),
+            ExportError::Json(error) => write!(f, "{}", error),
+            ExportError::Stalled(timeout) => {
+                write!(f, "export reader stalled for more than {:?}", timeout)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+impl From<Error> for ExportError {
+    fn from(error: Error) -> Self {
+        ExportError::Database(error)
+    }
+}
+
+impl From<csv::Error> for ExportError {
+    fn from(error: csv::Error) -> Self {
+        ExportError::Csv(error)
+    }
+}
+
+impl From<serde_json::Error> for ExportError {
+    fn from(error: serde_json::Error) -> Self {
+        ExportError::Json(error)
+    }
+}
+
+impl From<csv::IntoInnerError<csv::Writer<Vec<u8>>>> for ExportError {
+    fn from(error: csv::IntoInnerError<csv::Writer<Vec<u8>>>) -> Self {
+        ExportError::Csv(error.into_error().into())
+    }
+}
+
+/// Encodes rows into an in-memory buffer that is drained with [`RowEncoder::take`].
+pub struct RowEncoder {
+    format: ExportFormat,
+    csv: csv::Writer<Vec<u8>>,
+    json: Vec<u8>,
+}
+
+impl RowEncoder {
+    pub fn new(format: ExportFormat) -> Self {
+        RowEncoder {
+            format,
+            csv: csv::Writer::from_writer(Vec::new()),
+            json: Vec::new(),
+        }
+    }
+
+    pub fn encode<T: Serialize>(&mut self, row: &T) -> Result<(), ExportError> {
+        match self.format {
+            ExportFormat::Csv => self.csv.serialize(row)?,
+            ExportFormat::NdJson => {
+                serde_json::to_writer(&mut self.json, row)?;
+                self.json.push(b'\n');
+            }
+        }
+        Ok(())
+    }
+
+    /// Number of encoded bytes not taken yet. Only approximate for CSV,
+    /// which buffers the last few rows internally.
+    pub fn len(&self) -> usize {
+        match self.format {
+            ExportFormat::Csv => self.csv.get_ref().len(),
+            ExportFormat::NdJson => self.json.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the encoded bytes and clears the buffer. The CSV header is
+    /// only written once, before the first row.
+    pub fn take(&mut self) -> Result<Vec<u8>, ExportError> {
+        match self.format {
+            ExportFormat::Csv => {
+                let writer = csv::WriterBuilder::new()
+                    .has_headers(false)
+                    .from_writer(Vec::new());
+                Ok(std::mem::replace(&mut self.csv, writer).into_inner()?)
+            }
+            ExportFormat::NdJson => Ok(std::mem::take(&mut self.json)),
+        }
+    }
+}
+
+/// Encodes `rows` into chunks of about [`CHUNK_SIZE`] bytes, without
+/// collecting the rows. The stream ends with the first error.
+pub fn export<'a, T, S>(
+    rows: S,
+    format: ExportFormat,
+) -> impl Stream<Item = Result<Vec<u8>, ExportError>> + Send + 'a
+where
+    T: Serialize + Send + 'a,
+    S: Stream<Item = Result<T, Error>> + Send + 'a,
+{
+    let state = Some((rows.boxed(), RowEncoder::new(format)));
+    futures::stream::try_unfold(state, |state| async move {
+        let Some((mut rows, mut encoder)) = state else {
+            return Ok(None);
+        };
+
+        while let Some(row) = rows.try_next().await? {
+            encoder.encode(&row)?;
+            if encoder.len() >= CHUNK_SIZE {
+                let chunk = encoder.take()?;
+                return Ok(Some((chunk, Some((rows, encoder)))));
+            }
+        }
+
+        let chunk = encoder.take()?;
+        Ok((!chunk.is_empty()).then_some((chunk, None)))
+    })
+}
+
+pub type ExportStream = BoxStream<'static, Result<Vec<u8>, ExportError>>;
+
+/// Streams whole tables, see [`ExportTable`].
+pub trait Exporter: Send + Sync {
+    fn export(&self, table: ExportTable, format: ExportFormat) -> ExportStream;
+}
+
+const CATEGORIES: &str = "select * from category where deleted_at is null order by id";
+const BRANDS: &str = "select * from brands where deleted_at is null order by id";
+const SELLERS: &str = "select * from sellers where deleted_at is null order by id";
+const PRODUCTS: &str = "select * from products where deleted_at is null order by id";
+const SALES: &str = "select t.id as transaction_id, t.created_at, i.product_id, p.name as product_name, \
+i.quantity, i.price, i.subtotal from transactions t \
+join transaction_items i on i.transaction_id = t.id \
+join products p on p.id = i.product_id \
+order by t.id, i.product_id";
+
+/// Implements the exporter for one backend, MySQL and SQLite share the same SQL.
+macro_rules! exporter {
+    ($name:ident, $pool:ty, $row:ty) => {
+        #[derive(Clone)]
+        pub struct $name {
+            pool: $pool,
+            send_timeout: Duration,
+        }
+
+        impl $name {
+            pub fn new(pool: $pool) -> Self {
+                $name {
+                    pool,
+                    send_timeout: SEND_TIMEOUT,
+                }
+            }
+
+            pub fn with_send_timeout(mut self, send_timeout: Duration) -> Self {
+                self.send_timeout = send_timeout;
+                self
+            }
+
+            fn spawn<T>(&self, sql: &'static str, format: ExportFormat) -> ExportStream
+            where
+                T: for<'r> FromRow<'r, $row> + Serialize + Send + Unpin + 'static,
+            {
+                let (pool, send_timeout) = (self.pool.clone(), self.send_timeout);
+                let (sender, receiver) = mpsc::channel(CHANNEL_CAPACITY);
+                tokio::spawn(async move {
+                    let stalled = {
+                        let rows = sqlx::query_as::<_, T>(sql).fetch(&pool);
+                        let mut chunks = pin!(export(rows, format));
+                        loop {
+                            let Some(chunk) = chunks.next().await else {
+                                break false;
+                            };
+                            let failed = chunk.is_err();
+                            match timeout(send_timeout, sender.send(chunk)).await {
+                                Ok(Ok(())) if !failed => {}
+                                // a send error means the reader went away
+                                Ok(_) => break false,
+                                Err(_) => break true,
+                            }
+                        }
+                    };
+                    // the query is dropped here, so the connection is back in the pool
+                    // while waiting for the stalled reader
+                    if stalled {
+                        let _ = sender.send(Err(ExportError::Stalled(send_timeout))).await;
+                    }
+                });
+
+                futures::stream::unfold(receiver, |mut receiver| async move {
+                    let chunk = receiver.recv().await?;
+                    Some((chunk, receiver))
+                })
+                .boxed()
+            }
+        }
+
+        impl Exporter for $name {
+            fn export(&self, table: ExportTable, format: ExportFormat) -> ExportStream {
+                match table {
+                    ExportTable::Categories => self.spawn::<Category>(CATEGORIES, format),
+                    ExportTable::Brands => self.spawn::<Brand>(BRANDS, format),
+                    ExportTable::Sellers => self.spawn::<Seller>(SELLERS, format),
+                    ExportTable::Products => self.spawn::<Product>(PRODUCTS, format),
+                    ExportTable::Sales => self.spawn::<SaleLine>(SALES, format),
+                }
+            }
+        }
+    };
+}
+
+exporter!(MySqlExporter, MySqlPool, MySqlRow);
+exporter!(SqliteExporter, SqlitePool, SqliteRow);
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::{StreamExt, TryStreamExt};
+    use serde::Serialize;
+    use sqlx::Error;
+
+    use super::{
+        CHUNK_SIZE, ExportError, ExportFormat, ExportTable, Exporter, RowEncoder, SqliteExporter,
+        export,
+    };
+    use crate::{
+        model::ProductId,
+        repository::{NewTransactionItem, SqliteTransactionRepository, TransactionRepository},
+        testing::TestDatabase,
+    };
+
+    #[derive(Serialize)]
+    struct Row {
+        id: u32,
+        name: &'static str,
+    }
+
+    async fn collect(
+        table: ExportTable,
+        format: ExportFormat,
+        exporter: &SqliteExporter,
+    ) -> String {
+        let chunks: Vec<Vec<u8>> = exporter.export(table, format).try_collect().await.unwrap();
+        String::from_utf8(chunks.concat()).unwrap()
+    }
+
+    #[test]
+    fn test_row_encoder() -> Result<(), Box<dyn std::error::Error>> {
+        let mut encoder = RowEncoder::new(ExportFormat::Csv);
+        encoder.encode(&Row {
+            id: 1,
+            name: "Laptop, Pro",
+        })?;
+        assert_eq!(
+            "id,name\n1,\"Laptop, Pro\"\n",
+            String::from_utf8(encoder.take()?)?
+        );
+        encoder.encode(&Row {
+            id: 2,
+            name: "Mouse",
+        })?;
+        assert_eq!("2,Mouse\n", String::from_utf8(encoder.take()?)?);
+
+        let mut encoder = RowEncoder::new(ExportFormat::NdJson);
+        encoder.encode(&Row {
+            id: 1,
+            name: "Laptop",
+        })?;
+        encoder.encode(&Row {
+            id: 2,
+            name: "Mouse",
+        })?;
+        assert_eq!(
+            "{\"id\":1,\"name\":\"Laptop\"}\n{\"id\":2,\"name\":\"Mouse\"}\n",
+            String::from_utf8(encoder.take()?)?
+        );
+        assert!(encoder.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_export_chunks() -> Result<(), ExportError> {
+        let rows =
+            futures::stream::iter((0..2000).map(|id| Ok::<_, Error>(Row { id, name: "Produk" })));
+        let chunks: Vec<Vec<u8>> = export(rows, ExportFormat::NdJson).try_collect().await?;
+        assert!(chunks.len() > 1);
+        assert!(
+            chunks[..chunks.len() - 1]
+                .iter()
+                .all(|chunk| chunk.len() >= CHUNK_SIZE)
+        );
+        let lines = chunks
+            .concat()
+            .iter()
+            .filter(|byte| **byte == b'\n')
+            .count();
+        assert_eq!(2000, lines);
+
+        let rows = futures::stream::iter([
+            Ok(Row {
+                id: 1,
+                name: "Laptop",
+            }),
+            Err(Error::RowNotFound),
+        ]);
+        let results: Vec<_> = export(rows, ExportFormat::Csv).collect().await;
+        assert!(matches!(
+            results.as_slice(),
+            [Err(ExportError::Database(Error::RowNotFound))]
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_exporter() -> Result<(), Box<dyn std::error::Error>> {
+        let database = TestDatabase::sqlite().await?;
+        let exporter = SqliteExporter::new(database.pool().clone());
+
+        assert_eq!(
+            "id,name,description,price,stock,version\n1,Laptop Pro,Laptop canggih untuk profesional,15000000,50,0\n2,Mouse Wireless,Mouse tanpa kabel,150000,100,0\n",
+            collect(ExportTable::Products, ExportFormat::Csv, &exporter).await
+        );
+
+        SqliteTransactionRepository::new(database.pool().clone())
+            .checkout(
+                "test",
+                &[NewTransactionItem {
+                    product_id: ProductId(2),
+                    quantity: 3,
+                }],
+            )
+            .await?;
+        let sales = collect(ExportTable::Sales, ExportFormat::NdJson, &exporter).await;
+        let line: serde_json::Value = serde_json::from_str(sales.trim_end())?;
+        assert_eq!(1, line["transaction_id"]);
+        assert_eq!("Mouse Wireless", line["product_name"]);
+        assert_eq!(450_000, line["subtotal"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stalled_reader_releases_connection() -> Result<(), Box<dyn std::error::Error>> {
+        let database = TestDatabase::sqlite().await?;
+        sqlx::query(
+            "with recursive n(i) as (select 1 union all select i + 1 from n where i < 2000) \
+            insert into products(name, description, price, stock) select 'Produk ' || i, 'Deskripsi', 1000, 1 from n",
+        )
+        .execute(database.pool())
+        .await?;
+        let exporter = SqliteExporter::new(database.pool().clone())
+            .with_send_timeout(Duration::from_millis(50));
+
+        let stream = exporter.export(ExportTable::Products, ExportFormat::NdJson);
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(database.pool().size() as usize, database.pool().num_idle());
+
+        let chunks: Vec<_> = stream.collect().await;
+        assert!(chunks[..chunks.len() - 1].iter().all(Result::is_ok));
+        assert!(matches!(
+            chunks.last(),
+            Some(Err(ExportError::Stalled(timeout))) if *timeout == Duration::from_millis(50)
+        ));
+        Ok(())
+    }
+}
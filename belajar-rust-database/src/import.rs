@@ -0,0 +1,664 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
+
+use async_trait::async_trait;
+use belajar_rust_validation::catalog;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use sqlx::{Error, MySqlPool, QueryBuilder, SqlitePool};
+use validator::{Validate, ValidationErrors};
+
+use crate::{
+    audit::{self, AuditAction, AuditRecord, Audited},
+    model::{Category, Product, ProductId},
+    repository::{IdRange, InsertedIds},
+    transaction::with_transaction,
+};
+
+/*
+IMPORT
+- Importer membaca file CSV (misal hasil export spreadsheet), baris pertama adalah header berisi nama kolom
+- Setiap baris di-deserialize lalu divalidasi menggunakan validator di project belajar-rust-validation,
+baris yang gagal dicatat di report beserta nomor baris dan error per kolom, tanpa menghentikan import
+- Baris yang valid disimpan per batch, satu batch satu transaction, menggunakan upsert:
+data dengan id yang sudah ada akan di update, sisanya di insert
+- Perintah upsert berbeda di tiap database, MySQL menggunakan `on duplicate key update`,
+sedangkan SQLite menggunakan `on conflict(id) do update`
+- Produk tanpa id di insert sebagai produk baru, sehingga id nya bisa dicatat di audit log,
+sedangkan data yang di upsert juga mengembalikan data yang sudah di soft delete
+- Sebelum upsert, data dengan id yang sama dibaca terlebih dahulu, sehingga audit log mencatat `update` beserta data lamanya,
+atau `insert` jika id tersebut belum ada
+- Dengan `dry_run`, semua baris tetap divalidasi, namun tidak ada yang disimpan ke database
+*/
+
+/// Error key for problems that do not belong to one column.
+pub const ROW_ERROR: &str = "__all__";
+
+/// Tables that can be imported, named like their export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportTable {
+    Categories,
+    Products,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportOptions {
+    /// Maximum number of rows written in one transaction.
+    pub batch_size: usize,
+    pub dry_run: bool,
+    /// Recorded as the actor of every change in the audit log.
+    pub actor: String,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        ImportOptions {
+            batch_size: 500,
+            dry_run: false,
+            actor: "import".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    /// Number of data rows read, without the header.
+    pub total: usize,
+    pub valid: usize,
+    /// Number of rows written, always 0 on a dry run.
+    pub imported: usize,
+    pub rejected: Vec<RejectedRow>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RejectedRow {
+    /// Line in the file, the header is line 1.
+    pub line: u64,
+    /// Error messages per column, see [`ROW_ERROR`].
+    pub errors: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Debug)]
+pub enum ImportError {
+    Database(Error),
+    /// The file cannot be read at all, e.g. a missing or invalid header.
+    Csv(csv::Error),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Database(error) => write!(f, "{}", error),
+            ImportError::Csv(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+impl From<Error> for ImportError {
+    fn from(error: Error) -> Self {
+        ImportError::Database(error)
+    }
+}
+
+impl From<csv::Error> for ImportError {
+    fn from(error: csv::Error) -> Self {
+        ImportError::Csv(error)
+    }
+}
+
+/// Columns `id,name,description`, the description may be left out.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct CategoryRow {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+/// Columns `id,name,description,price,stock`. Rows with an empty `id` are
+/// inserted as new products, an empty `stock` is 0. The other columns except
+/// `name` and `price` may be left out.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ProductRow {
+    #[serde(default)]
+    pub id: Option<ProductId>,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub price: u64,
+    #[serde(default)]
+    pub stock: Option<u32>,
+}
+
+impl CategoryRow {
+    fn to_category(&self) -> Category {
+        Category {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            description: self.description.clone(),
+        }
+    }
+}
+
+impl ProductRow {
+    /// Uses the id of the row, or `id` for a new product.
+    fn to_product(&self, id: ProductId) -> Product {
+        Product {
+            id: self.id.unwrap_or(id),
+            name: self.name.clone(),
+            description: self.description.clone(),
+            price: self.price,
+            stock: self.stock.unwrap_or_default(),
+            version: 0,
+        }
+    }
+}
+
+/// An update of the row read before the upsert, or an insert when the id
+/// did not exist yet.
+fn audit_record<T: Audited>(actor: &str, old: Option<&T>, new: &T) -> AuditRecord {
+    let action = match old {
+        Some(_) => AuditAction::Update,
+        None => AuditAction::Insert,
+    };
+    AuditRecord::new(actor, action, old, Some(new))
+}
+
+/// A CSV row checked by the validators of the catalog.
+trait ImportRow: DeserializeOwned {
+    fn validate(&self) -> Result<(), ValidationErrors>;
+}
+
+impl ImportRow for CategoryRow {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        catalog::Category {
+            id: self.id.clone(),
+            name: self.name.clone(),
+        }
+        .validate()
+    }
+}
+
+impl ImportRow for ProductRow {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        catalog::Product {
+            name: self.name.clone(),
+            price: self.price,
+        }
+        .validate()
+    }
+}
+
+fn validation_errors(errors: &ValidationErrors) -> BTreeMap<String, Vec<String>> {
+    errors
+        .field_errors()
+        .into_iter()
+        .map(|(field, errors)| {
+            let messages = errors
+                .iter()
+                .map(|error| match &error.message {
+                    Some(message) => message.to_string(),
+                    None => error.code.to_string(),
+                })
+                .collect();
+            (field.to_string(), messages)
+        })
+        .collect()
+}
+
+/// Maps a record that cannot be deserialized to the column it failed on.
+fn csv_errors(error: &csv::Error, headers: &csv::StringRecord) -> BTreeMap<String, Vec<String>> {
+    let (column, message) = match error.kind() {
+        csv::ErrorKind::Deserialize { err, .. } => {
+            let column = err
+                .field()
+                .and_then(|index| headers.get(index as usize))
+                .unwrap_or(ROW_ERROR);
+            (column, err.kind().to_string())
+        }
+        _ => (ROW_ERROR, error.to_string()),
+    };
+    BTreeMap::from([(column.to_string(), vec![message])])
+}
+
+/// Reads and validates every row, returning the valid ones in file order
+/// together with a report of the rejected ones.
+fn read_rows<T: ImportRow>(
+    csv: &[u8],
+    options: &ImportOptions,
+) -> Result<(Vec<T>, ImportReport), ImportError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(csv);
+    let headers = reader.headers()?.clone();
+
+    let mut rows = Vec::new();
+    let mut report = ImportReport {
+        dry_run: options.dry_run,
+        ..ImportReport::default()
+    };
+    for record in reader.records() {
+        report.total += 1;
+        let result = record.and_then(|record| {
+            let line = record.position().map_or(0, |position| position.line());
+            record
+                .deserialize::<T>(Some(&headers))
+                .map(|row| (line, row))
+        });
+
+        let (line, errors) = match result {
+            Ok((line, row)) => match row.validate() {
+                Ok(()) => {
+                    rows.push(row);
+                    continue;
+                }
+                Err(errors) => (line, validation_errors(&errors)),
+            },
+            Err(error) => {
+                let line = error.position().map_or(0, |position| position.line());
+                (line, csv_errors(&error, &headers))
+            }
+        };
+        report.rejected.push(RejectedRow { line, errors });
+    }
+
+    report.valid = rows.len();
+    Ok((rows, report))
+}
+
+#[async_trait]
+pub trait Importer: Send + Sync {
+    async fn import_categories(
+        &self,
+        csv: &[u8],
+        options: &ImportOptions,
+    ) -> Result<ImportReport, ImportError>;
+    async fn import_products(
+        &self,
+        csv: &[u8],
+        options: &ImportOptions,
+    ) -> Result<ImportReport, ImportError>;
+
+    async fn import(
+        &self,
+        table: ImportTable,
+        csv: &[u8],
+        options: &ImportOptions,
+    ) -> Result<ImportReport, ImportError> {
+        match table {
+            ImportTable::Categories => self.import_categories(csv, options).await,
+            ImportTable::Products => self.import_products(csv, options).await,
+        }
+    }
+}
+
+const MYSQL_UPSERT_CATEGORY: &str = " on duplicate key update name = values(name), description = values(description), \
+deleted_at = null";
+const MYSQL_UPSERT_PRODUCT: &str = " on duplicate key update name = values(name), description = values(description), \
+price = values(price), stock = values(stock), version = version + 1, deleted_at = null";
+const SQLITE_UPSERT_CATEGORY: &str = " on conflict(id) do update set name = excluded.name, description = excluded.description, \
+deleted_at = null";
+const SQLITE_UPSERT_PRODUCT: &str = " on conflict(id) do update set name = excluded.name, description = excluded.description, \
+price = excluded.price, stock = excluded.stock, version = version + 1, updated_at = current_timestamp, \
+deleted_at = null";
+
+/// Implements the importer for one backend, only the upsert clause differs.
+/// `$lock` locks the existing rows read for the audit log, SQLite has no row locks.
+macro_rules! importer {
+    ($name:ident, $pool:ty, $lock:literal, $upsert_category:expr, $upsert_product:expr) => {
+        #[derive(Clone)]
+        pub struct $name {
+            pool: $pool,
+        }
+
+        impl $name {
+            pub fn new(pool: $pool) -> Self {
+                $name { pool }
+            }
+        }
+
+        #[async_trait]
+        impl Importer for $name {
+            async fn import_categories(
+                &self,
+                csv: &[u8],
+                options: &ImportOptions,
+            ) -> Result<ImportReport, ImportError> {
+                let (rows, mut report) = read_rows::<CategoryRow>(csv, options)?;
+                if options.dry_run {
+                    return Ok(report);
+                }
+
+                for batch in rows.chunks(options.batch_size.max(1)) {
+                    with_transaction(&self.pool, |tx| {
+                        let (actor, batch) = (options.actor.clone(), batch.to_vec());
+                        Box::pin(async move {
+                            let mut select =
+                                QueryBuilder::new("select * from category where id in (");
+                            let mut ids = select.separated(", ");
+                            for row in &batch {
+                                ids.push_bind(&row.id);
+                            }
+                            select.push(")").push($lock);
+                            let existing: Vec<Category> =
+                                select.build_query_as().fetch_all(&mut **tx).await?;
+                            let mut existing: HashMap<String, Category> = existing
+                                .into_iter()
+                                .map(|category| (category.id.clone(), category))
+                                .collect();
+
+                            QueryBuilder::new("insert into category(id, name, description) ")
+                                .push_values(&batch, |mut row, category| {
+                                    row.push_bind(&category.id)
+                                        .push_bind(&category.name)
+                                        .push_bind(&category.description);
+                                })
+                                .push($upsert_category)
+                                .build()
+                                .execute(&mut **tx)
+                                .await?;
+
+                            let records: Vec<AuditRecord> = batch
+                                .iter()
+                                .map(|row| {
+                                    let category = row.to_category();
+                                    let old = existing.insert(row.id.clone(), category.clone());
+                                    audit_record(&actor, old.as_ref(), &category)
+                                })
+                                .collect();
+                            audit::write(&mut **tx, &records).await
+                        })
+                    })
+                    .await?;
+                    report.imported += batch.len();
+                }
+                Ok(report)
+            }
+
+            async fn import_products(
+                &self,
+                csv: &[u8],
+                options: &ImportOptions,
+            ) -> Result<ImportReport, ImportError> {
+                let (rows, mut report) = read_rows::<ProductRow>(csv, options)?;
+                if options.dry_run {
+                    return Ok(report);
+                }
+
+                for batch in rows.chunks(options.batch_size.max(1)) {
+                    with_transaction(&self.pool, |tx| {
+                        let actor = options.actor.clone();
+                        let (with_id, new): (Vec<ProductRow>, Vec<ProductRow>) =
+                            batch.iter().cloned().partition(|row| row.id.is_some());
+                        Box::pin(async move {
+                            let mut records = Vec::with_capacity(with_id.len() + new.len());
+                            if !with_id.is_empty() {
+                                let mut select =
+                                    QueryBuilder::new("select * from products where id in (");
+                                let mut ids = select.separated(", ");
+                                for row in &with_id {
+                                    ids.push_bind(row.id);
+                                }
+                                select.push(")").push($lock);
+                                let existing: Vec<Product> =
+                                    select.build_query_as().fetch_all(&mut **tx).await?;
+                                let mut existing: HashMap<ProductId, Product> = existing
+                                    .into_iter()
+                                    .map(|product| (product.id, product))
+                                    .collect();
+
+                                QueryBuilder::new(
+                                    "insert into products(id, name, description, price, stock) ",
+                                )
+                                .push_values(&with_id, |mut row, product| {
+                                    row.push_bind(product.id)
+                                        .push_bind(&product.name)
+                                        .push_bind(&product.description)
+                                        .push_bind(product.price as i64)
+                                        .push_bind(product.stock.unwrap_or_default());
+                                })
+                                .push($upsert_product)
+                                .build()
+                                .execute(&mut **tx)
+                                .await?;
+
+                                records.extend(with_id.iter().map(|row| {
+                                    let mut product = row.to_product(ProductId::default());
+                                    let old = existing.get(&product.id).cloned();
+                                    // the upsert increments the version of an existing row
+                                    if let Some(old) = &old {
+                                        product.version = old.version + 1;
+                                    }
+                                    existing.insert(product.id, product.clone());
+                                    audit_record(&actor, old.as_ref(), &product)
+                                }));
+                            }
+
+                            if !new.is_empty() {
+                                let result = QueryBuilder::new(
+                                    "insert into products(name, description, price, stock) ",
+                                )
+                                .push_values(&new, |mut row, product| {
+                                    row.push_bind(&product.name)
+                                        .push_bind(&product.description)
+                                        .push_bind(product.price as i64)
+                                        .push_bind(product.stock.unwrap_or_default());
+                                })
+                                .build()
+                                .execute(&mut **tx)
+                                .await?;
+                                let ids: IdRange<ProductId> = result.inserted_ids();
+                                records.extend(ids.iter().zip(&new).map(|(id, row)| {
+                                    audit_record(&actor, None, &row.to_product(id))
+                                }));
+                            }
+                            audit::write(&mut **tx, &records).await
+                        })
+                    })
+                    .await?;
+                    report.imported += batch.len();
+                }
+                Ok(report)
+            }
+        }
+    };
+}
+
+importer!(
+    MySqlImporter,
+    MySqlPool,
+    " for update",
+    MYSQL_UPSERT_CATEGORY,
+    MYSQL_UPSERT_PRODUCT
+);
+importer!(
+    SqliteImporter,
+    SqlitePool,
+    "",
+    SQLITE_UPSERT_CATEGORY,
+    SQLITE_UPSERT_PRODUCT
+);
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde_json::{Value, json};
+
+    use super::{ImportOptions, Importer, RejectedRow, SqliteImporter};
+    use crate::{
+        audit::{AuditEntry, AuditLogRepository, SqliteAuditLogRepository},
+        model::{Category, Product, ProductId},
+        testing::TestDatabase,
+    };
+
+    const PRODUCTS: &str = "\
+id,name,description,price,stock
+1,Laptop Pro,Laptop generasi baru,17000000,40
+,Keyboard,Keyboard mekanik,450000,25
+,,Tanpa nama,0,5
+,Monitor,Monitor 24 inch,murah,10
+,Webcam,,350000,
+";
+
+    fn errors(errors: &[(&str, &str)]) -> BTreeMap<String, Vec<String>> {
+        let mut result: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (field, message) in errors {
+            result
+                .entry(field.to_string())
+                .or_default()
+                .push(message.to_string());
+        }
+        result
+    }
+
+    fn actions(entries: &[AuditEntry]) -> Vec<&str> {
+        entries.iter().map(|entry| entry.action.as_str()).collect()
+    }
+
+    async fn products(database: &TestDatabase<sqlx::Sqlite>) -> Vec<Product> {
+        sqlx::query_as("select * from products order by id")
+            .fetch_all(database.pool())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_import_products() -> Result<(), Box<dyn std::error::Error>> {
+        let database = TestDatabase::sqlite().await?;
+        let importer = SqliteImporter::new(database.pool().clone());
+        let options = ImportOptions {
+            batch_size: 2,
+            ..ImportOptions::default()
+        };
+
+        let report = importer
+            .import_products(PRODUCTS.as_bytes(), &options)
+            .await?;
+        assert_eq!((5, 3, 3), (report.total, report.valid, report.imported));
+        assert_eq!(
+            vec![
+                RejectedRow {
+                    line: 4,
+                    errors: errors(&[
+                        ("name", "Name must be between 1 and 255 characters"),
+                        ("price", "Price must be at least 1"),
+                    ]),
+                },
+                RejectedRow {
+                    line: 5,
+                    errors: errors(&[("price", "invalid digit found in string")]),
+                },
+            ],
+            report.rejected
+        );
+
+        let products = products(&database).await;
+        let names: Vec<&str> = products.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(
+            vec!["Laptop Pro", "Mouse Wireless", "Keyboard", "Webcam"],
+            names
+        );
+        assert_eq!(ProductId(1), products[0].id);
+        assert_eq!((17_000_000, 40), (products[0].price, products[0].stock));
+        assert_eq!(0, products[3].stock);
+
+        // The existing product is logged as an update from the row it replaced.
+        let audit_log = SqliteAuditLogRepository::new(database.pool().clone());
+        let entries = audit_log.find_by_entity("products", "1").await?;
+        assert_eq!(vec!["update"], actions(&entries));
+        let changes: Value = serde_json::from_str(&entries[0].changes)?;
+        assert_eq!(
+            json!({
+                "description": {
+                    "old": "Laptop canggih untuk profesional",
+                    "new": "Laptop generasi baru"
+                },
+                "price": { "old": 15_000_000, "new": 17_000_000 },
+                "stock": { "old": 50, "new": 40 },
+                "version": { "old": 0, "new": 1 },
+            }),
+            changes
+        );
+        let entries = audit_log
+            .find_by_entity("products", &products[2].id.to_string())
+            .await?;
+        assert_eq!(vec!["insert"], actions(&entries));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_import_dry_run() -> Result<(), Box<dyn std::error::Error>> {
+        let database = TestDatabase::sqlite().await?;
+        let importer = SqliteImporter::new(database.pool().clone());
+        let before = products(&database).await;
+
+        let options = ImportOptions {
+            dry_run: true,
+            ..ImportOptions::default()
+        };
+        let report = importer
+            .import_products(PRODUCTS.as_bytes(), &options)
+            .await?;
+        assert!(report.dry_run);
+        assert_eq!(
+            (3, 0, 2),
+            (report.valid, report.imported, report.rejected.len())
+        );
+        assert_eq!(before, products(&database).await);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_import_categories() -> Result<(), Box<dyn std::error::Error>> {
+        let database = TestDatabase::sqlite().await?;
+        let importer = SqliteImporter::new(database.pool().clone());
+
+        let csv = "id,name,description\nG,Gadget Baru,Kategori gadget terbaru\nF,Fashion,\n,Tanpa Id,\nK,Kosong\n";
+        let report = importer
+            .import_categories(csv.as_bytes(), &ImportOptions::default())
+            .await?;
+        assert_eq!((4, 2), (report.total, report.imported));
+        assert_eq!(
+            vec![4, 5],
+            report
+                .rejected
+                .iter()
+                .map(|row| row.line)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            errors(&[("id", "ID must be between 1 and 100 characters")]),
+            report.rejected[0].errors
+        );
+        assert!(report.rejected[1].errors.contains_key("__all__"));
+
+        let categories: Vec<Category> = sqlx::query_as("select * from category order by id")
+            .fetch_all(database.pool())
+            .await?;
+        let names: Vec<&str> = categories.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(vec!["Elektronik", "Fashion", "Gadget Baru"], names);
+
+        let audit_log = SqliteAuditLogRepository::new(database.pool().clone());
+        let entries = audit_log.find_by_entity("category", "G").await?;
+        assert_eq!(vec!["update"], actions(&entries));
+        let changes: Value = serde_json::from_str(&entries[0].changes)?;
+        assert_eq!(
+            json!({ "old": "Gadget", "new": "Gadget Baru" }),
+            changes["name"]
+        );
+        let entries = audit_log.find_by_entity("category", "F").await?;
+        assert_eq!(vec!["insert"], actions(&entries));
+
+        let result = importer
+            .import_categories(b"\xff\xfe", &ImportOptions::default())
+            .await;
+        assert!(result.is_err());
+        Ok(())
+    }
+}
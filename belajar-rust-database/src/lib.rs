@@ -0,0 +1,14 @@
+/*
+LIBRARY
+- Selain berisi catatan belajar di `main.rs`, project ini juga menjadi library data access untuk aplikasi MiniPOS
+- Entity dan repository disimpan di library, sehingga bisa digunakan oleh test di project ini maupun project lain
+*/
+
+pub mod audit;
+pub mod export;
+pub mod import;
+pub mod migrate;
+pub mod model;
+pub mod repository;
+pub mod testing;
+pub mod transaction;
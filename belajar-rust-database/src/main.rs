@@ -1,5 +1,51 @@
-fn main() {
-    println!("Hello, world!");
+use std::process::ExitCode;
+
+use belajar_rust_database::migrate::{MIGRATOR, MigrateCommand};
+use sqlx::{Connection, MySqlConnection};
+
+/*
+MIGRATE COMMAND
+- Binary project ini bisa digunakan untuk menjalankan embedded migration, tanpa perlu SQLx-CLI
+- `DATABASE_URL=mysql://... cargo run -- migrate status`
+- `DATABASE_URL=mysql://... cargo run -- migrate up --target-version 20250825045858`
+- `DATABASE_URL=mysql://... cargo run -- migrate down`
+*/
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) != Some("migrate") {
+        println!("Hello, world!");
+        return ExitCode::SUCCESS;
+    }
+
+    let command = match MigrateCommand::parse(&args[1..]) {
+        Ok(command) => command,
+        Err(message) => {
+            eprintln!("{}", message);
+            eprintln!("usage: migrate <status|up|down> [--target-version <version>]");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let Ok(url) = std::env::var("DATABASE_URL") else {
+        eprintln!("DATABASE_URL is not set");
+        return ExitCode::FAILURE;
+    };
+
+    let result = async {
+        let mut connection = MySqlConnection::connect(&url).await?;
+        command.execute(&mut connection, &MIGRATOR).await?;
+        connection.close().await?;
+        Ok::<_, Box<dyn std::error::Error>>(())
+    };
+    match result.await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{}", error);
+            ExitCode::FAILURE
+        }
+    }
 }
 
 /*
@@ -24,18 +70,30 @@ CONNECTION
 mod tests {
     use std::time::Duration;
 
-    use chrono::{DateTime, Utc};
+    use belajar_rust_database::{
+        model::{Brand, Category, Seller, SellerId},
+        repository::{
+            BrandRepository, CategoryRepository, MySqlBrandRepository, MySqlCategoryRepository,
+            MySqlSellerRepository, NewSeller, Page, SellerRepository, SqliteBrandRepository,
+            SqliteCategoryRepository, SqliteSellerRepository,
+        },
+        testing::TestDatabase,
+        transaction::with_transaction,
+    };
+    use chrono::Utc;
     use futures::TryStreamExt;
     use sqlx::{
-        Connection, Error, MySql, MySqlConnection, Pool, Row, Transaction,
+        Connection, Error, MySql, MySqlConnection, Pool, Row, Sqlite, Transaction,
         mysql::{MySqlPoolOptions, MySqlRow},
-        prelude::FromRow,
+        sqlite::SqliteRow,
     };
 
     #[tokio::test]
+    #[ignore = "requires MySQL, set TEST_MYSQL_URL"]
     async fn test_manual_connection() -> Result<(), Error> {
-        let url = "mysql://root:@Zhafir99@localhost:3306/belajar-rust-database";
-        let connection: MySqlConnection = MySqlConnection::connect(url).await?;
+        let database = TestDatabase::mysql().await?;
+        let options = database.pool().connect_options();
+        let connection: MySqlConnection = MySqlConnection::connect_with(&options).await?;
 
         connection.close().await?;
         Ok(())
@@ -56,20 +114,26 @@ mod tests {
     - Kita bisa melakukan banyak pengaturan ketika membuat Database Pool, seperti jumlah minimal dan maksimal koneksi menggunakan MySqlPoolOptions
      */
 
-    async fn get_pool() -> Result<Pool<MySql>, Error> {
-        let url = "mysql://root:@Zhafir99@localhost:3306/belajar-rust-database";
+    // Database nya dibuat oleh `TestDatabase`, sehingga setiap test memiliki database sendiri
+    // Test koneksi, pool, dan auto increment membutuhkan MySQL, test lain nya tidak bergantung pada database tertentu,
+    // sehingga dijalankan di SQLite, dan hanya berbeda di tipe Row nya (`SqliteRow` bukan `MySqlRow`)
+
+    async fn get_pool(database: &TestDatabase<MySql>) -> Result<Pool<MySql>, Error> {
+        let options = database.pool().connect_options();
         MySqlPoolOptions::new()
             .max_connections(10)
             .min_connections(5)
             .acquire_timeout(Duration::from_secs(5))
             .idle_timeout(Duration::from_secs(60))
-            .connect(url)
+            .connect_with((*options).clone())
             .await
     }
 
     #[tokio::test]
+    #[ignore = "requires MySQL, set TEST_MYSQL_URL"]
     async fn test_pool_connection() -> Result<(), Error> {
-        let pool = get_pool().await?;
+        let database = TestDatabase::mysql().await?;
+        let pool = get_pool(&database).await?;
         pool.close().await;
         Ok(())
     }
@@ -85,9 +149,10 @@ mod tests {
 
     #[tokio::test]
     async fn test_execute() -> Result<(), Error> {
-        let pool = get_pool().await?;
+        let database = TestDatabase::sqlite().await?;
+        let pool = database.pool();
         sqlx::query("insert into category(id, name, description) values('A', 'contoh', 'contoh');")
-            .execute(&pool)
+            .execute(pool)
             .await?;
         Ok(())
     }
@@ -101,12 +166,13 @@ mod tests {
 
     #[tokio::test]
     async fn test_prepare_statement() -> Result<(), Error> {
-        let pool = get_pool().await?;
+        let database = TestDatabase::sqlite().await?;
+        let pool = database.pool();
         sqlx::query("insert into category(id, name, description) values(?, ?, ?)")
             .bind("B")
             .bind("contoh lagi")
             .bind("inideskripsi")
-            .execute(&pool)
+            .execute(pool)
             .await?;
         Ok(())
     }
@@ -125,12 +191,14 @@ mod tests {
 
     #[tokio::test]
     async fn test_fetch_optional() -> Result<(), Error> {
-        let pool: Pool<MySql> = get_pool().await?;
-        let result: Option<MySqlRow> = sqlx::query("select * from category where id = ?")
-            .bind("B")
-            .fetch_optional(&pool)
+        let database = TestDatabase::sqlite().await?;
+        let pool: &Pool<Sqlite> = database.pool();
+        let result: Option<SqliteRow> = sqlx::query("select * from category where id = ?")
+            .bind("C")
+            .fetch_optional(pool)
             .await?;
 
+        assert!(result.is_some());
         if let Some(row) = result {
             let id: String = row.get("id");
             let name: String = row.get("name");
@@ -145,10 +213,11 @@ mod tests {
 
     #[tokio::test]
     async fn test_fetch_one() -> Result<(), Error> {
-        let pool: Pool<MySql> = get_pool().await?;
-        let result: MySqlRow = sqlx::query("select * from category where id = ?")
+        let database = TestDatabase::sqlite().await?;
+        let pool: &Pool<Sqlite> = database.pool();
+        let result: SqliteRow = sqlx::query("select * from category where id = ?")
             .bind("C")
-            .fetch_one(&pool)
+            .fetch_one(pool)
             .await?;
 
         let id: String = result.get("id");
@@ -161,9 +230,10 @@ mod tests {
 
     #[tokio::test]
     async fn test_fetch_all() -> Result<(), Error> {
-        let pool: Pool<MySql> = get_pool().await?;
-        let results: Vec<MySqlRow> = sqlx::query("select * from category")
-            .fetch_all(&pool)
+        let database = TestDatabase::sqlite().await?;
+        let pool: &Pool<Sqlite> = database.pool();
+        let results: Vec<SqliteRow> = sqlx::query("select * from category")
+            .fetch_all(pool)
             .await?;
 
         for row in results {
@@ -181,12 +251,14 @@ mod tests {
     - Khusus untuk method `fetch()`, hasil return dari method nya berupa Stream (versi async dari Iterator)
     - Oleh karena itu, kita perlu menambah library Futures untuk mengambil data di Stream tersebut
     - `cargo add futures`
+    - Stream ini juga digunakan module `export` untuk mengubah seluruh isi tabel menjadi CSV atau NDJSON tanpa `fetch_all()`
      */
 
     #[tokio::test]
     async fn test_fetch() -> Result<(), Error> {
-        let pool: Pool<MySql> = get_pool().await?;
-        let mut results = sqlx::query("select * from category").fetch(&pool);
+        let database = TestDatabase::sqlite().await?;
+        let pool: &Pool<Sqlite> = database.pool();
+        let mut results = sqlx::query("select * from category").fetch(pool);
 
         while let Some(result) = results.try_next().await? {
             let id: String = result.get("id");
@@ -204,24 +276,21 @@ mod tests {
     - Untuk mengubah data hasil Query dalam bentuk Row menjadi Struct, kita bisa memanfaatkan method `map()` pada Query
      */
 
-    #[derive(FromRow, Debug)]
-    struct Category {
-        id: String,
-        name: String,
-        description: String,
-    }
+    // Struct Category ada di module `belajar_rust_database::model`, agar bisa digunakan juga oleh repository
 
     #[tokio::test]
     async fn test_result_mapping() -> Result<(), Error> {
-        let pool: Pool<MySql> = get_pool().await?;
+        let database = TestDatabase::sqlite().await?;
+        let pool: &Pool<Sqlite> = database.pool();
         let results: Vec<Category> = sqlx::query("select * from category")
-            .map(|row: MySqlRow| Category {
+            .map(|row: SqliteRow| Category {
                 id: row.get("id"),
                 name: row.get("name"),
                 description: row.get("description"),
             })
-            .fetch_all(&pool)
+            .fetch_all(pool)
             .await?;
+        assert_eq!(2, results.len());
 
         for category in results {
             println!("Category: {:?}", category);
@@ -239,10 +308,18 @@ mod tests {
 
     #[tokio::test]
     async fn test_automatic_result_mapping() -> Result<(), Error> {
-        let pool: Pool<MySql> = get_pool().await?;
+        let database = TestDatabase::sqlite().await?;
+        let pool: &Pool<Sqlite> = database.pool();
         let results: Vec<Category> = sqlx::query_as("select * from category")
-            .fetch_all(&pool)
+            .fetch_all(pool)
             .await?;
+        assert_eq!(
+            vec!["C", "G"],
+            results
+                .iter()
+                .map(|category| category.id.as_str())
+                .collect::<Vec<_>>()
+        );
 
         for category in results {
             println!("Category: {:?}", category);
@@ -257,21 +334,16 @@ mod tests {
     - Tiap database, memiliki jenis data masing - masing, dan bisa berbeda dengan jenis data di Rust
      */
 
-    #[derive(FromRow, Debug)]
-    struct Brand {
-        id: String,
-        name: String,
-        description: String,
-        created_at: DateTime<Utc>,
-        updated_at: DateTime<Utc>,
-    }
+    // Struct Brand ada di module `belajar_rust_database::model`, field created_at dan updated_at menggunakan `DateTime<Utc>`
 
     #[tokio::test]
     async fn test_result_mapping_brand() -> Result<(), Error> {
-        let pool: Pool<MySql> = get_pool().await?;
+        let database = TestDatabase::sqlite().await?;
+        let pool: &Pool<Sqlite> = database.pool();
         let results: Vec<Brand> = sqlx::query_as("select * from brands")
-            .fetch_all(&pool)
+            .fetch_all(pool)
             .await?;
+        assert_eq!(2, results.len());
         for brand in results {
             println!("Brand: {:?}", brand);
         }
@@ -281,14 +353,15 @@ mod tests {
 
     #[tokio::test]
     async fn test_insert_brand() -> Result<(), Error> {
-        let pool: Pool<MySql> = get_pool().await?;
+        let database = TestDatabase::sqlite().await?;
+        let pool: &Pool<Sqlite> = database.pool();
         sqlx::query("insert into brands (id, name, description, created_at, updated_at) values (?, ?, ?, ?, ?)")
             .bind("A")
             .bind("Brand Name")
             .bind("Brand Description")
-            .bind(Utc::now().naive_local())
-            .bind(Utc::now().naive_local())
-            .execute(&pool)
+            .bind(Utc::now())
+            .bind(Utc::now())
+            .execute(pool)
             .await?;
 
         Ok(())
@@ -298,43 +371,65 @@ mod tests {
     TRANSACTION
     - Salah satu fitur yang sangat penting di Database adalah Transaction
     - SQLx juga memiliki fitur yang bisa digunakan untuk membuat database transaction
-    - Kita bisa menggunakan method `begin()` yang menghasilkan object transaction
+    - Kita bisa menggunakan method `begin()` yang menghasilkan object transaction, lalu `commit()` atau `rollback()`
+    - Agar tidak lupa rollback ketika terjadi error, gunakan `with_transaction()` di module `belajar_rust_database::transaction`,
+    yang memanggil `begin()`, lalu `commit()` jika closure nya berhasil atau `rollback()` jika closure nya error
+    - `with_transaction()` juga mengulang transaction ketika terjadi deadlock, dan mendukung nested transaction menggunakan savepoint
      */
-    #[tokio::test]
-    async fn test_transaction() -> Result<(), Error> {
-        let pool: Pool<MySql> = get_pool().await?;
-
-        let mut transaction: Transaction<MySql> = pool.begin().await?;
 
+    async fn insert_brand(
+        transaction: &mut Transaction<'static, Sqlite>,
+        id: &str,
+    ) -> Result<(), Error> {
         sqlx::query("insert into brands(id, name, description, created_at, updated_at) values (?, ?, ?, ?, ?)")
-        .bind("D")
-        .bind("Ini contoh D")
-        .bind("Ini contoh D")
-        .bind(Utc::now().naive_local())
-        .bind(Utc::now().naive_local())
-        .execute(&mut *transaction)
-        .await?;
+            .bind(id)
+            .bind(format!("Ini contoh {}", id))
+            .bind(format!("Ini contoh {}", id))
+            .bind(Utc::now())
+            .bind(Utc::now())
+            .execute(&mut **transaction)
+            .await?;
+        Ok(())
+    }
 
-        sqlx::query("insert into brands(id, name, description, created_at, updated_at) values (?, ?, ?, ?, ?)")
-        .bind("E")
-        .bind("Ini contoh E")
-        .bind("Ini contoh E")
-        .bind(Utc::now().naive_local())
-        .bind(Utc::now().naive_local())
-        .execute(&mut *transaction)
-        .await?;
+    async fn count_brands(pool: &Pool<Sqlite>) -> Result<i64, Error> {
+        sqlx::query_scalar("select count(*) from brands")
+            .fetch_one(pool)
+            .await
+    }
 
-        sqlx::query("insert into brands(id, name, description, created_at, updated_at) values (?, ?, ?, ?, ?)")
-        .bind("A")
-        .bind("Brand Name")
-        .bind("Brand Description")
-        .bind(Utc::now().naive_local())
-        .bind(Utc::now().naive_local())
-        .execute(&mut *transaction)
+    #[tokio::test]
+    async fn test_transaction() -> Result<(), Error> {
+        let database = TestDatabase::sqlite().await?;
+
+        with_transaction(database.pool(), |transaction| {
+            Box::pin(async move {
+                insert_brand(transaction, "D").await?;
+                insert_brand(transaction, "E").await?;
+                insert_brand(transaction, "A").await
+            })
+        })
         .await?;
 
-        transaction.commit().await?;
+        assert_eq!(5, count_brands(database.pool()).await?);
+        Ok(())
+    }
 
+    #[tokio::test]
+    async fn test_transaction_rollback() -> Result<(), Error> {
+        let database = TestDatabase::sqlite().await?;
+
+        // Brand B sudah ada di data awal, sehingga insert kedua gagal dan brand D ikut dibatalkan
+        let result = with_transaction(database.pool(), |transaction| {
+            Box::pin(async move {
+                insert_brand(transaction, "D").await?;
+                insert_brand(transaction, "B").await
+            })
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(2, count_brands(database.pool()).await?);
         Ok(())
     }
 
@@ -347,29 +442,207 @@ mod tests {
     - Namun perlu diingat, untuk dapat nilai Auto Increment, kita harus menggunakan koneksi yang sama pada SQLx,
     jika menggunakan Database Pool, kita bisa saja mendapatkan koneksi yang berbeda
     - Oleh karena itu, kita bisa memanfaatkan juga Transaction, karena akan menggunakan koneksi yang sama
+    - Cara yang lebih mudah, hasil `execute()` di MySQL berupa `MySqlQueryResult` yang sudah memiliki method `last_insert_id()`,
+    sehingga tidak perlu transaction, seperti yang digunakan method insert di repository
      */
 
     #[tokio::test]
+    #[ignore = "requires MySQL, set TEST_MYSQL_URL"]
     async fn test_auto_increment() -> Result<(), Error> {
-        let pool: Pool<MySql> = get_pool().await?;
-        let mut transaction: Transaction<MySql> = pool.begin().await?;
+        let database = TestDatabase::mysql().await?;
+        let pool: Pool<MySql> = get_pool(&database).await?;
+
+        let id: u64 = with_transaction(&pool, |transaction| {
+            Box::pin(async move {
+                sqlx::query("insert into sellers(name) values(?)")
+                    .bind("Seller A")
+                    .execute(&mut **transaction)
+                    .await?;
+
+                let result: MySqlRow = sqlx::query("select last_insert_id() as id")
+                    .fetch_one(&mut **transaction)
+                    .await?;
+                Ok::<_, Error>(result.get_unchecked("id"))
+            })
+        })
+        .await?;
 
-        sqlx::query("insert into sellers(name) values(?)")
-            .bind("Seller A")
-            .execute(&mut *transaction)
-            .await?;
+        // Seller Seed dari data awal memiliki id 1
+        assert_eq!(2, id);
+        println!("Last Inserted ID: {}", id);
+        Ok(())
+    }
 
-        let result: MySqlRow = sqlx::query("select last_insert_id() as id")
-            .fetch_one(&mut *transaction)
-            .await?;
+    /*
+    REPOSITORY
+    - Daripada menulis perintah SQL yang sama berulang - ulang di banyak tempat, kita bisa membungkusnya dalam Repository
+    - Repository untuk Category, Brand, dan Seller ada di module `belajar_rust_database::repository`
+    - Setiap repository memiliki method find_by_id, list, search_by_name, insert, update, dan delete
+    - Method list dan search_by_name mendukung offset pagination (`Page::Offset`) dan keyset pagination (`Page::After`)
+     */
 
-        let id: i32 = result.get_unchecked("id");
-        println!("Last Inserted ID: {}", id);
+    // Test repository ditulis terhadap trait, sehingga bisa dijalankan di SQLite maupun MySQL
+
+    const ACTOR: &str = "test";
+
+    async fn check_category_repository(repository: &dyn CategoryRepository) -> Result<(), Error> {
+        let mut category = Category {
+            id: "REPO".to_string(),
+            name: "Gadget Terbaru".to_string(),
+            description: "Kategori gadget".to_string(),
+        };
+        repository.insert(ACTOR, &category).await?;
 
-        transaction.commit().await?;
+        category.description = "Kategori gadget terbaru".to_string();
+        assert!(repository.update(ACTOR, &category).await?);
+        assert_eq!(Some(category), repository.find_by_id("REPO").await?);
+
+        let categories = repository.search_by_name("gadg", &Page::first(10)).await?;
+        let ids: Vec<&str> = categories
+            .iter()
+            .map(|category| category.id.as_str())
+            .collect();
+        assert_eq!(vec!["G", "REPO"], ids);
+
+        assert!(repository.delete(ACTOR, "REPO").await?);
+        assert_eq!(None, repository.find_by_id("REPO").await?);
+        assert!(!repository.delete(ACTOR, "REPO").await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_category_repository() -> Result<(), Error> {
+        let database = TestDatabase::sqlite().await?;
+        check_category_repository(&SqliteCategoryRepository::new(database.pool().clone())).await
+    }
+
+    #[tokio::test]
+    #[ignore = "requires MySQL, set TEST_MYSQL_URL"]
+    async fn test_category_repository_mysql() -> Result<(), Error> {
+        let database = TestDatabase::mysql().await?;
+        check_category_repository(&MySqlCategoryRepository::new(database.pool().clone())).await
+    }
+
+    async fn check_brand_repository_keyset_page(
+        repository: &dyn BrandRepository,
+    ) -> Result<(), Error> {
+        for id in ["D", "E", "F"] {
+            let brand = Brand {
+                id: id.to_string(),
+                name: format!("Brand {}", id),
+                description: format!("Brand {}", id),
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+            };
+            repository.insert(ACTOR, &brand).await?;
+        }
+
+        let mut ids = Vec::new();
+        let mut after = None;
+        loop {
+            let brands = repository.list(&Page::After { limit: 2, after }).await?;
+            ids.extend(brands.iter().map(|brand| brand.id.clone()));
+            match brands.last() {
+                Some(brand) => after = Some(brand.id.clone()),
+                None => break,
+            }
+        }
+        assert_eq!(vec!["B", "C", "D", "E", "F"], ids);
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_brand_repository_keyset_page() -> Result<(), Error> {
+        let database = TestDatabase::sqlite().await?;
+        check_brand_repository_keyset_page(&SqliteBrandRepository::new(database.pool().clone()))
+            .await
+    }
+
+    #[tokio::test]
+    #[ignore = "requires MySQL, set TEST_MYSQL_URL"]
+    async fn test_brand_repository_keyset_page_mysql() -> Result<(), Error> {
+        let database = TestDatabase::mysql().await?;
+        check_brand_repository_keyset_page(&MySqlBrandRepository::new(database.pool().clone()))
+            .await
+    }
+
+    async fn check_seller_repository(repository: &dyn SellerRepository) -> Result<(), Error> {
+        let id = repository
+            .insert(
+                ACTOR,
+                &NewSeller {
+                    name: "Seller Repository".to_string(),
+                    latitude: Some(-6.17759),
+                    longitude: Some(106.822702),
+                },
+            )
+            .await?;
+        assert_eq!(SellerId(2), id);
+        let seller = Seller {
+            id,
+            name: "Seller Repository".to_string(),
+            latitude: Some(-6.17759),
+            longitude: Some(106.822702),
+        };
+        assert_eq!(Some(seller.clone()), repository.find_by_id(id).await?);
+        assert_eq!(
+            vec![SellerId(1), id],
+            repository
+                .find_by_ids(&[id, SellerId(99), SellerId(1)])
+                .await?
+                .iter()
+                .map(|seller| seller.id)
+                .collect::<Vec<_>>()
+        );
+        assert!(repository.find_by_ids(&[]).await?.is_empty());
+
+        let ids = repository
+            .insert_many(
+                ACTOR,
+                &[
+                    NewSeller {
+                        name: "Seller Bulk A".to_string(),
+                        latitude: None,
+                        longitude: None,
+                    },
+                    NewSeller {
+                        name: "Seller Bulk B".to_string(),
+                        latitude: None,
+                        longitude: None,
+                    },
+                ],
+            )
+            .await?;
+        assert_eq!(
+            vec![SellerId(3), SellerId(4)],
+            ids.iter().collect::<Vec<_>>()
+        );
+
+        let sellers = repository
+            .list(&Page::After {
+                limit: 1,
+                after: Some(SellerId(1)),
+            })
+            .await?;
+        assert_eq!(vec![seller], sellers);
+
+        assert!(repository.delete(ACTOR, id).await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_seller_repository() -> Result<(), Error> {
+        let database = TestDatabase::sqlite().await?;
+        check_seller_repository(&SqliteSellerRepository::new(database.pool().clone())).await
+    }
+
+    #[tokio::test]
+    #[ignore = "requires MySQL, set TEST_MYSQL_URL"]
+    async fn test_seller_repository_mysql() -> Result<(), Error> {
+        let database = TestDatabase::mysql().await?;
+        check_seller_repository(&MySqlSellerRepository::new(database.pool().clone())).await
+    }
+
     /*
     DATABASE MIGRATION
     - SQLx memiliki fitur bernama database migration, yang bisa kita gunakan untuk melakukan management versi skema perubahan database
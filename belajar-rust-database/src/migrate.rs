@@ -0,0 +1,489 @@
+use std::collections::{BTreeMap, HashMap};
+
+use sqlx::migrate::{AppliedMigration, Migrate, MigrateError, Migration, Migrator};
+
+/*
+EMBEDDED MIGRATION
+- Macro `sqlx::migrate!()` membaca folder migrations saat kompilasi, lalu menyimpan semua file migration di dalam binary
+- Dengan begitu, aplikasi bisa menjalankan migration sendiri tanpa perlu menginstall SQLx-CLI di server
+- Module ini menyediakan perintah yang sama dengan SQLx-CLI:
+- `migrate status` => menampilkan status setiap migration
+- `migrate up [--target-version <version>]` => sama seperti `sqlx migrate run`
+- `migrate down [--target-version <version>]` => sama seperti `sqlx migrate revert`
+- `--target-version` harus versi migration yang ada di binary (khusus `down`, versi `0` berarti revert semua),
+versi yang salah ketik ditolak sebelum ada migration yang dijalankan
+
+SCHEMA DRIFT
+- Drift terjadi jika migration yang sudah dijalankan di database tidak ada di binary (database lebih baru dari aplikasi),
+atau isi file migration nya berubah setelah dijalankan (checksum berbeda)
+- Aplikasi sebaiknya menolak berjalan jika terjadi drift, karena skema database tidak sesuai dengan yang diharapkan kode
+- Karena itu, file migration yang sudah pernah dijalankan tidak boleh diubah, perubahan skema selalu dibuat di file migration baru
+- `build.rs` memastikan binary dikompilasi ulang ketika ada file migration baru
+*/
+
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MigrationState {
+    Pending,
+    Applied,
+    /// Applied, but the embedded file has changed since.
+    Modified,
+    /// Applied in the database, but not embedded in this binary.
+    Missing,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub state: MigrationState,
+}
+
+fn up_migrations(migrator: &Migrator) -> impl Iterator<Item = &Migration> {
+    migrator
+        .iter()
+        .filter(|migration| !migration.migration_type.is_down_migration())
+}
+
+async fn applied_migrations<C: Migrate>(
+    conn: &mut C,
+) -> Result<HashMap<i64, AppliedMigration>, MigrateError> {
+    conn.ensure_migrations_table().await?;
+    if let Some(version) = conn.dirty_version().await? {
+        return Err(MigrateError::Dirty(version));
+    }
+
+    let applied = conn.list_applied_migrations().await?;
+    Ok(applied
+        .into_iter()
+        .map(|migration| (migration.version, migration))
+        .collect())
+}
+
+pub async fn status<C: Migrate>(
+    conn: &mut C,
+    migrator: &Migrator,
+) -> Result<Vec<MigrationStatus>, MigrateError> {
+    let mut applied = applied_migrations(conn).await?;
+    let mut result = BTreeMap::new();
+
+    for migration in up_migrations(migrator) {
+        let state = match applied.remove(&migration.version) {
+            Some(applied) if applied.checksum == migration.checksum => MigrationState::Applied,
+            Some(_) => MigrationState::Modified,
+            None => MigrationState::Pending,
+        };
+        result.insert(
+            migration.version,
+            MigrationStatus {
+                version: migration.version,
+                description: migration.description.to_string(),
+                state,
+            },
+        );
+    }
+
+    for version in applied.into_keys() {
+        result.insert(
+            version,
+            MigrationStatus {
+                version,
+                description: String::new(),
+                state: MigrationState::Missing,
+            },
+        );
+    }
+
+    Ok(result.into_values().collect())
+}
+
+/// Fails with `VersionMissing` when the database has migrations this binary
+/// does not know about, or `VersionMismatch` when an applied migration was
+/// modified afterwards.
+pub async fn check_drift<C: Migrate>(
+    conn: &mut C,
+    migrator: &Migrator,
+) -> Result<(), MigrateError> {
+    for migration in status(conn, migrator).await? {
+        match migration.state {
+            MigrationState::Missing => return Err(MigrateError::VersionMissing(migration.version)),
+            MigrationState::Modified => {
+                return Err(MigrateError::VersionMismatch(migration.version));
+            }
+            MigrationState::Pending | MigrationState::Applied => {}
+        }
+    }
+    Ok(())
+}
+
+/// Applies pending migrations up to and including `target`, or all of them
+/// when `target` is `None`. Returns the applied versions.
+pub async fn up<C: Migrate>(
+    conn: &mut C,
+    migrator: &Migrator,
+    target: Option<i64>,
+) -> Result<Vec<i64>, MigrateError> {
+    if let Some(target) = target
+        && !migrator.version_exists(target)
+    {
+        return Err(MigrateError::VersionNotPresent(target));
+    }
+
+    if migrator.locking {
+        conn.lock().await?;
+    }
+    let result = apply_up(conn, migrator, target).await;
+    if migrator.locking {
+        conn.unlock().await?;
+    }
+    result
+}
+
+async fn apply_up<C: Migrate>(
+    conn: &mut C,
+    migrator: &Migrator,
+    target: Option<i64>,
+) -> Result<Vec<i64>, MigrateError> {
+    let applied = applied_migrations(conn).await?;
+    if !migrator.ignore_missing
+        && let Some(version) = applied
+            .keys()
+            .find(|version| !migrator.version_exists(**version))
+    {
+        return Err(MigrateError::VersionMissing(*version));
+    }
+
+    let mut versions = Vec::new();
+    for migration in up_migrations(migrator) {
+        if target.is_some_and(|target| migration.version > target) {
+            break;
+        }
+        match applied.get(&migration.version) {
+            Some(applied) if applied.checksum != migration.checksum => {
+                return Err(MigrateError::VersionMismatch(migration.version));
+            }
+            Some(_) => {}
+            None => {
+                conn.apply(migration).await?;
+                versions.push(migration.version);
+            }
+        }
+    }
+    Ok(versions)
+}
+
+/// Reverts applied migrations newer than `target`, or only the latest one
+/// when `target` is `None`. `target` must be an embedded version, or `0` to
+/// revert everything. Returns the reverted versions.
+pub async fn down<C: Migrate>(
+    conn: &mut C,
+    migrator: &Migrator,
+    target: Option<i64>,
+) -> Result<Vec<i64>, MigrateError> {
+    if let Some(target) = target
+        && target != 0
+        && !migrator.version_exists(target)
+    {
+        return Err(MigrateError::VersionNotPresent(target));
+    }
+
+    if migrator.locking {
+        conn.lock().await?;
+    }
+    let result = apply_down(conn, migrator, target).await;
+    if migrator.locking {
+        conn.unlock().await?;
+    }
+    result
+}
+
+async fn apply_down<C: Migrate>(
+    conn: &mut C,
+    migrator: &Migrator,
+    target: Option<i64>,
+) -> Result<Vec<i64>, MigrateError> {
+    let applied = applied_migrations(conn).await?;
+    let target = match target {
+        Some(target) => target,
+        None => {
+            let mut versions: Vec<i64> = applied.keys().copied().collect();
+            versions.sort();
+            match versions.len() {
+                0 => return Ok(Vec::new()),
+                1 => 0,
+                len => versions[len - 2],
+            }
+        }
+    };
+
+    let mut versions = Vec::new();
+    for migration in migrator
+        .iter()
+        .rev()
+        .filter(|migration| migration.migration_type.is_down_migration())
+        .filter(|migration| migration.version > target)
+    {
+        if applied.contains_key(&migration.version) {
+            conn.revert(migration).await?;
+            versions.push(migration.version);
+        }
+    }
+    Ok(versions)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MigrateCommand {
+    Status,
+    Up { target: Option<i64> },
+    Down { target: Option<i64> },
+}
+
+impl MigrateCommand {
+    /// Parses the arguments after `migrate`, e.g. `up --target-version 20250825045847`.
+    pub fn parse<I, S>(args: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut args = args.into_iter();
+        let command = args
+            .next()
+            .ok_or("missing migrate command, expected status, up, or down")?;
+
+        let mut target = None;
+        while let Some(arg) = args.next() {
+            match arg.as_ref() {
+                "--target-version" => {
+                    let value = args.next().ok_or("missing value for --target-version")?;
+                    let version = value
+                        .as_ref()
+                        .parse()
+                        .map_err(|_| format!("invalid target version: {}", value.as_ref()))?;
+                    target = Some(version);
+                }
+                other => return Err(format!("unknown argument: {}", other)),
+            }
+        }
+
+        match command.as_ref() {
+            "status" if target.is_none() => Ok(MigrateCommand::Status),
+            "status" => Err("status does not accept --target-version".to_string()),
+            "up" | "run" => Ok(MigrateCommand::Up { target }),
+            "down" | "revert" => Ok(MigrateCommand::Down { target }),
+            other => Err(format!("unknown migrate command: {}", other)),
+        }
+    }
+
+    /// Runs the command and prints the result, like `sqlx migrate` does.
+    pub async fn execute<C: Migrate>(
+        &self,
+        conn: &mut C,
+        migrator: &Migrator,
+    ) -> Result<(), MigrateError> {
+        match self {
+            MigrateCommand::Status => {
+                for migration in status(conn, migrator).await? {
+                    println!(
+                        "{}/{:?} {}",
+                        migration.version, migration.state, migration.description
+                    );
+                }
+            }
+            MigrateCommand::Up { target } => {
+                for version in up(conn, migrator, *target).await? {
+                    println!("Applied {}", version);
+                }
+            }
+            MigrateCommand::Down { target } => {
+                for version in down(conn, migrator, *target).await? {
+                    println!("Reverted {}", version);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::{
+        Connection, SqliteConnection,
+        migrate::{MigrateError, Migrator},
+    };
+
+    use super::{MIGRATOR, MigrateCommand, MigrationState, check_drift, down, status, up};
+
+    /// Reversible SQLite migrations 1, 2 and 3, the SQLite schema of the app
+    /// has no down migrations.
+    static FIXTURE: Migrator = sqlx::migrate!("./fixtures/migrations");
+
+    async fn connect() -> Result<SqliteConnection, sqlx::Error> {
+        SqliteConnection::connect("sqlite::memory:").await
+    }
+
+    async fn tables(conn: &mut SqliteConnection) -> Result<Vec<String>, sqlx::Error> {
+        sqlx::query_scalar(
+            "select name from sqlite_master where type = 'table' and name != '_sqlx_migrations' order by name",
+        )
+        .fetch_all(conn)
+        .await
+    }
+
+    async fn states(
+        conn: &mut SqliteConnection,
+    ) -> Result<Vec<(i64, MigrationState)>, MigrateError> {
+        Ok(status(conn, &FIXTURE)
+            .await?
+            .into_iter()
+            .map(|migration| (migration.version, migration.state))
+            .collect())
+    }
+
+    #[test]
+    fn test_embedded_migrations() {
+        let versions: Vec<i64> = MIGRATOR
+            .iter()
+            .filter(|migration| migration.migration_type.is_up_migration())
+            .map(|migration| migration.version)
+            .collect();
+        assert_eq!(
+            vec![
+                20250825045847,
+                20250825045858,
+                20250902080000,
+                20250902080100,
+                20250902080200,
+                20250902080300,
+                20250902080400,
+                20250902080500,
+                20250905080000,
+                20250905080100,
+                20250906080000,
+                20250907080000,
+                20250908080000,
+                20250909080000,
+            ],
+            versions
+        );
+    }
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(
+            Ok(MigrateCommand::Status),
+            MigrateCommand::parse(["status"])
+        );
+        assert_eq!(
+            Ok(MigrateCommand::Up { target: None }),
+            MigrateCommand::parse(["up"])
+        );
+        assert_eq!(
+            Ok(MigrateCommand::Down {
+                target: Some(20250825045847)
+            }),
+            MigrateCommand::parse(["down", "--target-version", "20250825045847"])
+        );
+        assert!(MigrateCommand::parse(Vec::<String>::new()).is_err());
+        assert!(MigrateCommand::parse(["up", "--target-version"]).is_err());
+        assert!(MigrateCommand::parse(["up", "--target-version", "abc"]).is_err());
+        assert!(MigrateCommand::parse(["drop"]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_up() -> Result<(), Box<dyn std::error::Error>> {
+        let mut conn = connect().await?;
+
+        assert_eq!(vec![1, 2], up(&mut conn, &FIXTURE, Some(2)).await?);
+        assert_eq!(vec!["customers", "orders"], tables(&mut conn).await?);
+        assert_eq!(
+            vec![
+                (1, MigrationState::Applied),
+                (2, MigrationState::Applied),
+                (3, MigrationState::Pending),
+            ],
+            states(&mut conn).await?
+        );
+
+        assert_eq!(vec![3], up(&mut conn, &FIXTURE, None).await?);
+        assert!(up(&mut conn, &FIXTURE, None).await?.is_empty());
+        assert!(matches!(
+            up(&mut conn, &FIXTURE, Some(4)).await,
+            Err(MigrateError::VersionNotPresent(4))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_down() -> Result<(), Box<dyn std::error::Error>> {
+        let mut conn = connect().await?;
+        up(&mut conn, &FIXTURE, None).await?;
+
+        assert_eq!(vec![3, 2], down(&mut conn, &FIXTURE, Some(1)).await?);
+        assert_eq!(vec!["customers"], tables(&mut conn).await?);
+        assert_eq!(
+            vec![
+                (1, MigrationState::Applied),
+                (2, MigrationState::Pending),
+                (3, MigrationState::Pending),
+            ],
+            states(&mut conn).await?
+        );
+
+        assert_eq!(vec![1], down(&mut conn, &FIXTURE, None).await?);
+        assert!(down(&mut conn, &FIXTURE, None).await?.is_empty());
+        assert!(tables(&mut conn).await?.is_empty());
+
+        up(&mut conn, &FIXTURE, None).await?;
+        assert_eq!(vec![3, 2, 1], down(&mut conn, &FIXTURE, Some(0)).await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_down_unknown_target() -> Result<(), Box<dyn std::error::Error>> {
+        let mut conn = connect().await?;
+        up(&mut conn, &FIXTURE, None).await?;
+
+        assert!(matches!(
+            down(&mut conn, &FIXTURE, Some(20)).await,
+            Err(MigrateError::VersionNotPresent(20))
+        ));
+        assert_eq!(vec!["customers", "orders"], tables(&mut conn).await?);
+        assert!(
+            states(&mut conn)
+                .await?
+                .iter()
+                .all(|(_, state)| *state == MigrationState::Applied)
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_check_drift() -> Result<(), Box<dyn std::error::Error>> {
+        let mut conn = connect().await?;
+        up(&mut conn, &FIXTURE, Some(2)).await?;
+        check_drift(&mut conn, &FIXTURE).await?;
+
+        sqlx::query("update _sqlx_migrations set checksum = x'00' where version = 2")
+            .execute(&mut conn)
+            .await?;
+        assert_eq!((2, MigrationState::Modified), states(&mut conn).await?[1]);
+        assert!(matches!(
+            check_drift(&mut conn, &FIXTURE).await,
+            Err(MigrateError::VersionMismatch(2))
+        ));
+        assert!(matches!(
+            up(&mut conn, &FIXTURE, None).await,
+            Err(MigrateError::VersionMismatch(2))
+        ));
+
+        sqlx::query("update _sqlx_migrations set version = 20 where version = 2")
+            .execute(&mut conn)
+            .await?;
+        assert!(matches!(
+            check_drift(&mut conn, &FIXTURE).await,
+            Err(MigrateError::VersionMissing(20))
+        ));
+        Ok(())
+    }
+}
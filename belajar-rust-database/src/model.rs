@@ -0,0 +1,203 @@
+use std::fmt;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{
+    Database, Decode, Encode, MySql, Sqlite, Type, encode::IsNull, error::BoxDynError,
+    prelude::FromRow,
+};
+
+/*
+ENTITY
+- Entity adalah representasi satu baris tabel di database dalam bentuk struct
+- Entity menggunakan derive FromRow, sehingga bisa digunakan oleh `query_as()`
+
+ENTITY ID
+- Id auto increment dibungkus dalam newtype, misal `SellerId` dan `ProductId`, sehingga id milik entity lain tidak bisa tertukar
+- Newtype tersebut bisa langsung digunakan di `bind()` dan FromRow, baik di MySQL (`bigint unsigned`) maupun SQLite (`integer`)
+*/
+
+/// Declares a `u64` id newtype that binds and decodes on MySQL and SQLite.
+/// SQLite cannot bind `u64`, so the id is bound as `i64` there.
+macro_rules! entity_id {
+    ($name:ident) => {
+        #[derive(
+            Debug,
+            Clone,
+            Copy,
+            PartialEq,
+            Eq,
+            PartialOrd,
+            Ord,
+            Hash,
+            Default,
+            Serialize,
+            Deserialize,
+        )]
+        #[serde(transparent)]
+        pub struct $name(pub u64);
+
+        impl From<u64> for $name {
+            fn from(id: u64) -> Self {
+                $name(id)
+            }
+        }
+
+        impl From<$name> for u64 {
+            fn from(id: $name) -> Self {
+                id.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}", self.0)
+            }
+        }
+
+        impl Type<MySql> for $name {
+            fn type_info() -> <MySql as Database>::TypeInfo {
+                <u64 as Type<MySql>>::type_info()
+            }
+
+            fn compatible(ty: &<MySql as Database>::TypeInfo) -> bool {
+                <u64 as Type<MySql>>::compatible(ty)
+            }
+        }
+
+        impl Encode<'_, MySql> for $name {
+            fn encode_by_ref(
+                &self,
+                buf: &mut <MySql as Database>::ArgumentBuffer<'_>,
+            ) -> Result<IsNull, BoxDynError> {
+                <u64 as Encode<MySql>>::encode_by_ref(&self.0, buf)
+            }
+        }
+
+        impl<'r> Decode<'r, MySql> for $name {
+            fn decode(value: <MySql as Database>::ValueRef<'r>) -> Result<Self, BoxDynError> {
+                <u64 as Decode<MySql>>::decode(value).map($name)
+            }
+        }
+
+        impl Type<Sqlite> for $name {
+            fn type_info() -> <Sqlite as Database>::TypeInfo {
+                <u64 as Type<Sqlite>>::type_info()
+            }
+
+            fn compatible(ty: &<Sqlite as Database>::TypeInfo) -> bool {
+                <u64 as Type<Sqlite>>::compatible(ty)
+            }
+        }
+
+        impl<'q> Encode<'q, Sqlite> for $name {
+            fn encode_by_ref(
+                &self,
+                buf: &mut <Sqlite as Database>::ArgumentBuffer<'q>,
+            ) -> Result<IsNull, BoxDynError> {
+                <i64 as Encode<Sqlite>>::encode(i64::try_from(self.0)?, buf)
+            }
+        }
+
+        impl<'r> Decode<'r, Sqlite> for $name {
+            fn decode(value: <Sqlite as Database>::ValueRef<'r>) -> Result<Self, BoxDynError> {
+                <u64 as Decode<Sqlite>>::decode(value).map($name)
+            }
+        }
+    };
+}
+
+entity_id!(SellerId);
+entity_id!(ProductId);
+entity_id!(TransactionId);
+entity_id!(UserId);
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Category {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+}
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Brand {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Seller {
+    pub id: SellerId,
+    pub name: String,
+    /// Used by the nearby seller search, `None` when the location is unknown.
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+}
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct User {
+    pub id: UserId,
+    pub name: String,
+    pub email: String,
+    /// Argon2 hash of the password, never serialized so it stays out of
+    /// responses and the audit log.
+    #[serde(skip_serializing, default)]
+    pub password: String,
+}
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Product {
+    pub id: ProductId,
+    pub name: String,
+    pub description: String,
+    pub price: u64,
+    pub stock: u32,
+    /// Incremented on every change, see `ProductRepository::update`.
+    pub version: u32,
+}
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Transaction {
+    pub id: TransactionId,
+    pub total: u64,
+    pub created_at: DateTime<Utc>,
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub items: Vec<TransactionItem>,
+}
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TransactionItem {
+    pub product_id: ProductId,
+    pub quantity: u32,
+    pub price: u64,
+    pub subtotal: u64,
+}
+
+/// One sold item together with its transaction, used by the sales export.
+#[derive(FromRow, Serialize, Debug, Clone, PartialEq)]
+pub struct SaleLine {
+    pub transaction_id: TransactionId,
+    pub created_at: DateTime<Utc>,
+    pub product_id: ProductId,
+    pub product_name: String,
+    pub quantity: u32,
+    pub price: u64,
+    pub subtotal: u64,
+}
+
+/// Unique visitors of one route on one day, [`DailyVisitors::ALL_ROUTES`]
+/// counts every route together.
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DailyVisitors {
+    pub date: NaiveDate,
+    pub route: String,
+    pub visitors: u64,
+}
+
+impl DailyVisitors {
+    pub const ALL_ROUTES: &str = "*";
+}
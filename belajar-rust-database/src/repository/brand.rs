@@ -0,0 +1,149 @@
+use async_trait::async_trait;
+use chrono::{SubsecRound, Utc};
+use sqlx::{Error, MySqlPool, SqlitePool};
+
+use super::{Page, select_page};
+use crate::{
+    audit::{self, AuditAction, AuditRecord},
+    model::Brand,
+    transaction::with_transaction,
+};
+
+#[async_trait]
+pub trait BrandRepository: Send + Sync {
+    async fn find_by_id(&self, id: &str) -> Result<Option<Brand>, Error>;
+    async fn list(&self, page: &Page<String>) -> Result<Vec<Brand>, Error>;
+    async fn search_by_name(&self, name: &str, page: &Page<String>) -> Result<Vec<Brand>, Error>;
+    async fn insert(&self, actor: &str, brand: &Brand) -> Result<(), Error>;
+    /// Updates name and description and bumps `updated_at`. Returns `false`
+    /// when no brand has the given id.
+    async fn update(&self, actor: &str, brand: &Brand) -> Result<bool, Error>;
+    /// Soft deletes the brand. Returns `false` when no brand has the given id.
+    async fn delete(&self, actor: &str, id: &str) -> Result<bool, Error>;
+}
+
+/// Implements the repository for one backend, MySQL and SQLite share the same
+/// SQL. `$lock` locks the row read before a change, SQLite has no row locks.
+macro_rules! brand_repository {
+    ($name:ident, $pool:ty, $lock:literal) => {
+        #[derive(Clone)]
+        pub struct $name {
+            pool: $pool,
+        }
+
+        impl $name {
+            pub fn new(pool: $pool) -> Self {
+                $name { pool }
+            }
+        }
+
+        #[async_trait]
+        impl BrandRepository for $name {
+            async fn find_by_id(&self, id: &str) -> Result<Option<Brand>, Error> {
+                sqlx::query_as("select * from brands where id = ? and deleted_at is null")
+                    .bind(id)
+                    .fetch_optional(&self.pool)
+                    .await
+            }
+
+            async fn list(&self, page: &Page<String>) -> Result<Vec<Brand>, Error> {
+                select_page("brands", "id", None, page)
+                    .build_query_as()
+                    .fetch_all(&self.pool)
+                    .await
+            }
+
+            async fn search_by_name(&self, name: &str, page: &Page<String>) -> Result<Vec<Brand>, Error> {
+                select_page("brands", "id", Some(name), page)
+                    .build_query_as()
+                    .fetch_all(&self.pool)
+                    .await
+            }
+
+            async fn insert(&self, actor: &str, brand: &Brand) -> Result<(), Error> {
+                with_transaction(&self.pool, |tx| {
+                    let brand = brand.clone();
+                    let record = AuditRecord::new(actor, AuditAction::Insert, None, Some(&brand));
+                    Box::pin(async move {
+                        sqlx::query(
+                            "insert into brands(id, name, description, created_at, updated_at) values (?, ?, ?, ?, ?)",
+                        )
+                        .bind(&brand.id)
+                        .bind(&brand.name)
+                        .bind(&brand.description)
+                        .bind(brand.created_at)
+                        .bind(brand.updated_at)
+                        .execute(&mut **tx)
+                        .await?;
+                        audit::write(&mut **tx, &[record]).await
+                    })
+                })
+                .await
+            }
+
+            async fn update(&self, actor: &str, brand: &Brand) -> Result<bool, Error> {
+                with_transaction(&self.pool, |tx| {
+                    let (actor, mut brand) = (actor.to_string(), brand.clone());
+                    Box::pin(async move {
+                        let old: Option<Brand> = sqlx::query_as(concat!(
+                            "select * from brands where id = ? and deleted_at is null",
+                            $lock
+                        ))
+                        .bind(&brand.id)
+                        .fetch_optional(&mut **tx)
+                        .await?;
+                        let Some(old) = old else {
+                            return Ok(false);
+                        };
+
+                        brand.created_at = old.created_at;
+                        brand.updated_at = Utc::now().trunc_subsecs(0);
+                        sqlx::query("update brands set name = ?, description = ?, updated_at = ? where id = ?")
+                            .bind(&brand.name)
+                            .bind(&brand.description)
+                            .bind(brand.updated_at)
+                            .bind(&brand.id)
+                            .execute(&mut **tx)
+                            .await?;
+                        let record =
+                            AuditRecord::new(&actor, AuditAction::Update, Some(&old), Some(&brand));
+                        audit::write(&mut **tx, &[record]).await?;
+                        Ok(true)
+                    })
+                })
+                .await
+            }
+
+            async fn delete(&self, actor: &str, id: &str) -> Result<bool, Error> {
+                with_transaction(&self.pool, |tx| {
+                    let (actor, id) = (actor.to_string(), id.to_string());
+                    Box::pin(async move {
+                        let old: Option<Brand> = sqlx::query_as(concat!(
+                            "select * from brands where id = ? and deleted_at is null",
+                            $lock
+                        ))
+                        .bind(&id)
+                        .fetch_optional(&mut **tx)
+                        .await?;
+                        let Some(old) = old else {
+                            return Ok(false);
+                        };
+
+                        sqlx::query("update brands set deleted_at = ? where id = ?")
+                            .bind(Utc::now())
+                            .bind(&id)
+                            .execute(&mut **tx)
+                            .await?;
+                        let record = AuditRecord::new(&actor, AuditAction::Delete, Some(&old), None);
+                        audit::write(&mut **tx, &[record]).await?;
+                        Ok(true)
+                    })
+                })
+                .await
+            }
+        }
+    };
+}
+
+brand_repository!(MySqlBrandRepository, MySqlPool, " for update");
+brand_repository!(SqliteBrandRepository, SqlitePool, "");
@@ -0,0 +1,153 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use sqlx::{Error, MySqlPool, SqlitePool};
+
+use super::{Page, select_page};
+use crate::{
+    audit::{self, AuditAction, AuditRecord},
+    model::Category,
+    transaction::with_transaction,
+};
+
+#[async_trait]
+pub trait CategoryRepository: Send + Sync {
+    async fn find_by_id(&self, id: &str) -> Result<Option<Category>, Error>;
+    async fn list(&self, page: &Page<String>) -> Result<Vec<Category>, Error>;
+    async fn search_by_name(&self, name: &str, page: &Page<String>)
+    -> Result<Vec<Category>, Error>;
+    async fn insert(&self, actor: &str, category: &Category) -> Result<(), Error>;
+    /// Returns `false` when no category has the given id.
+    async fn update(&self, actor: &str, category: &Category) -> Result<bool, Error>;
+    /// Soft deletes the category. Returns `false` when no category has the
+    /// given id.
+    async fn delete(&self, actor: &str, id: &str) -> Result<bool, Error>;
+}
+
+/// Implements the repository for one backend, MySQL and SQLite share the same
+/// SQL. `$lock` locks the row read before a change, SQLite has no row locks.
+macro_rules! category_repository {
+    ($name:ident, $pool:ty, $lock:literal) => {
+        #[derive(Clone)]
+        pub struct $name {
+            pool: $pool,
+        }
+
+        impl $name {
+            pub fn new(pool: $pool) -> Self {
+                $name { pool }
+            }
+        }
+
+        #[async_trait]
+        impl CategoryRepository for $name {
+            async fn find_by_id(&self, id: &str) -> Result<Option<Category>, Error> {
+                sqlx::query_as("select * from category where id = ? and deleted_at is null")
+                    .bind(id)
+                    .fetch_optional(&self.pool)
+                    .await
+            }
+
+            async fn list(&self, page: &Page<String>) -> Result<Vec<Category>, Error> {
+                select_page("category", "id", None, page)
+                    .build_query_as()
+                    .fetch_all(&self.pool)
+                    .await
+            }
+
+            async fn search_by_name(
+                &self,
+                name: &str,
+                page: &Page<String>,
+            ) -> Result<Vec<Category>, Error> {
+                select_page("category", "id", Some(name), page)
+                    .build_query_as()
+                    .fetch_all(&self.pool)
+                    .await
+            }
+
+            async fn insert(&self, actor: &str, category: &Category) -> Result<(), Error> {
+                with_transaction(&self.pool, |tx| {
+                    let category = category.clone();
+                    let record =
+                        AuditRecord::new(actor, AuditAction::Insert, None, Some(&category));
+                    Box::pin(async move {
+                        sqlx::query("insert into category(id, name, description) values (?, ?, ?)")
+                            .bind(&category.id)
+                            .bind(&category.name)
+                            .bind(&category.description)
+                            .execute(&mut **tx)
+                            .await?;
+                        audit::write(&mut **tx, &[record]).await
+                    })
+                })
+                .await
+            }
+
+            async fn update(&self, actor: &str, category: &Category) -> Result<bool, Error> {
+                with_transaction(&self.pool, |tx| {
+                    let (actor, category) = (actor.to_string(), category.clone());
+                    Box::pin(async move {
+                        let old: Option<Category> = sqlx::query_as(concat!(
+                            "select * from category where id = ? and deleted_at is null",
+                            $lock
+                        ))
+                        .bind(&category.id)
+                        .fetch_optional(&mut **tx)
+                        .await?;
+                        let Some(old) = old else {
+                            return Ok(false);
+                        };
+
+                        sqlx::query("update category set name = ?, description = ? where id = ?")
+                            .bind(&category.name)
+                            .bind(&category.description)
+                            .bind(&category.id)
+                            .execute(&mut **tx)
+                            .await?;
+                        let record = AuditRecord::new(
+                            &actor,
+                            AuditAction::Update,
+                            Some(&old),
+                            Some(&category),
+                        );
+                        audit::write(&mut **tx, &[record]).await?;
+                        Ok(true)
+                    })
+                })
+                .await
+            }
+
+            async fn delete(&self, actor: &str, id: &str) -> Result<bool, Error> {
+                with_transaction(&self.pool, |tx| {
+                    let (actor, id) = (actor.to_string(), id.to_string());
+                    Box::pin(async move {
+                        let old: Option<Category> = sqlx::query_as(concat!(
+                            "select * from category where id = ? and deleted_at is null",
+                            $lock
+                        ))
+                        .bind(&id)
+                        .fetch_optional(&mut **tx)
+                        .await?;
+                        let Some(old) = old else {
+                            return Ok(false);
+                        };
+
+                        sqlx::query("update category set deleted_at = ? where id = ?")
+                            .bind(Utc::now())
+                            .bind(&id)
+                            .execute(&mut **tx)
+                            .await?;
+                        let record =
+                            AuditRecord::new(&actor, AuditAction::Delete, Some(&old), None);
+                        audit::write(&mut **tx, &[record]).await?;
+                        Ok(true)
+                    })
+                })
+                .await
+            }
+        }
+    };
+}
+
+category_repository!(MySqlCategoryRepository, MySqlPool, " for update");
+category_repository!(SqliteCategoryRepository, SqlitePool, "");
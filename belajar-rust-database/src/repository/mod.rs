@@ -0,0 +1,237 @@
+use sqlx::{
+    Database, Encode, QueryBuilder, Type, mysql::MySqlQueryResult, sqlite::SqliteQueryResult,
+};
+
+mod brand;
+mod category;
+mod product;
+mod seller;
+mod transaction;
+mod user;
+mod visitor;
+
+pub use brand::{BrandRepository, MySqlBrandRepository, SqliteBrandRepository};
+pub use category::{CategoryRepository, MySqlCategoryRepository, SqliteCategoryRepository};
+pub use product::{
+    MySqlProductRepository, NewProduct, ProductRepository, SqliteProductRepository,
+    UpdateProductError,
+};
+pub use seller::{MySqlSellerRepository, NewSeller, SellerRepository, SqliteSellerRepository};
+pub use transaction::{
+    CheckoutError, MySqlTransactionRepository, NewTransactionItem, SqliteTransactionRepository,
+    TransactionRepository,
+};
+pub use user::{MySqlUserRepository, NewUser, SqliteUserRepository, UserError, UserRepository};
+pub use visitor::{MySqlVisitorRepository, SqliteVisitorRepository, VisitorRepository};
+
+/*
+REPOSITORY
+- Repository adalah lapisan yang membungkus semua perintah SQL untuk satu entity
+- Setiap entity memiliki trait repository sendiri, dan implementasi MySQL nya menggunakan Database Pool
+- Dengan trait, handler maupun test cukup bergantung pada trait, bukan pada perintah SQL nya langsung
+- Selain MySQL, setiap repository juga memiliki implementasi SQLite dengan perintah SQL yang sama,
+sehingga test bisa berjalan tanpa server database (lihat module `testing`)
+
+PAGINATION
+- Offset pagination menggunakan `limit` dan `offset`, mudah digunakan namun semakin lambat jika offset semakin besar
+- Keyset pagination menggunakan id terakhir dari halaman sebelumnya (`where id > ?`),
+sehingga database bisa langsung menggunakan index primary key
+*/
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Page<K> {
+    Offset { limit: u32, offset: u64 },
+    After { limit: u32, after: Option<K> },
+}
+
+impl<K> Page<K> {
+    pub fn first(limit: u32) -> Self {
+        Page::Offset { limit, offset: 0 }
+    }
+}
+
+/*
+INSERT RETURNING ID
+- Id auto increment didapat langsung dari hasil `execute()`, yaitu `MySqlQueryResult::last_insert_id()`,
+sehingga tidak perlu lagi query `select last_insert_id()` di dalam transaction
+- Untuk insert banyak data sekaligus (`insert into ... values (...), (...)`), MySQL mengembalikan id baris pertama,
+dan id baris berikutnya berurutan, sehingga hasilnya bisa dikembalikan dalam bentuk `IdRange`
+- Id yang berurutan ini berlaku selama `auto_increment_increment` bernilai 1 (default MySQL)
+*/
+
+/// Ids generated by one multi-row insert, from `start` up to but excluding `end`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct IdRange<Id> {
+    pub start: Id,
+    pub end: Id,
+}
+
+impl<Id> IdRange<Id>
+where
+    Id: Copy + From<u64> + Into<u64>,
+{
+    pub fn len(&self) -> u64 {
+        self.end.into().saturating_sub(self.start.into())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains(&self, id: Id) -> bool {
+        (self.start.into()..self.end.into()).contains(&id.into())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Id> + use<Id> {
+        (self.start.into()..self.end.into()).map(Id::from)
+    }
+}
+
+/// Reads generated auto increment ids from the result of an insert. MySQL
+/// reports the first id of a multi-row insert, SQLite the last one.
+pub(crate) trait InsertedIds {
+    fn inserted_ids<Id: From<u64>>(&self) -> IdRange<Id>;
+
+    /// Id of a single-row insert.
+    fn inserted_id<Id: From<u64>>(&self) -> Id {
+        self.inserted_ids().start
+    }
+}
+
+impl InsertedIds for MySqlQueryResult {
+    fn inserted_ids<Id: From<u64>>(&self) -> IdRange<Id> {
+        let start = self.last_insert_id();
+        IdRange {
+            start: Id::from(start),
+            end: Id::from(start + self.rows_affected()),
+        }
+    }
+}
+
+impl InsertedIds for SqliteQueryResult {
+    fn inserted_ids<Id: From<u64>>(&self) -> IdRange<Id> {
+        let end = self.last_insert_rowid() as u64 + 1;
+        IdRange {
+            start: Id::from(end - self.rows_affected()),
+            end: Id::from(end),
+        }
+    }
+}
+
+/// Escapes `%`, `_` and the escape character itself, so the search term is
+/// matched literally inside `like ? escape '!'`.
+fn like_pattern(term: &str) -> String {
+    let mut pattern = String::with_capacity(term.len() + 2);
+    pattern.push('%');
+    for c in term.chars() {
+        if matches!(c, '!' | '%' | '_') {
+            pattern.push('!');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+/// Builds `select * from <table>` without soft deleted rows, with an optional
+/// name search and the requested page, always ordered by `key` so pages are
+/// stable.
+fn select_page<'a, DB, K>(
+    table: &str,
+    key: &str,
+    name: Option<&str>,
+    page: &Page<K>,
+) -> QueryBuilder<'a, DB>
+where
+    DB: Database,
+    K: Clone + Encode<'a, DB> + Type<DB> + 'a,
+    String: Encode<'a, DB> + Type<DB>,
+    i64: Encode<'a, DB> + Type<DB>,
+{
+    let mut builder =
+        QueryBuilder::new(format!("select * from {} where deleted_at is null", table));
+
+    if let Some(name) = name {
+        builder
+            .push(" and name like ")
+            .push_bind(like_pattern(name))
+            .push(" escape '!'");
+    }
+
+    if let Page::After {
+        after: Some(after), ..
+    } = page
+    {
+        builder
+            .push(" and ")
+            .push(key)
+            .push(" > ")
+            .push_bind(after.clone());
+    }
+
+    builder.push(" order by ").push(key);
+    match page {
+        Page::Offset { limit, offset } => {
+            builder
+                .push(" limit ")
+                .push_bind(*limit as i64)
+                .push(" offset ")
+                .push_bind(*offset as i64);
+        }
+        Page::After { limit, .. } => {
+            builder.push(" limit ").push_bind(*limit as i64);
+        }
+    }
+    builder
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::{MySql, Sqlite};
+
+    use crate::model::SellerId;
+
+    use super::{Page, like_pattern, select_page};
+
+    #[test]
+    fn test_like_pattern() {
+        assert_eq!("%laptop%", like_pattern("laptop"));
+        assert_eq!("%50!%!_off!!%", like_pattern("50%_off!"));
+    }
+
+    #[test]
+    fn test_select_offset_page() {
+        let page: Page<String> = Page::Offset {
+            limit: 10,
+            offset: 20,
+        };
+        let builder = select_page::<MySql, _>("category", "id", None, &page);
+        assert_eq!(
+            "select * from category where deleted_at is null order by id limit ? offset ?",
+            builder.sql()
+        );
+    }
+
+    #[test]
+    fn test_select_keyset_page_with_search() {
+        let page = Page::After {
+            limit: 10,
+            after: Some("B".to_string()),
+        };
+        let builder = select_page::<MySql, _>("brands", "id", Some("sepatu"), &page);
+        assert_eq!(
+            "select * from brands where deleted_at is null and name like ? escape '!' and id > ? order by id limit ?",
+            builder.sql()
+        );
+
+        let page: Page<SellerId> = Page::After {
+            limit: 10,
+            after: None,
+        };
+        let builder = select_page::<Sqlite, _>("sellers", "id", None, &page);
+        assert_eq!(
+            "select * from sellers where deleted_at is null order by id limit ?",
+            builder.sql()
+        );
+    }
+}
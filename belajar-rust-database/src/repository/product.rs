@@ -0,0 +1,475 @@
+use std::fmt;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use sqlx::{Error, MySqlPool, QueryBuilder, SqlitePool};
+
+use super::{IdRange, InsertedIds, Page, select_page};
+use crate::{
+    audit::{self, AuditAction, AuditRecord},
+    model::{Product, ProductId},
+    transaction::{TransactionError, is_retryable, with_transaction},
+};
+
+/*
+OPTIMISTIC LOCKING
+- Dua kasir bisa membaca product yang sama, lalu mengubahnya bersamaan, sehingga perubahan kasir pertama tertimpa
+- Tabel products memiliki kolom `version` yang selalu bertambah setiap kali product berubah
+- Update hanya dijalankan jika versi di database masih sama dengan versi yang dibaca sebelumnya (`where version = ?`),
+jika tidak ada baris yang berubah, berarti product sudah diubah orang lain, dan update ditolak dengan `Conflict`
+- Berbeda dengan `select ... for update`, tidak ada row yang dikunci selama user mengubah data
+*/
+
+#[derive(Debug)]
+pub enum UpdateProductError {
+    NotFound(ProductId),
+    /// The product was changed since it was read, `version` is the current one.
+    Conflict {
+        product_id: ProductId,
+        version: u32,
+    },
+    Database(Error),
+}
+
+impl fmt::Display for UpdateProductError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UpdateProductError::NotFound(id) => write!(f, "product {} not found", id),
+            UpdateProductError::Conflict {
+                product_id,
+                version,
+            } => write!(
+                f,
+                "product {} was changed by someone else, current version is {}",
+                product_id, version
+            ),
+            UpdateProductError::Database(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for UpdateProductError {}
+
+impl From<Error> for UpdateProductError {
+    fn from(error: Error) -> Self {
+        UpdateProductError::Database(error)
+    }
+}
+
+impl TransactionError for UpdateProductError {
+    fn is_retryable(&self) -> bool {
+        matches!(self, UpdateProductError::Database(error) if is_retryable(error))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewProduct {
+    pub name: String,
+    pub description: String,
+    pub price: u64,
+    pub stock: u32,
+}
+
+impl NewProduct {
+    fn with_id(self, id: ProductId) -> Product {
+        Product {
+            id,
+            name: self.name,
+            description: self.description,
+            price: self.price,
+            stock: self.stock,
+            version: 0,
+        }
+    }
+}
+
+#[async_trait]
+pub trait ProductRepository: Send + Sync {
+    async fn find_by_id(&self, id: ProductId) -> Result<Option<Product>, Error>;
+    async fn list(&self, page: &Page<ProductId>) -> Result<Vec<Product>, Error>;
+    async fn search_by_name(
+        &self,
+        name: &str,
+        page: &Page<ProductId>,
+    ) -> Result<Vec<Product>, Error>;
+    /// Inserts the product and returns its auto increment id.
+    async fn insert(&self, actor: &str, product: &NewProduct) -> Result<ProductId, Error>;
+    /// Inserts all products in one statement and returns their ids, in the
+    /// same order as `products`.
+    async fn insert_many(
+        &self,
+        actor: &str,
+        products: &[NewProduct],
+    ) -> Result<IdRange<ProductId>, Error>;
+    /// Saves the product if it is still at `product.version`, and returns it
+    /// with the incremented version.
+    async fn update(&self, actor: &str, product: &Product) -> Result<Product, UpdateProductError>;
+    /// Soft deletes the product. Returns `false` when no product has the
+    /// given id.
+    async fn delete(&self, actor: &str, id: ProductId) -> Result<bool, Error>;
+}
+
+/// Implements the repository for one backend, MySQL and SQLite share the same
+/// SQL. `$lock` locks the row read before a change, SQLite has no row locks.
+macro_rules! product_repository {
+    ($name:ident, $pool:ty, $lock:literal) => {
+        #[derive(Clone)]
+        pub struct $name {
+            pool: $pool,
+        }
+
+        impl $name {
+            pub fn new(pool: $pool) -> Self {
+                $name { pool }
+            }
+        }
+
+        #[async_trait]
+        impl ProductRepository for $name {
+            async fn find_by_id(&self, id: ProductId) -> Result<Option<Product>, Error> {
+                sqlx::query_as("select * from products where id = ? and deleted_at is null")
+                    .bind(id)
+                    .fetch_optional(&self.pool)
+                    .await
+            }
+
+            async fn list(&self, page: &Page<ProductId>) -> Result<Vec<Product>, Error> {
+                select_page("products", "id", None, page)
+                    .build_query_as()
+                    .fetch_all(&self.pool)
+                    .await
+            }
+
+            async fn search_by_name(
+                &self,
+                name: &str,
+                page: &Page<ProductId>,
+            ) -> Result<Vec<Product>, Error> {
+                select_page("products", "id", Some(name), page)
+                    .build_query_as()
+                    .fetch_all(&self.pool)
+                    .await
+            }
+
+            async fn insert(&self, actor: &str, product: &NewProduct) -> Result<ProductId, Error> {
+                with_transaction(&self.pool, |tx| {
+                    let (actor, product) = (actor.to_string(), product.clone());
+                    Box::pin(async move {
+                        let result = sqlx::query(
+                            "insert into products(name, description, price, stock) values (?, ?, ?, ?)",
+                        )
+                        .bind(&product.name)
+                        .bind(&product.description)
+                        .bind(product.price as i64)
+                        .bind(product.stock)
+                        .execute(&mut **tx)
+                        .await?;
+                        let product = product.with_id(result.inserted_id());
+                        let record = AuditRecord::new(&actor, AuditAction::Insert, None, Some(&product));
+                        audit::write(&mut **tx, &[record]).await?;
+                        Ok(product.id)
+                    })
+                })
+                .await
+            }
+
+            async fn insert_many(
+                &self,
+                actor: &str,
+                products: &[NewProduct],
+            ) -> Result<IdRange<ProductId>, Error> {
+                if products.is_empty() {
+                    return Ok(IdRange::default());
+                }
+
+                with_transaction(&self.pool, |tx| {
+                    let (actor, products) = (actor.to_string(), products.to_vec());
+                    Box::pin(async move {
+                        let result =
+                            QueryBuilder::new("insert into products(name, description, price, stock) ")
+                                .push_values(&products, |mut row, product| {
+                                    row.push_bind(&product.name)
+                                        .push_bind(&product.description)
+                                        .push_bind(product.price as i64)
+                                        .push_bind(product.stock);
+                                })
+                                .build()
+                                .execute(&mut **tx)
+                                .await?;
+                        let ids: IdRange<ProductId> = result.inserted_ids();
+
+                        let records: Vec<AuditRecord> = ids
+                            .iter()
+                            .zip(products)
+                            .map(|(id, product)| {
+                                let product = product.with_id(id);
+                                AuditRecord::new(&actor, AuditAction::Insert, None, Some(&product))
+                            })
+                            .collect();
+                        audit::write(&mut **tx, &records).await?;
+                        Ok(ids)
+                    })
+                })
+                .await
+            }
+
+            async fn update(
+                &self,
+                actor: &str,
+                product: &Product,
+            ) -> Result<Product, UpdateProductError> {
+                with_transaction(&self.pool, |tx| {
+                    let (actor, product) = (actor.to_string(), product.clone());
+                    Box::pin(async move {
+                        let old: Option<Product> = sqlx::query_as(
+                            "select * from products where id = ? and deleted_at is null",
+                        )
+                        .bind(product.id)
+                        .fetch_optional(&mut **tx)
+                        .await?;
+                        let old = old.ok_or(UpdateProductError::NotFound(product.id))?;
+                        let conflict = UpdateProductError::Conflict {
+                            product_id: product.id,
+                            version: old.version,
+                        };
+                        if old.version != product.version {
+                            return Err(conflict);
+                        }
+
+                        // checked again by the database, the row may change after the select
+                        let result = sqlx::query(
+                            "update products set name = ?, description = ?, price = ?, stock = ?, version = version + 1, updated_at = ? \
+                            where id = ? and version = ? and deleted_at is null",
+                        )
+                        .bind(&product.name)
+                        .bind(&product.description)
+                        .bind(product.price as i64)
+                        .bind(product.stock)
+                        .bind(Utc::now())
+                        .bind(product.id)
+                        .bind(product.version)
+                        .execute(&mut **tx)
+                        .await?;
+                        if result.rows_affected() == 0 {
+                            return Err(conflict);
+                        }
+
+                        let new = Product {
+                            version: product.version + 1,
+                            ..product
+                        };
+                        let record =
+                            AuditRecord::new(&actor, AuditAction::Update, Some(&old), Some(&new));
+                        audit::write(&mut **tx, &[record]).await?;
+                        Ok(new)
+                    })
+                })
+                .await
+            }
+
+            async fn delete(&self, actor: &str, id: ProductId) -> Result<bool, Error> {
+                with_transaction(&self.pool, |tx| {
+                    let actor = actor.to_string();
+                    Box::pin(async move {
+                        let old: Option<Product> = sqlx::query_as(concat!(
+                            "select * from products where id = ? and deleted_at is null",
+                            $lock
+                        ))
+                        .bind(id)
+                        .fetch_optional(&mut **tx)
+                        .await?;
+                        let Some(old) = old else {
+                            return Ok(false);
+                        };
+
+                        sqlx::query("update products set deleted_at = ?, version = version + 1 where id = ?")
+                            .bind(Utc::now())
+                            .bind(id)
+                            .execute(&mut **tx)
+                            .await?;
+                        let record = AuditRecord::new(&actor, AuditAction::Delete, Some(&old), None);
+                        audit::write(&mut **tx, &[record]).await?;
+                        Ok(true)
+                    })
+                })
+                .await
+            }
+        }
+    };
+}
+
+product_repository!(MySqlProductRepository, MySqlPool, " for update");
+product_repository!(SqliteProductRepository, SqlitePool, "");
+
+#[cfg(test)]
+mod tests {
+    use sqlx::Error;
+
+    use serde_json::{Value, json};
+
+    use super::{NewProduct, ProductRepository, SqliteProductRepository, UpdateProductError};
+    use crate::{
+        audit::{AuditLogRepository, SqliteAuditLogRepository},
+        model::{Product, ProductId},
+        repository::{IdRange, Page},
+        testing::TestDatabase,
+    };
+
+    const ACTOR: &str = "admin";
+
+    fn new_product(name: &str) -> NewProduct {
+        NewProduct {
+            name: name.to_string(),
+            description: format!("Deskripsi {}", name),
+            price: 25_000,
+            stock: 10,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_insert_returns_typed_id() -> Result<(), Box<dyn std::error::Error>> {
+        let database = TestDatabase::sqlite().await?;
+        let repository = SqliteProductRepository::new(database.pool().clone());
+
+        let id = repository.insert(ACTOR, &new_product("Keyboard")).await?;
+        assert_eq!(ProductId(3), id);
+
+        let mut product = repository.find_by_id(id).await?.unwrap();
+        assert_eq!("Keyboard", product.name);
+
+        product.stock = 5;
+        let updated = repository.update(ACTOR, &product).await?;
+        assert_eq!(1, updated.version);
+        assert_eq!(Some(updated), repository.find_by_id(id).await?);
+
+        assert!(repository.delete(ACTOR, id).await?);
+        assert!(!repository.delete(ACTOR, id).await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_insert_many_returns_id_range() -> Result<(), Error> {
+        let database = TestDatabase::sqlite().await?;
+        let repository = SqliteProductRepository::new(database.pool().clone());
+
+        let products = [
+            new_product("Monitor"),
+            new_product("Webcam"),
+            new_product("Headset"),
+        ];
+        let ids = repository.insert_many(ACTOR, &products).await?;
+        assert_eq!(
+            IdRange {
+                start: ProductId(3),
+                end: ProductId(6)
+            },
+            ids
+        );
+        assert_eq!(3, ids.len());
+        assert!(ids.contains(ProductId(5)));
+        assert!(!ids.contains(ProductId(6)));
+
+        for (id, product) in ids.iter().zip(&products) {
+            let inserted = repository.find_by_id(id).await?.unwrap();
+            assert_eq!(product.name, inserted.name);
+        }
+
+        let listed = repository
+            .list(&Page::After {
+                limit: 10,
+                after: Some(ProductId(2)),
+            })
+            .await?;
+        assert_eq!(3, listed.len());
+
+        assert!(repository.insert_many(ACTOR, &[]).await?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_soft_delete_writes_audit_log() -> Result<(), Box<dyn std::error::Error>> {
+        let database = TestDatabase::sqlite().await?;
+        let repository = SqliteProductRepository::new(database.pool().clone());
+
+        let id = repository.insert(ACTOR, &new_product("Keyboard")).await?;
+        let mut product = repository.find_by_id(id).await?.unwrap();
+        product.price = 30_000;
+        let product = repository.update("kasir", &product).await?;
+        assert!(repository.delete(ACTOR, id).await?);
+
+        assert_eq!(None, repository.find_by_id(id).await?);
+        assert!(matches!(
+            repository.update(ACTOR, &product).await,
+            Err(UpdateProductError::NotFound(_))
+        ));
+        let listed = repository.list(&Page::first(10)).await?;
+        assert!(listed.iter().all(|product| product.id != id));
+        let deleted: i64 =
+            sqlx::query_scalar("select count(*) from products where deleted_at is not null")
+                .fetch_one(database.pool())
+                .await?;
+        assert_eq!(1, deleted);
+
+        let entries = SqliteAuditLogRepository::new(database.pool().clone())
+            .find_by_entity("products", &id.to_string())
+            .await?;
+        let actions: Vec<(&str, &str)> = entries
+            .iter()
+            .map(|entry| (entry.actor.as_str(), entry.action.as_str()))
+            .collect();
+        assert_eq!(
+            vec![
+                ("admin", "insert"),
+                ("kasir", "update"),
+                ("admin", "delete")
+            ],
+            actions
+        );
+        let changes: Value = serde_json::from_str(&entries[1].changes)?;
+        assert_eq!(
+            json!({
+                "price": { "old": 25_000, "new": 30_000 },
+                "version": { "old": 0, "new": 1 },
+            }),
+            changes
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_update_conflict() -> Result<(), Box<dyn std::error::Error>> {
+        let database = TestDatabase::sqlite().await?;
+        let repository = SqliteProductRepository::new(database.pool().clone());
+
+        let first = repository.find_by_id(ProductId(1)).await?.unwrap();
+        let mut second = first.clone();
+        let first = repository
+            .update(
+                ACTOR,
+                &Product {
+                    price: 14_500_000,
+                    ..first
+                },
+            )
+            .await?;
+
+        second.stock = 45;
+        let result = repository.update("kasir", &second).await;
+        assert!(matches!(
+            result,
+            Err(UpdateProductError::Conflict {
+                product_id: ProductId(1),
+                version: 1
+            })
+        ));
+        assert_eq!(
+            Some(first.clone()),
+            repository.find_by_id(ProductId(1)).await?
+        );
+
+        second.version = first.version;
+        let second = repository.update("kasir", &second).await?;
+        assert_eq!((2, 45), (second.version, second.stock));
+        Ok(())
+    }
+}
@@ -0,0 +1,241 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use sqlx::{Error, MySqlPool, QueryBuilder, SqlitePool};
+
+use super::{IdRange, InsertedIds, Page, select_page};
+use crate::{
+    audit::{self, AuditAction, AuditRecord},
+    model::{Seller, SellerId},
+    transaction::with_transaction,
+};
+
+#[derive(Debug, Clone)]
+pub struct NewSeller {
+    pub name: String,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+}
+
+#[async_trait]
+pub trait SellerRepository: Send + Sync {
+    async fn find_by_id(&self, id: SellerId) -> Result<Option<Seller>, Error>;
+    /// Sellers with the given ids in one query, ordered by id. Ids of missing
+    /// or deleted sellers are left out.
+    async fn find_by_ids(&self, ids: &[SellerId]) -> Result<Vec<Seller>, Error>;
+    async fn list(&self, page: &Page<SellerId>) -> Result<Vec<Seller>, Error>;
+    async fn search_by_name(&self, name: &str, page: &Page<SellerId>)
+    -> Result<Vec<Seller>, Error>;
+    /// Inserts the seller and returns its auto increment id.
+    async fn insert(&self, actor: &str, seller: &NewSeller) -> Result<SellerId, Error>;
+    /// Inserts all sellers in one statement and returns their ids, in the
+    /// same order as `sellers`.
+    async fn insert_many(
+        &self,
+        actor: &str,
+        sellers: &[NewSeller],
+    ) -> Result<IdRange<SellerId>, Error>;
+    /// Returns `false` when no seller has the given id.
+    async fn update(&self, actor: &str, seller: &Seller) -> Result<bool, Error>;
+    /// Soft deletes the seller. Returns `false` when no seller has the given id.
+    async fn delete(&self, actor: &str, id: SellerId) -> Result<bool, Error>;
+}
+
+/// Implements the repository for one backend, MySQL and SQLite share the same
+/// SQL. `$lock` locks the row read before a change, SQLite has no row locks.
+macro_rules! seller_repository {
+    ($name:ident, $pool:ty, $lock:literal) => {
+        #[derive(Clone)]
+        pub struct $name {
+            pool: $pool,
+        }
+
+        impl $name {
+            pub fn new(pool: $pool) -> Self {
+                $name { pool }
+            }
+        }
+
+        #[async_trait]
+        impl SellerRepository for $name {
+            async fn find_by_id(&self, id: SellerId) -> Result<Option<Seller>, Error> {
+                sqlx::query_as("select * from sellers where id = ? and deleted_at is null")
+                    .bind(id)
+                    .fetch_optional(&self.pool)
+                    .await
+            }
+
+            async fn find_by_ids(&self, ids: &[SellerId]) -> Result<Vec<Seller>, Error> {
+                if ids.is_empty() {
+                    return Ok(Vec::new());
+                }
+
+                let mut select =
+                    QueryBuilder::new("select * from sellers where deleted_at is null and id in (");
+                let mut separated = select.separated(", ");
+                for id in ids {
+                    separated.push_bind(*id);
+                }
+                select.push(") order by id");
+                select.build_query_as().fetch_all(&self.pool).await
+            }
+
+            async fn list(&self, page: &Page<SellerId>) -> Result<Vec<Seller>, Error> {
+                select_page("sellers", "id", None, page)
+                    .build_query_as()
+                    .fetch_all(&self.pool)
+                    .await
+            }
+
+            async fn search_by_name(
+                &self,
+                name: &str,
+                page: &Page<SellerId>,
+            ) -> Result<Vec<Seller>, Error> {
+                select_page("sellers", "id", Some(name), page)
+                    .build_query_as()
+                    .fetch_all(&self.pool)
+                    .await
+            }
+
+            async fn insert(&self, actor: &str, seller: &NewSeller) -> Result<SellerId, Error> {
+                with_transaction(&self.pool, |tx| {
+                    let (actor, seller) = (actor.to_string(), seller.clone());
+                    Box::pin(async move {
+                        let result = sqlx::query(
+                            "insert into sellers(name, latitude, longitude) values (?, ?, ?)",
+                        )
+                        .bind(&seller.name)
+                        .bind(seller.latitude)
+                        .bind(seller.longitude)
+                        .execute(&mut **tx)
+                        .await?;
+                        let seller = Seller {
+                            id: result.inserted_id(),
+                            name: seller.name,
+                            latitude: seller.latitude,
+                            longitude: seller.longitude,
+                        };
+                        let record =
+                            AuditRecord::new(&actor, AuditAction::Insert, None, Some(&seller));
+                        audit::write(&mut **tx, &[record]).await?;
+                        Ok(seller.id)
+                    })
+                })
+                .await
+            }
+
+            async fn insert_many(
+                &self,
+                actor: &str,
+                sellers: &[NewSeller],
+            ) -> Result<IdRange<SellerId>, Error> {
+                if sellers.is_empty() {
+                    return Ok(IdRange::default());
+                }
+
+                with_transaction(&self.pool, |tx| {
+                    let (actor, sellers) = (actor.to_string(), sellers.to_vec());
+                    Box::pin(async move {
+                        let result =
+                            QueryBuilder::new("insert into sellers(name, latitude, longitude) ")
+                                .push_values(&sellers, |mut row, seller| {
+                                    row.push_bind(&seller.name)
+                                        .push_bind(seller.latitude)
+                                        .push_bind(seller.longitude);
+                                })
+                                .build()
+                                .execute(&mut **tx)
+                                .await?;
+                        let ids: IdRange<SellerId> = result.inserted_ids();
+
+                        let records: Vec<AuditRecord> = ids
+                            .iter()
+                            .zip(sellers)
+                            .map(|(id, seller)| {
+                                let seller = Seller {
+                                    id,
+                                    name: seller.name,
+                                    latitude: seller.latitude,
+                                    longitude: seller.longitude,
+                                };
+                                AuditRecord::new(&actor, AuditAction::Insert, None, Some(&seller))
+                            })
+                            .collect();
+                        audit::write(&mut **tx, &records).await?;
+                        Ok(ids)
+                    })
+                })
+                .await
+            }
+
+            async fn update(&self, actor: &str, seller: &Seller) -> Result<bool, Error> {
+                with_transaction(&self.pool, |tx| {
+                    let (actor, seller) = (actor.to_string(), seller.clone());
+                    Box::pin(async move {
+                        let old: Option<Seller> = sqlx::query_as(concat!(
+                            "select * from sellers where id = ? and deleted_at is null",
+                            $lock
+                        ))
+                        .bind(seller.id)
+                        .fetch_optional(&mut **tx)
+                        .await?;
+                        let Some(old) = old else {
+                            return Ok(false);
+                        };
+
+                        sqlx::query(
+                            "update sellers set name = ?, latitude = ?, longitude = ? where id = ?",
+                        )
+                        .bind(&seller.name)
+                        .bind(seller.latitude)
+                        .bind(seller.longitude)
+                        .bind(seller.id)
+                        .execute(&mut **tx)
+                        .await?;
+                        let record = AuditRecord::new(
+                            &actor,
+                            AuditAction::Update,
+                            Some(&old),
+                            Some(&seller),
+                        );
+                        audit::write(&mut **tx, &[record]).await?;
+                        Ok(true)
+                    })
+                })
+                .await
+            }
+
+            async fn delete(&self, actor: &str, id: SellerId) -> Result<bool, Error> {
+                with_transaction(&self.pool, |tx| {
+                    let actor = actor.to_string();
+                    Box::pin(async move {
+                        let old: Option<Seller> = sqlx::query_as(concat!(
+                            "select * from sellers where id = ? and deleted_at is null",
+                            $lock
+                        ))
+                        .bind(id)
+                        .fetch_optional(&mut **tx)
+                        .await?;
+                        let Some(old) = old else {
+                            return Ok(false);
+                        };
+
+                        sqlx::query("update sellers set deleted_at = ? where id = ?")
+                            .bind(Utc::now())
+                            .bind(id)
+                            .execute(&mut **tx)
+                            .await?;
+                        let record =
+                            AuditRecord::new(&actor, AuditAction::Delete, Some(&old), None);
+                        audit::write(&mut **tx, &[record]).await?;
+                        Ok(true)
+                    })
+                })
+                .await
+            }
+        }
+    };
+}
+
+seller_repository!(MySqlSellerRepository, MySqlPool, " for update");
+seller_repository!(SqliteSellerRepository, SqlitePool, "");
@@ -0,0 +1,417 @@
+use std::{collections::BTreeMap, fmt};
+
+use async_trait::async_trait;
+use chrono::{DateTime, SubsecRound, Utc};
+use serde_json::json;
+use sqlx::{Error, MySqlPool, SqlitePool};
+
+use super::{InsertedIds, Page};
+use crate::{
+    audit::{self, AuditAction, AuditRecord, Audited},
+    model::{Product, ProductId, Transaction, TransactionId, TransactionItem},
+    transaction::{TransactionError, is_retryable, with_transaction},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewTransactionItem {
+    pub product_id: ProductId,
+    pub quantity: u32,
+}
+
+#[derive(Debug)]
+pub enum CheckoutError {
+    EmptyTransaction,
+    ProductNotFound(ProductId),
+    InsufficientStock { product_id: ProductId, stock: u32 },
+    Database(Error),
+}
+
+impl fmt::Display for CheckoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckoutError::EmptyTransaction => write!(f, "transaction has no items"),
+            CheckoutError::ProductNotFound(id) => write!(f, "product {} not found", id),
+            CheckoutError::InsufficientStock { product_id, stock } => write!(
+                f,
+                "insufficient stock for product {}, only {} left",
+                product_id, stock
+            ),
+            CheckoutError::Database(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for CheckoutError {}
+
+impl From<Error> for CheckoutError {
+    fn from(error: Error) -> Self {
+        CheckoutError::Database(error)
+    }
+}
+
+impl TransactionError for CheckoutError {
+    fn is_retryable(&self) -> bool {
+        matches!(self, CheckoutError::Database(error) if is_retryable(error))
+    }
+}
+
+#[async_trait]
+pub trait TransactionRepository: Send + Sync {
+    /// Returns the transaction together with its items.
+    async fn find_by_id(&self, id: TransactionId) -> Result<Option<Transaction>, Error>;
+    async fn list(&self, page: &Page<TransactionId>) -> Result<Vec<Transaction>, Error>;
+    /// Decrements the stock of every product and records the transaction with
+    /// its items, all or nothing. Items of the same product are merged. Each
+    /// decrement is guarded by `stock >= quantity` in the update itself, so
+    /// concurrent checkouts cannot sell more than the stock.
+    async fn checkout(
+        &self,
+        actor: &str,
+        items: &[NewTransactionItem],
+    ) -> Result<Transaction, CheckoutError>;
+    /// Units sold per product in transactions created at or after `since`, or
+    /// in every transaction when `since` is `None`, ordered by product id.
+    async fn units_sold(
+        &self,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<Vec<(ProductId, u64)>, Error>;
+}
+
+/// Merges items of the same product, sorted by product id so concurrent
+/// checkouts lock the product rows in the same order.
+fn merge_items(items: &[NewTransactionItem]) -> BTreeMap<ProductId, u32> {
+    let mut merged = BTreeMap::new();
+    for item in items {
+        let quantity: &mut u32 = merged.entry(item.product_id).or_default();
+        *quantity = quantity.saturating_add(item.quantity);
+    }
+    merged
+}
+
+/// Implements the repository for one backend, MySQL and SQLite share the same SQL.
+macro_rules! transaction_repository {
+    ($name:ident, $pool:ty) => {
+        #[derive(Clone)]
+        pub struct $name {
+            pool: $pool,
+        }
+
+        impl $name {
+            pub fn new(pool: $pool) -> Self {
+                $name { pool }
+            }
+
+            async fn items(&self, id: TransactionId) -> Result<Vec<TransactionItem>, Error> {
+                sqlx::query_as(
+                    "select * from transaction_items where transaction_id = ? order by product_id",
+                )
+                .bind(id)
+                .fetch_all(&self.pool)
+                .await
+            }
+        }
+
+        #[async_trait]
+        impl TransactionRepository for $name {
+            async fn find_by_id(&self, id: TransactionId) -> Result<Option<Transaction>, Error> {
+                let transaction: Option<Transaction> =
+                    sqlx::query_as("select * from transactions where id = ?")
+                        .bind(id)
+                        .fetch_optional(&self.pool)
+                        .await?;
+                let Some(mut transaction) = transaction else {
+                    return Ok(None);
+                };
+
+                transaction.items = self.items(id).await?;
+                Ok(Some(transaction))
+            }
+
+            async fn list(&self, page: &Page<TransactionId>) -> Result<Vec<Transaction>, Error> {
+                let mut transactions: Vec<Transaction> = match page {
+                    Page::Offset { limit, offset } => {
+                        sqlx::query_as("select * from transactions order by id limit ? offset ?")
+                            .bind(*limit as i64)
+                            .bind(*offset as i64)
+                            .fetch_all(&self.pool)
+                            .await?
+                    }
+                    Page::After { limit, after } => {
+                        sqlx::query_as("select * from transactions where id > ? order by id limit ?")
+                            .bind(after.unwrap_or_default())
+                            .bind(*limit as i64)
+                            .fetch_all(&self.pool)
+                            .await?
+                    }
+                };
+
+                for transaction in &mut transactions {
+                    transaction.items = self.items(transaction.id).await?;
+                }
+                Ok(transactions)
+            }
+
+            async fn checkout(
+                &self,
+                actor: &str,
+                items: &[NewTransactionItem],
+            ) -> Result<Transaction, CheckoutError> {
+                let items = merge_items(items);
+                if items.is_empty() {
+                    return Err(CheckoutError::EmptyTransaction);
+                }
+
+                with_transaction(&self.pool, |tx| {
+                    let (actor, items) = (actor.to_string(), items.clone());
+                    Box::pin(async move {
+                        let mut lines = Vec::with_capacity(items.len());
+                        let mut records = Vec::with_capacity(items.len() + 1);
+                        for (product_id, quantity) in items {
+                            // the guard makes the decrement atomic, the stock can never go below zero
+                            let result = sqlx::query(
+                                "update products set stock = stock - ?, version = version + 1 \
+                                where id = ? and stock >= ? and deleted_at is null",
+                            )
+                            .bind(quantity)
+                            .bind(product_id)
+                            .bind(quantity)
+                            .execute(&mut **tx)
+                            .await?;
+                            let product: Option<(u64, u32, u32)> =
+                                sqlx::query_as("select price, stock, version from products where id = ? and deleted_at is null")
+                                    .bind(product_id)
+                                    .fetch_optional(&mut **tx)
+                                    .await?;
+                            let (price, stock, version) =
+                                product.ok_or(CheckoutError::ProductNotFound(product_id))?;
+                            if result.rows_affected() == 0 {
+                                return Err(CheckoutError::InsufficientStock { product_id, stock });
+                            }
+
+                            records.push(AuditRecord {
+                                actor: actor.clone(),
+                                action: AuditAction::Update,
+                                entity: Product::ENTITY,
+                                entity_id: product_id.to_string(),
+                                changes: json!({
+                                    "stock": { "old": stock + quantity, "new": stock },
+                                    "version": { "old": version - 1, "new": version },
+                                }),
+                            });
+                            lines.push(TransactionItem {
+                                product_id,
+                                quantity,
+                                price,
+                                subtotal: price * u64::from(quantity),
+                            });
+                        }
+
+                        let total: u64 = lines.iter().map(|line| line.subtotal).sum();
+                        let created_at = Utc::now().trunc_subsecs(0);
+                        let result =
+                            sqlx::query("insert into transactions(total, created_at) values (?, ?)")
+                                .bind(total as i64)
+                                .bind(created_at)
+                                .execute(&mut **tx)
+                                .await?;
+                        let id = result.inserted_id();
+
+                        for line in &lines {
+                            sqlx::query(
+                                "insert into transaction_items(transaction_id, product_id, quantity, price, subtotal) values (?, ?, ?, ?, ?)",
+                            )
+                            .bind(id)
+                            .bind(line.product_id)
+                            .bind(line.quantity)
+                            .bind(line.price as i64)
+                            .bind(line.subtotal as i64)
+                            .execute(&mut **tx)
+                            .await?;
+                        }
+
+                        let transaction = Transaction {
+                            id,
+                            total,
+                            created_at,
+                            items: lines,
+                        };
+                        records.push(AuditRecord::new(
+                            &actor,
+                            AuditAction::Insert,
+                            None,
+                            Some(&transaction),
+                        ));
+                        audit::write(&mut **tx, &records).await?;
+                        Ok(transaction)
+                    })
+                })
+                .await
+            }
+
+            async fn units_sold(
+                &self,
+                since: Option<DateTime<Utc>>,
+            ) -> Result<Vec<(ProductId, u64)>, Error> {
+                sqlx::query_as(
+                    "select i.product_id, cast(sum(i.quantity) as unsigned) from transaction_items i \
+                    join transactions t on t.id = i.transaction_id \
+                    where t.created_at >= ? group by i.product_id order by i.product_id",
+                )
+                .bind(since.unwrap_or(DateTime::UNIX_EPOCH))
+                .fetch_all(&self.pool)
+                .await
+            }
+        }
+    };
+}
+
+transaction_repository!(MySqlTransactionRepository, MySqlPool);
+transaction_repository!(SqliteTransactionRepository, SqlitePool);
+
+#[cfg(test)]
+mod tests {
+    use sqlx::Error;
+
+    use super::{
+        CheckoutError, NewTransactionItem, SqliteTransactionRepository, TransactionRepository,
+    };
+    use crate::{model::ProductId, repository::Page, testing::TestDatabase};
+
+    const ACTOR: &str = "kasir";
+
+    fn item(product_id: u64, quantity: u32) -> NewTransactionItem {
+        NewTransactionItem {
+            product_id: ProductId(product_id),
+            quantity,
+        }
+    }
+
+    async fn stock(database: &TestDatabase<sqlx::Sqlite>, id: i64) -> Result<u32, Error> {
+        sqlx::query_scalar("select stock from products where id = ?")
+            .bind(id)
+            .fetch_one(database.pool())
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_checkout() -> Result<(), Box<dyn std::error::Error>> {
+        let database = TestDatabase::sqlite().await?;
+        let repository = SqliteTransactionRepository::new(database.pool().clone());
+
+        let transaction = repository
+            .checkout(ACTOR, &[item(2, 1), item(1, 2), item(2, 3)])
+            .await?;
+        assert_eq!(2 * 15_000_000 + 4 * 150_000, transaction.total);
+        let ids: Vec<ProductId> = transaction
+            .items
+            .iter()
+            .map(|item| item.product_id)
+            .collect();
+        assert_eq!(vec![ProductId(1), ProductId(2)], ids);
+        assert_eq!(48, stock(&database, 1).await?);
+        assert_eq!(96, stock(&database, 2).await?);
+
+        assert_eq!(
+            Some(transaction.clone()),
+            repository.find_by_id(transaction.id).await?
+        );
+        let entries: Vec<(String, String, String)> =
+            sqlx::query_as("select actor, entity, entity_id from audit_log order by id")
+                .fetch_all(database.pool())
+                .await?;
+        let expected = [("products", "1"), ("products", "2"), ("transactions", "1")];
+        assert_eq!(expected.len(), entries.len());
+        for ((actor, entity, id), (expected_entity, expected_id)) in entries.iter().zip(expected) {
+            assert_eq!(ACTOR, actor);
+            assert_eq!(
+                (expected_entity, expected_id),
+                (entity.as_str(), id.as_str())
+            );
+        }
+
+        repository.checkout(ACTOR, &[item(1, 1)]).await?;
+        assert_eq!(
+            vec![(ProductId(1), 3), (ProductId(2), 4)],
+            repository.units_sold(None).await?
+        );
+        assert_eq!(
+            vec![(ProductId(1), 3), (ProductId(2), 4)],
+            repository.units_sold(Some(transaction.created_at)).await?
+        );
+        assert!(
+            repository
+                .units_sold(Some(transaction.created_at + chrono::Duration::days(1)))
+                .await?
+                .is_empty()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_checkout_rolls_back() -> Result<(), Box<dyn std::error::Error>> {
+        let database = TestDatabase::sqlite().await?;
+        let repository = SqliteTransactionRepository::new(database.pool().clone());
+
+        let result = repository
+            .checkout(ACTOR, &[item(1, 2), item(2, 101)])
+            .await;
+        assert!(matches!(
+            result,
+            Err(CheckoutError::InsufficientStock {
+                product_id: ProductId(2),
+                stock: 100
+            })
+        ));
+        assert_eq!(50, stock(&database, 1).await?);
+
+        let result = repository.checkout(ACTOR, &[item(1, 2), item(99, 1)]).await;
+        assert!(matches!(
+            result,
+            Err(CheckoutError::ProductNotFound(ProductId(99)))
+        ));
+        assert_eq!(50, stock(&database, 1).await?);
+
+        let result = repository.checkout(ACTOR, &[]).await;
+        assert!(matches!(result, Err(CheckoutError::EmptyTransaction)));
+
+        for table in ["transactions", "audit_log"] {
+            let count: i64 = sqlx::query_scalar(&format!("select count(*) from {}", table))
+                .fetch_one(database.pool())
+                .await?;
+            assert_eq!(0, count);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_checkout_keeps_stock() -> Result<(), Box<dyn std::error::Error>> {
+        let database = TestDatabase::sqlite().await?;
+        let repository = SqliteTransactionRepository::new(database.pool().clone());
+
+        let checkouts: Vec<_> = (0..12)
+            .map(|_| {
+                let repository = repository.clone();
+                tokio::spawn(async move { repository.checkout(ACTOR, &[item(1, 5)]).await })
+            })
+            .collect();
+        let mut sold = 0;
+        for checkout in checkouts {
+            match checkout.await? {
+                Ok(_) => sold += 1,
+                Err(CheckoutError::InsufficientStock { stock, .. }) => assert!(stock < 5),
+                Err(error) => return Err(error.into()),
+            }
+        }
+        assert_eq!(10, sold);
+        assert_eq!(0, stock(&database, 1).await?);
+
+        let transactions = repository.list(&Page::first(20)).await?;
+        assert_eq!(10, transactions.len());
+        assert!(
+            transactions
+                .iter()
+                .all(|transaction| transaction.items.len() == 1)
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,324 @@
+use std::fmt;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use sqlx::{Error, MySqlPool, SqlitePool};
+
+use super::{InsertedIds, Page, select_page};
+use crate::{
+    audit::{self, AuditAction, AuditRecord},
+    model::{User, UserId},
+    transaction::{TransactionError, is_retryable, with_transaction},
+};
+
+/*
+USER
+- User MiniPOS disimpan di tabel `users`, password nya disimpan dalam bentuk hash, bukan password mentah
+- Hash password dibuat oleh aplikasi sebelum memanggil repository, repository hanya menyimpan hasilnya
+- Email bersifat unik (unique index `users_email_unique`), insert atau update dengan email yang sudah terdaftar
+ditolak dengan `UserError::EmailTaken`
+- User yang dihapus hanya di soft delete, sehingga email nya tetap terpakai dan tidak bisa didaftarkan lagi
+*/
+
+#[derive(Debug)]
+pub enum UserError {
+    EmailTaken(String),
+    Database(Error),
+}
+
+impl UserError {
+    /// Turns a unique index violation into [`UserError::EmailTaken`], email
+    /// is the only unique column besides the id.
+    fn from_write(error: Error, email: &str) -> Self {
+        let taken = error
+            .as_database_error()
+            .is_some_and(|error| error.is_unique_violation());
+        if taken {
+            UserError::EmailTaken(email.to_string())
+        } else {
+            UserError::Database(error)
+        }
+    }
+}
+
+impl fmt::Display for UserError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UserError::EmailTaken(email) => write!(f, "email {} is already registered", email),
+            UserError::Database(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for UserError {}
+
+impl From<Error> for UserError {
+    fn from(error: Error) -> Self {
+        UserError::Database(error)
+    }
+}
+
+impl TransactionError for UserError {
+    fn is_retryable(&self) -> bool {
+        matches!(self, UserError::Database(error) if is_retryable(error))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NewUser {
+    pub name: String,
+    pub email: String,
+    /// Already hashed password.
+    pub password: String,
+}
+
+#[async_trait]
+pub trait UserRepository: Send + Sync {
+    async fn find_by_id(&self, id: UserId) -> Result<Option<User>, Error>;
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, Error>;
+    async fn list(&self, page: &Page<UserId>) -> Result<Vec<User>, Error>;
+    async fn search_by_name(&self, name: &str, page: &Page<UserId>) -> Result<Vec<User>, Error>;
+    /// Inserts the user and returns its auto increment id.
+    async fn insert(&self, actor: &str, user: &NewUser) -> Result<UserId, UserError>;
+    /// Returns `false` when no user has the given id.
+    async fn update(&self, actor: &str, user: &User) -> Result<bool, UserError>;
+    /// Soft deletes the user. Returns `false` when no user has the given id.
+    async fn delete(&self, actor: &str, id: UserId) -> Result<bool, Error>;
+}
+
+/// Implements the repository for one backend, MySQL and SQLite share the same
+/// SQL. `$lock` locks the row read before a change, SQLite has no row locks.
+macro_rules! user_repository {
+    ($name:ident, $pool:ty, $lock:literal) => {
+        #[derive(Clone)]
+        pub struct $name {
+            pool: $pool,
+        }
+
+        impl $name {
+            pub fn new(pool: $pool) -> Self {
+                $name { pool }
+            }
+        }
+
+        #[async_trait]
+        impl UserRepository for $name {
+            async fn find_by_id(&self, id: UserId) -> Result<Option<User>, Error> {
+                sqlx::query_as("select * from users where id = ? and deleted_at is null")
+                    .bind(id)
+                    .fetch_optional(&self.pool)
+                    .await
+            }
+
+            async fn find_by_email(&self, email: &str) -> Result<Option<User>, Error> {
+                sqlx::query_as("select * from users where email = ? and deleted_at is null")
+                    .bind(email)
+                    .fetch_optional(&self.pool)
+                    .await
+            }
+
+            async fn list(&self, page: &Page<UserId>) -> Result<Vec<User>, Error> {
+                select_page("users", "id", None, page)
+                    .build_query_as()
+                    .fetch_all(&self.pool)
+                    .await
+            }
+
+            async fn search_by_name(
+                &self,
+                name: &str,
+                page: &Page<UserId>,
+            ) -> Result<Vec<User>, Error> {
+                select_page("users", "id", Some(name), page)
+                    .build_query_as()
+                    .fetch_all(&self.pool)
+                    .await
+            }
+
+            async fn insert(&self, actor: &str, user: &NewUser) -> Result<UserId, UserError> {
+                with_transaction(&self.pool, |tx| {
+                    let (actor, user) = (actor.to_string(), user.clone());
+                    Box::pin(async move {
+                        let result =
+                            sqlx::query("insert into users(name, email, password) values (?, ?, ?)")
+                                .bind(&user.name)
+                                .bind(&user.email)
+                                .bind(&user.password)
+                                .execute(&mut **tx)
+                                .await
+                                .map_err(|error| UserError::from_write(error, &user.email))?;
+                        let user = User {
+                            id: result.inserted_id(),
+                            name: user.name,
+                            email: user.email,
+                            password: user.password,
+                        };
+                        let record =
+                            AuditRecord::new(&actor, AuditAction::Insert, None, Some(&user));
+                        audit::write(&mut **tx, &[record]).await?;
+                        Ok(user.id)
+                    })
+                })
+                .await
+            }
+
+            async fn update(&self, actor: &str, user: &User) -> Result<bool, UserError> {
+                with_transaction(&self.pool, |tx| {
+                    let (actor, user) = (actor.to_string(), user.clone());
+                    Box::pin(async move {
+                        let old: Option<User> = sqlx::query_as(concat!(
+                            "select * from users where id = ? and deleted_at is null",
+                            $lock
+                        ))
+                        .bind(user.id)
+                        .fetch_optional(&mut **tx)
+                        .await?;
+                        let Some(old) = old else {
+                            return Ok(false);
+                        };
+
+                        sqlx::query(
+                            "update users set name = ?, email = ?, password = ?, updated_at = ? where id = ?",
+                        )
+                        .bind(&user.name)
+                        .bind(&user.email)
+                        .bind(&user.password)
+                        .bind(Utc::now())
+                        .bind(user.id)
+                        .execute(&mut **tx)
+                        .await
+                        .map_err(|error| UserError::from_write(error, &user.email))?;
+                        let record =
+                            AuditRecord::new(&actor, AuditAction::Update, Some(&old), Some(&user));
+                        audit::write(&mut **tx, &[record]).await?;
+                        Ok(true)
+                    })
+                })
+                .await
+            }
+
+            async fn delete(&self, actor: &str, id: UserId) -> Result<bool, Error> {
+                with_transaction(&self.pool, |tx| {
+                    let actor = actor.to_string();
+                    Box::pin(async move {
+                        let old: Option<User> = sqlx::query_as(concat!(
+                            "select * from users where id = ? and deleted_at is null",
+                            $lock
+                        ))
+                        .bind(id)
+                        .fetch_optional(&mut **tx)
+                        .await?;
+                        let Some(old) = old else {
+                            return Ok(false);
+                        };
+
+                        sqlx::query("update users set deleted_at = ? where id = ?")
+                            .bind(Utc::now())
+                            .bind(id)
+                            .execute(&mut **tx)
+                            .await?;
+                        let record =
+                            AuditRecord::new(&actor, AuditAction::Delete, Some(&old), None);
+                        audit::write(&mut **tx, &[record]).await?;
+                        Ok(true)
+                    })
+                })
+                .await
+            }
+        }
+    };
+}
+
+user_repository!(MySqlUserRepository, MySqlPool, " for update");
+user_repository!(SqliteUserRepository, SqlitePool, "");
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, json};
+
+    use super::{NewUser, SqliteUserRepository, UserError, UserRepository};
+    use crate::{
+        audit::{AuditLogRepository, SqliteAuditLogRepository},
+        repository::Page,
+        testing::TestDatabase,
+    };
+
+    const ACTOR: &str = "admin";
+
+    fn new_user(name: &str, email: &str) -> NewUser {
+        NewUser {
+            name: name.to_string(),
+            email: email.to_string(),
+            password: "hash".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_email_taken() -> Result<(), Box<dyn std::error::Error>> {
+        let database = TestDatabase::sqlite().await?;
+        let repository = SqliteUserRepository::new(database.pool().clone());
+
+        repository
+            .insert(ACTOR, &new_user("Eko", "eko@example.com"))
+            .await?;
+        let id = repository
+            .insert(ACTOR, &new_user("Budi", "budi@example.com"))
+            .await?;
+        assert!(matches!(
+            repository.insert(ACTOR, &new_user("Eko", "eko@example.com")).await,
+            Err(UserError::EmailTaken(email)) if email == "eko@example.com"
+        ));
+
+        let mut user = repository.find_by_id(id).await?.unwrap();
+        user.email = "eko@example.com".to_string();
+        assert!(matches!(
+            repository.update(ACTOR, &user).await,
+            Err(UserError::EmailTaken(_))
+        ));
+        assert_eq!(
+            Some("budi@example.com".to_string()),
+            repository.find_by_id(id).await?.map(|user| user.email)
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_soft_delete_writes_audit_log() -> Result<(), Box<dyn std::error::Error>> {
+        let database = TestDatabase::sqlite().await?;
+        let repository = SqliteUserRepository::new(database.pool().clone());
+
+        let id = repository
+            .insert(ACTOR, &new_user("Eko", "eko@example.com"))
+            .await?;
+        let mut user = repository.find_by_email("eko@example.com").await?.unwrap();
+        assert_eq!(id, user.id);
+        user.name = "Eko Kurniawan".to_string();
+        user.password = "new hash".to_string();
+        assert!(repository.update(ACTOR, &user).await?);
+        assert!(repository.delete(ACTOR, id).await?);
+        assert!(!repository.delete(ACTOR, id).await?);
+
+        assert_eq!(None, repository.find_by_id(id).await?);
+        assert_eq!(None, repository.find_by_email("eko@example.com").await?);
+        assert!(!repository.update(ACTOR, &user).await?);
+        assert!(repository.list(&Page::first(10)).await?.is_empty());
+        let deleted: i64 =
+            sqlx::query_scalar("select count(*) from users where deleted_at is not null")
+                .fetch_one(database.pool())
+                .await?;
+        assert_eq!(1, deleted);
+
+        let entries = SqliteAuditLogRepository::new(database.pool().clone())
+            .find_by_entity("users", &id.to_string())
+            .await?;
+        let actions: Vec<&str> = entries.iter().map(|entry| entry.action.as_str()).collect();
+        assert_eq!(vec!["insert", "update", "delete"], actions);
+        let changes: Value = serde_json::from_str(&entries[1].changes)?;
+        assert_eq!(
+            json!({ "name": { "old": "Eko", "new": "Eko Kurniawan" } }),
+            changes
+        );
+        assert!(entries.iter().all(|entry| !entry.changes.contains("hash")));
+        Ok(())
+    }
+}
@@ -0,0 +1,145 @@
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use sqlx::{Error, MySqlPool, QueryBuilder, SqlitePool};
+
+use crate::model::DailyVisitors;
+
+/*
+DAILY VISITORS
+- Jumlah pengunjung unik per hari dihitung di Redis HyperLogLog, lalu disimpan ke tabel `daily_visitors`
+oleh job terjadwal sebelum key Redis nya kadaluarsa
+- Job yang sama bisa menyimpan hari yang sama berkali - kali, jumlah yang disimpan adalah yang terbesar,
+karena hitungan satu hari hanya bisa bertambah, dan key yang sudah hilang dari Redis terhitung 0
+*/
+
+#[async_trait]
+pub trait VisitorRepository: Send + Sync {
+    /// Inserts the counts, or keeps the larger count when the day and route
+    /// are already saved.
+    async fn save_daily(&self, counts: &[DailyVisitors]) -> Result<(), Error>;
+    /// Counts of `route` from `from` to `to` inclusive, oldest first.
+    async fn daily(
+        &self,
+        route: &str,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<DailyVisitors>, Error>;
+}
+
+/// Implements the repository for one backend, `$upsert` is the conflict
+/// clause of the backend.
+macro_rules! visitor_repository {
+    ($name:ident, $pool:ty, $upsert:literal) => {
+        #[derive(Clone)]
+        pub struct $name {
+            pool: $pool,
+        }
+
+        impl $name {
+            pub fn new(pool: $pool) -> Self {
+                $name { pool }
+            }
+        }
+
+        #[async_trait]
+        impl VisitorRepository for $name {
+            async fn save_daily(&self, counts: &[DailyVisitors]) -> Result<(), Error> {
+                if counts.is_empty() {
+                    return Ok(());
+                }
+                QueryBuilder::new("insert into daily_visitors(date, route, visitors) ")
+                    .push_values(counts, |mut row, count| {
+                        row.push_bind(count.date)
+                            .push_bind(&count.route)
+                            .push_bind(count.visitors as i64);
+                    })
+                    .push($upsert)
+                    .build()
+                    .execute(&self.pool)
+                    .await?;
+                Ok(())
+            }
+
+            async fn daily(
+                &self,
+                route: &str,
+                from: NaiveDate,
+                to: NaiveDate,
+            ) -> Result<Vec<DailyVisitors>, Error> {
+                sqlx::query_as(
+                    "select * from daily_visitors where route = ? and date between ? and ? order by date",
+                )
+                .bind(route)
+                .bind(from)
+                .bind(to)
+                .fetch_all(&self.pool)
+                .await
+            }
+        }
+    };
+}
+
+visitor_repository!(
+    MySqlVisitorRepository,
+    MySqlPool,
+    " on duplicate key update visitors = greatest(visitors, values(visitors))"
+);
+visitor_repository!(
+    SqliteVisitorRepository,
+    SqlitePool,
+    " on conflict(date, route) do update set visitors = max(visitors, excluded.visitors)"
+);
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::{SqliteVisitorRepository, VisitorRepository};
+    use crate::{model::DailyVisitors, testing::TestDatabase};
+
+    fn count(day: u32, route: &str, visitors: u64) -> DailyVisitors {
+        DailyVisitors {
+            date: NaiveDate::from_ymd_opt(2025, 9, day).unwrap(),
+            route: route.to_string(),
+            visitors,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_save_daily_keeps_largest_count() -> Result<(), Box<dyn std::error::Error>> {
+        let database = TestDatabase::sqlite().await?;
+        let repository = SqliteVisitorRepository::new(database.pool().clone());
+
+        repository
+            .save_daily(&[
+                count(7, DailyVisitors::ALL_ROUTES, 10),
+                count(8, DailyVisitors::ALL_ROUTES, 4),
+                count(8, "GET /products", 3),
+            ])
+            .await?;
+        // The key of the 7th has expired and the 8th gained visitors.
+        repository
+            .save_daily(&[
+                count(7, DailyVisitors::ALL_ROUTES, 0),
+                count(8, DailyVisitors::ALL_ROUTES, 6),
+            ])
+            .await?;
+
+        let from = NaiveDate::from_ymd_opt(2025, 9, 1).unwrap();
+        let to = NaiveDate::from_ymd_opt(2025, 9, 30).unwrap();
+        assert_eq!(
+            vec![
+                count(7, DailyVisitors::ALL_ROUTES, 10),
+                count(8, DailyVisitors::ALL_ROUTES, 6),
+            ],
+            repository
+                .daily(DailyVisitors::ALL_ROUTES, from, to)
+                .await?
+        );
+        assert_eq!(
+            vec![count(8, "GET /products", 3)],
+            repository.daily("GET /products", from, to).await?
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,313 @@
+use std::{
+    path::PathBuf,
+    str::FromStr,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use sqlx::{
+    Connection, Database, Error, Executor, MySql, MySqlConnection, Pool, Sqlite,
+    migrate::Migrator,
+    mysql::{MySqlConnectOptions, MySqlPoolOptions},
+    sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+};
+
+use crate::migrate::MIGRATOR;
+
+/*
+TEST DATABASE
+- Test yang menggunakan database sebaiknya tidak bergantung pada database yang sudah ada di laptop developer
+- `TestDatabase` membuat database baru untuk setiap test, menjalankan migration, lalu mengisi data awal dari `fixtures/seed.sql`
+- Saat `TestDatabase` di drop (test selesai, berhasil ataupun panic), database nya akan dihapus lagi
+- Karena setiap test memiliki database sendiri, test bisa berjalan paralel tanpa saling mengganggu
+
+BACKEND
+- `TestDatabase::sqlite()` membuat file SQLite baru di folder temporary, tidak membutuhkan server database sama sekali
+- `TestDatabase::mysql()` membuat schema baru di server MySQL yang url nya diambil dari environment variable `TEST_MYSQL_URL`,
+misal `TEST_MYSQL_URL=mysql://root@localhost:3306 cargo test -- --include-ignored`
+- Migration SQLite ada di folder `migrations/sqlite`, karena beberapa perintah DDL MySQL tidak didukung SQLite
+- Kedua folder migration harus menghasilkan tabel dan kolom yang sama, test di bawah membandingkan keduanya,
+jadi migration MySQL baru yang lupa dibuatkan pasangan SQLite nya akan membuat test gagal
+*/
+
+pub static SQLITE_MIGRATOR: Migrator = sqlx::migrate!("./migrations/sqlite");
+
+pub const MYSQL_URL_VAR: &str = "TEST_MYSQL_URL";
+
+const SEED: &str = include_str!("../fixtures/seed.sql");
+
+static COUNTER: AtomicU32 = AtomicU32::new(0);
+
+fn unique_name() -> String {
+    format!(
+        "test_{}_{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+enum Cleanup {
+    MySql {
+        options: Box<MySqlConnectOptions>,
+        name: String,
+    },
+    Sqlite {
+        path: PathBuf,
+    },
+}
+
+pub struct TestDatabase<DB: Database> {
+    pool: Pool<DB>,
+    cleanup: Cleanup,
+}
+
+impl<DB: Database> TestDatabase<DB> {
+    pub fn pool(&self) -> &Pool<DB> {
+        &self.pool
+    }
+}
+
+impl TestDatabase<Sqlite> {
+    /// Creates a migrated and seeded SQLite file in the temp directory.
+    pub async fn sqlite() -> Result<Self, Error> {
+        let path = std::env::temp_dir().join(format!("belajar-rust-database-{}.db", unique_name()));
+        let options = SqliteConnectOptions::new()
+            .filename(&path)
+            .create_if_missing(true)
+            .foreign_keys(true);
+
+        let database = TestDatabase {
+            pool: SqlitePoolOptions::new().connect_lazy_with(options),
+            cleanup: Cleanup::Sqlite { path },
+        };
+        SQLITE_MIGRATOR.run(&database.pool).await?;
+        sqlx::raw_sql(SEED).execute(&database.pool).await?;
+        Ok(database)
+    }
+}
+
+impl TestDatabase<MySql> {
+    /// Creates a migrated and seeded schema on the server in `TEST_MYSQL_URL`.
+    pub async fn mysql() -> Result<Self, Error> {
+        let url = std::env::var(MYSQL_URL_VAR)
+            .map_err(|_| Error::Configuration(format!("{} is not set", MYSQL_URL_VAR).into()))?;
+        Self::mysql_with(&url).await
+    }
+
+    pub async fn mysql_with(url: &str) -> Result<Self, Error> {
+        let options = MySqlConnectOptions::from_str(url)?;
+        let name = unique_name();
+
+        let mut connection = MySqlConnection::connect_with(&options).await?;
+        connection
+            .execute(format!("create database `{}`", name).as_str())
+            .await?;
+        connection.close().await?;
+
+        let database = TestDatabase {
+            pool: MySqlPoolOptions::new().connect_lazy_with(options.clone().database(&name)),
+            cleanup: Cleanup::MySql {
+                options: Box::new(options),
+                name,
+            },
+        };
+        MIGRATOR.run(&database.pool).await?;
+        sqlx::raw_sql(SEED).execute(&database.pool).await?;
+        Ok(database)
+    }
+}
+
+impl<DB: Database> Drop for TestDatabase<DB> {
+    fn drop(&mut self) {
+        match &self.cleanup {
+            Cleanup::Sqlite { path } => {
+                for suffix in ["", "-wal", "-shm"] {
+                    let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+                }
+            }
+            Cleanup::MySql { options, name } => {
+                // Drop cannot await, so the schema is dropped on its own thread and runtime.
+                let options = options.as_ref().clone();
+                let sql = format!("drop database if exists `{}`", name);
+                let _ = std::thread::spawn(move || {
+                    let runtime = tokio::runtime::Builder::new_current_thread()
+                        .enable_all()
+                        .build()
+                        .ok()?;
+                    runtime.block_on(async {
+                        let mut connection = MySqlConnection::connect_with(&options).await.ok()?;
+                        connection.execute(sql.as_str()).await.ok()?;
+                        connection.close().await.ok()
+                    })
+                })
+                .join();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use sqlx::{Error, MySqlPool, SqlitePool};
+
+    use super::{Cleanup, TestDatabase};
+    use crate::migrate::MIGRATOR;
+
+    type Schema = BTreeMap<String, BTreeSet<String>>;
+
+    #[tokio::test]
+    async fn test_sqlite_database_is_isolated() -> Result<(), Error> {
+        let first = TestDatabase::sqlite().await?;
+        let second = TestDatabase::sqlite().await?;
+
+        sqlx::query("insert into category(id, name, description) values ('X', 'Isolated', '')")
+            .execute(first.pool())
+            .await?;
+
+        assert_eq!(3, count_categories(first.pool()).await?);
+        assert_eq!(2, count_categories(second.pool()).await?);
+        Ok(())
+    }
+
+    async fn count_categories(pool: &SqlitePool) -> Result<i64, Error> {
+        sqlx::query_scalar("select count(*) from category")
+            .fetch_one(pool)
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_database_is_removed_on_drop() -> Result<(), Error> {
+        let database = TestDatabase::sqlite().await?;
+        let Cleanup::Sqlite { path } = &database.cleanup else {
+            unreachable!()
+        };
+        let path = path.clone();
+        assert!(path.exists());
+
+        drop(database);
+        assert!(!path.exists());
+        Ok(())
+    }
+
+    /// Replays the DDL of the embedded MySQL migrations without a server. Only the
+    /// statement forms used in `migrations/` are understood, anything else is skipped.
+    fn mysql_migration_schema() -> Schema {
+        let mut schema = Schema::new();
+        for migration in MIGRATOR
+            .iter()
+            .filter(|migration| !migration.migration_type.is_down_migration())
+        {
+            let sql: String = migration
+                .sql
+                .lines()
+                .filter(|line| !line.trim_start().starts_with("--"))
+                .collect::<Vec<_>>()
+                .join("\n");
+            for statement in sql.split(';') {
+                let statement = statement.replace('`', "").to_lowercase();
+                let words: Vec<&str> = statement.split_whitespace().collect();
+                match words.as_slice() {
+                    ["create", "table", "if", "not", "exists", table, ..]
+                    | ["create", "table", table, ..] => {
+                        let table = table.trim_end_matches('(').to_string();
+                        let body = &statement
+                            [statement.find('(').unwrap() + 1..statement.rfind(')').unwrap()];
+                        let columns = schema.entry(table).or_default();
+                        columns.extend(column_names(body));
+                    }
+                    ["alter", "table", table, "add", "column", column, ..] => {
+                        schema
+                            .get_mut(*table)
+                            .unwrap_or_else(|| panic!("alter on unknown table {}", table))
+                            .insert(column.to_string());
+                    }
+                    ["drop", "table", "if", "exists", table] | ["drop", "table", table] => {
+                        schema.remove(*table);
+                    }
+                    ["rename", "table", from, "to", to] => {
+                        let columns = schema.remove(*from).unwrap_or_default();
+                        schema.insert(to.to_string(), columns);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        schema
+    }
+
+    fn column_names(body: &str) -> Vec<String> {
+        let mut definitions = vec![String::new()];
+        let mut depth = 0;
+        for c in body.chars() {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                ',' if depth == 0 => {
+                    definitions.push(String::new());
+                    continue;
+                }
+                _ => {}
+            }
+            definitions.last_mut().unwrap().push(c);
+        }
+        definitions
+            .iter()
+            .filter_map(|definition| definition.split_whitespace().next())
+            .filter(|word| {
+                !["primary", "constraint", "index", "key", "unique", "foreign"].contains(word)
+            })
+            .map(str::to_string)
+            .collect()
+    }
+
+    async fn sqlite_schema(pool: &SqlitePool) -> Result<Schema, Error> {
+        let rows: Vec<(String, String)> = sqlx::query_as(
+            "select m.name, p.name from sqlite_master m join pragma_table_info(m.name) p \
+             where m.type = 'table' and m.name not like 'sqlite_%' and m.name != '_sqlx_migrations'",
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(into_schema(rows))
+    }
+
+    async fn mysql_schema(pool: &MySqlPool) -> Result<Schema, Error> {
+        let rows: Vec<(String, String)> = sqlx::query_as(
+            "select cast(table_name as char), cast(column_name as char) from information_schema.columns \
+             where table_schema = database() and table_name != '_sqlx_migrations'",
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(into_schema(rows))
+    }
+
+    fn into_schema(rows: Vec<(String, String)>) -> Schema {
+        let mut schema = Schema::new();
+        for (table, column) in rows {
+            schema.entry(table).or_default().insert(column);
+        }
+        schema
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_migrations_match_mysql_migrations() -> Result<(), Error> {
+        let database = TestDatabase::sqlite().await?;
+        let expected = mysql_migration_schema();
+        assert!(expected["brands"].contains("description"));
+        assert!(!expected.contains_key("categories"));
+        assert_eq!(expected, sqlite_schema(database.pool()).await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[ignore = "requires MySQL, set TEST_MYSQL_URL"]
+    async fn test_mysql_schema_matches_sqlite_schema() -> Result<(), Error> {
+        let mysql = TestDatabase::mysql().await?;
+        let sqlite = TestDatabase::sqlite().await?;
+        assert_eq!(
+            sqlite_schema(sqlite.pool()).await?,
+            mysql_schema(mysql.pool()).await?
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,358 @@
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use sqlx::{Connection, Database, Error, Pool, Transaction, mysql::MySqlDatabaseError};
+
+/*
+WITH TRANSACTION
+- Daripada memanggil `begin()`, `commit()` dan `rollback()` secara manual, kita bisa menggunakan function `with_transaction()`
+- Jika closure mengembalikan Ok, transaction akan di commit, jika mengembalikan Err, transaction akan di rollback
+- Closure menerima `&mut Transaction`, dan harus mengembalikan future yang di `Box::pin`, misal
+`with_transaction(&pool, |tx| Box::pin(async move { ... })).await`
+
+RETRY
+- Di MySQL, dua transaction bisa saling menunggu lock (deadlock), maka salah satu transaction akan dibatalkan oleh MySQL
+- Transaction yang dibatalkan karena deadlock, lock wait timeout atau serialization failure aman untuk diulang dari awal
+- Oleh karena itu, closure bisa dipanggil lebih dari satu kali, dengan jeda (backoff) yang semakin lama setiap kali diulang
+
+SAVEPOINT
+- Transaction di dalam transaction (nested) dibuat menggunakan SAVEPOINT
+- Menggunakan `with_savepoint()`, jika closure mengembalikan Err, hanya perubahan di dalam savepoint tersebut yang dibatalkan,
+sedangkan transaction luarnya tetap bisa dilanjutkan
+- Jika terjadi deadlock di dalam savepoint, MySQL membatalkan seluruh transaction, sehingga rollback ke savepoint gagal,
+error rollback tersebut diabaikan, dan error deadlock nya yang dikembalikan, sehingga transaction luar bisa diulang
+*/
+
+/// Error type of a transaction closure, which must be able to wrap
+/// [`sqlx::Error`] and tell whether the whole transaction may be run again.
+pub trait TransactionError: From<Error> {
+    fn is_retryable(&self) -> bool;
+}
+
+impl TransactionError for Error {
+    fn is_retryable(&self) -> bool {
+        is_retryable(self)
+    }
+}
+
+/// MySQL deadlock (1213), lock wait timeout (1205), SQLSTATE 40001
+/// serialization failure and SQLite busy errors.
+pub fn is_retryable(error: &Error) -> bool {
+    let Error::Database(error) = error else {
+        return false;
+    };
+    if let Some(error) = error.try_downcast_ref::<MySqlDatabaseError>() {
+        return matches!(error.number(), 1205 | 1213) || error.code() == Some("40001");
+    }
+    matches!(error.code().as_deref(), Some("40001" | "5" | "517"))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one.
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(20),
+            max_delay: Duration::from_secs(1),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before the given retry, doubling from `base_delay` up to `max_delay`.
+    pub fn delay(&self, retry: u32) -> Duration {
+        self.base_delay
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+            .min(self.max_delay)
+    }
+
+    pub async fn run<DB, T, E, F>(&self, pool: &Pool<DB>, mut f: F) -> Result<T, E>
+    where
+        DB: Database,
+        E: TransactionError,
+        F: for<'t> FnMut(&'t mut Transaction<'static, DB>) -> BoxFuture<'t, Result<T, E>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match run_once(pool, &mut f).await {
+                Err(error) if error.is_retryable() && attempt < self.max_attempts => {
+                    tokio::time::sleep(self.delay(attempt)).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+async fn run_once<DB, T, E, F>(pool: &Pool<DB>, f: &mut F) -> Result<T, E>
+where
+    DB: Database,
+    E: TransactionError,
+    F: for<'t> FnMut(&'t mut Transaction<'static, DB>) -> BoxFuture<'t, Result<T, E>>,
+{
+    let mut transaction = pool.begin().await?;
+    match f(&mut transaction).await {
+        Ok(value) => {
+            transaction.commit().await?;
+            Ok(value)
+        }
+        Err(error) => {
+            // the closure error is more useful than a failed rollback
+            let _ = transaction.rollback().await;
+            Err(error)
+        }
+    }
+}
+
+/// Runs `f` in a transaction with the default [`RetryPolicy`].
+pub async fn with_transaction<DB, T, E, F>(pool: &Pool<DB>, f: F) -> Result<T, E>
+where
+    DB: Database,
+    E: TransactionError,
+    F: for<'t> FnMut(&'t mut Transaction<'static, DB>) -> BoxFuture<'t, Result<T, E>>,
+{
+    RetryPolicy::default().run(pool, f).await
+}
+
+/// Runs `f` in a savepoint of `transaction`. Errors roll back to the
+/// savepoint only, and are not retried since the outer transaction owns that.
+/// A failed rollback is ignored, the error of `f` is always returned.
+pub async fn with_savepoint<DB, T, E, F>(
+    transaction: &mut Transaction<'_, DB>,
+    f: F,
+) -> Result<T, E>
+where
+    DB: Database,
+    E: From<Error>,
+    F: for<'t, 'c> FnOnce(&'t mut Transaction<'c, DB>) -> BoxFuture<'t, Result<T, E>>,
+{
+    let mut savepoint = Connection::begin(&mut **transaction).await?;
+    match f(&mut savepoint).await {
+        Ok(value) => {
+            savepoint.commit().await?;
+            Ok(value)
+        }
+        Err(error) => {
+            // after a MySQL deadlock the whole transaction is already rolled
+            // back, so the savepoint is gone (1305) and the closure error,
+            // which tells the outer transaction to retry, must be kept
+            let _ = savepoint.rollback().await;
+            Err(error)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicU32, Ordering},
+        time::Duration,
+    };
+
+    use sqlx::{Error, SqlitePool};
+
+    use super::{RetryPolicy, TransactionError, with_savepoint, with_transaction};
+    use crate::testing::TestDatabase;
+
+    #[derive(Debug)]
+    enum TestError {
+        Database(#[allow(dead_code)] Error),
+        Conflict,
+        Invalid,
+    }
+
+    impl From<Error> for TestError {
+        fn from(error: Error) -> Self {
+            TestError::Database(error)
+        }
+    }
+
+    impl TransactionError for TestError {
+        fn is_retryable(&self) -> bool {
+            matches!(self, TestError::Conflict)
+        }
+    }
+
+    async fn insert_category(
+        transaction: &mut sqlx::SqliteConnection,
+        id: &str,
+    ) -> Result<(), Error> {
+        sqlx::query("insert into category(id, name, description) values (?, ?, '')")
+            .bind(id)
+            .bind(id)
+            .execute(transaction)
+            .await?;
+        Ok(())
+    }
+
+    async fn category_exists(pool: &SqlitePool, id: &str) -> Result<bool, Error> {
+        let count: i64 = sqlx::query_scalar("select count(*) from category where id = ?")
+            .bind(id)
+            .fetch_one(pool)
+            .await?;
+        Ok(count > 0)
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(50),
+        };
+        assert_eq!(Duration::from_millis(10), policy.delay(1));
+        assert_eq!(Duration::from_millis(20), policy.delay(2));
+        assert_eq!(Duration::from_millis(40), policy.delay(3));
+        assert_eq!(Duration::from_millis(50), policy.delay(4));
+    }
+
+    #[tokio::test]
+    async fn test_commit_and_rollback() -> Result<(), Error> {
+        let database = TestDatabase::sqlite().await?;
+        let pool = database.pool();
+
+        with_transaction(pool, |tx| {
+            Box::pin(async move { insert_category(tx, "OK").await })
+        })
+        .await?;
+        assert!(category_exists(pool, "OK").await?);
+
+        let result: Result<(), TestError> = with_transaction(pool, |tx| {
+            Box::pin(async move {
+                insert_category(tx, "ERR").await?;
+                Err(TestError::Invalid)
+            })
+        })
+        .await;
+        assert!(matches!(result, Err(TestError::Invalid)));
+        assert!(!category_exists(pool, "ERR").await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_retry_retryable_error() -> Result<(), Error> {
+        let database = TestDatabase::sqlite().await?;
+        let pool = database.pool();
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(1),
+        };
+
+        let attempts = AtomicU32::new(0);
+        let result: Result<u32, TestError> = policy
+            .run(pool, |tx| {
+                let attempt = attempts.fetch_add(1, Ordering::SeqCst) + 1;
+                Box::pin(async move {
+                    insert_category(tx, "RETRY").await?;
+                    if attempt < 3 {
+                        return Err(TestError::Conflict);
+                    }
+                    Ok(attempt)
+                })
+            })
+            .await;
+        assert!(matches!(result, Ok(3)));
+        assert!(category_exists(pool, "RETRY").await?);
+
+        attempts.store(0, Ordering::SeqCst);
+        let result: Result<(), TestError> = policy
+            .run(pool, |_| {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                Box::pin(async { Err(TestError::Conflict) })
+            })
+            .await;
+        assert!(matches!(result, Err(TestError::Conflict)));
+        assert_eq!(3, attempts.load(Ordering::SeqCst));
+
+        attempts.store(0, Ordering::SeqCst);
+        let result: Result<(), TestError> = policy
+            .run(pool, |_| {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                Box::pin(async { Err(TestError::Invalid) })
+            })
+            .await;
+        assert!(matches!(result, Err(TestError::Invalid)));
+        assert_eq!(1, attempts.load(Ordering::SeqCst));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_nested_savepoint() -> Result<(), Error> {
+        let database = TestDatabase::sqlite().await?;
+        let pool = database.pool();
+
+        with_transaction(pool, |tx| {
+            Box::pin(async move {
+                insert_category(tx, "OUTER").await?;
+
+                let result: Result<(), TestError> = with_savepoint(tx, |sp| {
+                    Box::pin(async move {
+                        insert_category(sp, "INNER").await?;
+                        Err(TestError::Invalid)
+                    })
+                })
+                .await;
+                assert!(matches!(result, Err(TestError::Invalid)));
+
+                with_savepoint(tx, |sp| {
+                    Box::pin(async move { insert_category(sp, "INNER_OK").await })
+                })
+                .await
+            })
+        })
+        .await?;
+
+        assert!(category_exists(pool, "OUTER").await?);
+        assert!(!category_exists(pool, "INNER").await?);
+        assert!(category_exists(pool, "INNER_OK").await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_retry_error_from_savepoint() -> Result<(), Error> {
+        let database = TestDatabase::sqlite().await?;
+        let pool = database.pool();
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(1),
+        };
+
+        let attempts = AtomicU32::new(0);
+        let result: Result<u32, TestError> = policy
+            .run(pool, |tx| {
+                let attempt = attempts.fetch_add(1, Ordering::SeqCst) + 1;
+                Box::pin(async move {
+                    insert_category(tx, "OUTER").await?;
+                    with_savepoint(tx, |sp| {
+                        Box::pin(async move {
+                            insert_category(sp, "INNER").await?;
+                            if attempt == 1 {
+                                // like a deadlock, the server rolls back the
+                                // whole transaction, savepoint included
+                                sqlx::query("rollback").execute(&mut **sp).await?;
+                                return Err(TestError::Conflict);
+                            }
+                            Ok(())
+                        })
+                    })
+                    .await?;
+                    Ok(attempt)
+                })
+            })
+            .await;
+        assert!(matches!(result, Ok(2)), "{:?}", result);
+        assert!(category_exists(pool, "OUTER").await?);
+        assert!(category_exists(pool, "INNER").await?);
+        Ok(())
+    }
+}
@@ -0,0 +1,54 @@
+use std::time::Duration;
+
+/*
+BACKOFF
+- Saat koneksi ke Redis terputus, koneksi dibuat ulang setelah jeda tertentu
+- Jeda nya dimulai dari `min` dan digandakan setiap kali gagal (exponential backoff) sampai maksimal `max`,
+sehingga Redis yang sedang restart tidak dibanjiri percobaan koneksi
+- Setelah koneksi berhasil, `reset` mengembalikan jeda ke `min`
+*/
+
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    min: Duration,
+    max: Duration,
+    current: Duration,
+}
+
+impl Backoff {
+    pub fn new(min: Duration, max: Duration) -> Self {
+        Backoff {
+            min,
+            max: max.max(min),
+            current: min,
+        }
+    }
+
+    /// Returns how long to wait before the next attempt.
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self.current;
+        self.current = self.current.saturating_mul(2).min(self.max);
+        delay
+    }
+
+    pub fn reset(&mut self) {
+        self.current = self.min;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::Backoff;
+
+    #[test]
+    fn test_backoff() {
+        let mut backoff = Backoff::new(Duration::from_millis(100), Duration::from_millis(500));
+        let delays: Vec<u128> = (0..5).map(|_| backoff.next_delay().as_millis()).collect();
+        assert_eq!(vec![100, 200, 400, 500, 500], delays);
+
+        backoff.reset();
+        assert_eq!(Duration::from_millis(100), backoff.next_delay());
+    }
+}
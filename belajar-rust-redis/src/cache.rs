@@ -0,0 +1,558 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use belajar_rust_database::{
+    model::{Product, ProductId, Seller, SellerId, Transaction, TransactionId},
+    repository::{
+        CheckoutError, IdRange, NewProduct, NewSeller, NewTransactionItem, Page, ProductRepository,
+        SellerRepository, TransactionRepository, UpdateProductError,
+    },
+};
+use chrono::{DateTime, Utc};
+use redis::{AsyncCommands, RedisError};
+use serde::{Serialize, de::DeserializeOwned};
+use sqlx::Error;
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+
+use crate::connection::RedisConnection;
+
+/*
+READ THROUGH CACHE
+- Saat data dibaca, cache di Redis diperiksa terlebih dahulu, jika tidak ada (miss), data dibaca dari database,
+lalu disimpan ke Redis dalam bentuk JSON dengan TTL menggunakan `SET key value EX ttl`
+- Saat data diubah atau dihapus, key nya dihapus dari Redis (invalidation), sehingga pembacaan berikutnya
+mengambil data terbaru dari database
+- TTL membatasi berapa lama data lama bisa terlihat, misal jika invalidation gagal atau data diubah dari aplikasi lain
+- Jika Redis error, cache dianggap miss dan data tetap dibaca dari database, error nya hanya dihitung di `CacheStats`
+
+STAMPEDE
+- Jika banyak request membaca key yang sama ketika key tersebut belum ada di cache,
+semua request akan membaca database secara bersamaan (cache stampede)
+- `SingleFlight` membuat hanya satu task yang membaca database untuk satu key, task lain menunggu,
+lalu membaca hasilnya dari cache
+- Invalidation juga menunggu load yang sedang berjalan, sehingga data lama yang sedang dimuat tidak tersimpan setelah dihapus
+*/
+
+/// Snapshot of the cache counters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Redis or serialization errors, lookups fall back to the database then.
+    pub errors: u64,
+}
+
+impl CacheStats {
+    pub fn hit_ratio(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+#[derive(Default)]
+struct Counters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    errors: AtomicU64,
+}
+
+impl Counters {
+    fn increment(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+type Flights = Arc<Mutex<HashMap<String, Arc<AsyncMutex<()>>>>>;
+
+/// Lets only one task at a time work on a key, the others wait in
+/// [`SingleFlight::enter`] until the [`Flight`] is dropped.
+#[derive(Clone, Default)]
+pub struct SingleFlight {
+    flights: Flights,
+}
+
+pub struct Flight {
+    flights: Flights,
+    key: String,
+    guard: Option<OwnedMutexGuard<()>>,
+}
+
+impl SingleFlight {
+    pub async fn enter(&self, key: &str) -> Flight {
+        let lock = {
+            let mut flights = self.flights.lock().unwrap();
+            flights.entry(key.to_string()).or_default().clone()
+        };
+        Flight {
+            flights: self.flights.clone(),
+            key: key.to_string(),
+            guard: Some(lock.lock_owned().await),
+        }
+    }
+
+    /// Number of keys that have a task working on them or waiting.
+    pub fn len(&self) -> usize {
+        self.flights.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Drop for Flight {
+    fn drop(&mut self) {
+        let mut flights = self.flights.lock().unwrap();
+        self.guard.take();
+        // only the map still holds the lock, so nobody is waiting for this key
+        if flights
+            .get(&self.key)
+            .is_some_and(|lock| Arc::strong_count(lock) == 1)
+        {
+            flights.remove(&self.key);
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RedisCache {
+    connection: RedisConnection,
+    prefix: String,
+    ttl: Duration,
+    counters: Arc<Counters>,
+    flights: SingleFlight,
+}
+
+impl RedisCache {
+    /// Keys are `<prefix>:<entity>:<id>`, entries expire after `ttl`
+    /// (rounded down to seconds, at least one).
+    pub fn new(connection: RedisConnection, prefix: &str, ttl: Duration) -> Self {
+        RedisCache {
+            connection,
+            prefix: prefix.to_string(),
+            ttl,
+            counters: Arc::default(),
+            flights: SingleFlight::default(),
+        }
+    }
+
+    pub fn key(&self, entity: &str, id: impl Display) -> String {
+        format!("{}:{}:{}", self.prefix, entity, id)
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.counters.hits.load(Ordering::Relaxed),
+            misses: self.counters.misses.load(Ordering::Relaxed),
+            errors: self.counters.errors.load(Ordering::Relaxed),
+        }
+    }
+
+    async fn read<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let value: Result<Option<String>, RedisError> = self.connection.clone().get(key).await;
+        let value = match value {
+            Ok(value) => value?,
+            Err(_) => {
+                Counters::increment(&self.counters.errors);
+                return None;
+            }
+        };
+        serde_json::from_str(&value)
+            .inspect_err(|_| Counters::increment(&self.counters.errors))
+            .ok()
+    }
+
+    async fn write<T: Serialize>(&self, key: &str, value: &T) {
+        let Ok(value) = serde_json::to_string(value) else {
+            Counters::increment(&self.counters.errors);
+            return;
+        };
+        let result: Result<(), RedisError> = self
+            .connection
+            .clone()
+            .set_ex(key, value, self.ttl.as_secs().max(1))
+            .await;
+        if result.is_err() {
+            Counters::increment(&self.counters.errors);
+        }
+    }
+
+    /// Returns the cached value of `key`, or calls `load` and caches what it
+    /// returns. `None` is not cached, so missing rows are loaded every time.
+    pub async fn get_or_load<T, E, F, Fut>(&self, key: &str, load: F) -> Result<Option<T>, E>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Option<T>, E>>,
+    {
+        if let Some(value) = self.read(key).await {
+            Counters::increment(&self.counters.hits);
+            return Ok(Some(value));
+        }
+
+        let _flight = self.flights.enter(key).await;
+        // another task may have loaded it while this one was waiting
+        if let Some(value) = self.read(key).await {
+            Counters::increment(&self.counters.hits);
+            return Ok(Some(value));
+        }
+
+        Counters::increment(&self.counters.misses);
+        let value = load().await?;
+        if let Some(value) = &value {
+            self.write(key, value).await;
+        }
+        Ok(value)
+    }
+
+    /// Removes the keys, after any load of them that is still running.
+    pub async fn invalidate(&self, keys: &[String]) {
+        if keys.is_empty() {
+            return;
+        }
+
+        let mut flights = Vec::with_capacity(keys.len());
+        for key in keys {
+            flights.push(self.flights.enter(key).await);
+        }
+        let result: Result<(), RedisError> = self.connection.clone().del(keys).await;
+        if result.is_err() {
+            Counters::increment(&self.counters.errors);
+        }
+    }
+}
+
+/*
+CACHED REPOSITORY
+- `Cached` membungkus repository dari belajar-rust-database, dan mengimplementasikan trait repository yang sama,
+sehingga kode yang menggunakan trait tidak perlu diubah
+- Hanya `find_by_id` yang di-cache, list dan pencarian selalu membaca database
+- Checkout mengubah stock product, sehingga cache product yang dibeli juga dihapus
+*/
+
+const PRODUCTS: &str = "products";
+const SELLERS: &str = "sellers";
+
+#[derive(Clone)]
+pub struct Cached<R> {
+    inner: R,
+    cache: RedisCache,
+}
+
+impl<R> Cached<R> {
+    pub fn new(inner: R, cache: RedisCache) -> Self {
+        Cached { inner, cache }
+    }
+
+    pub fn cache(&self) -> &RedisCache {
+        &self.cache
+    }
+}
+
+#[async_trait]
+impl<R: ProductRepository> ProductRepository for Cached<R> {
+    async fn find_by_id(&self, id: ProductId) -> Result<Option<Product>, Error> {
+        let key = self.cache.key(PRODUCTS, id);
+        self.cache
+            .get_or_load(&key, || self.inner.find_by_id(id))
+            .await
+    }
+
+    async fn list(&self, page: &Page<ProductId>) -> Result<Vec<Product>, Error> {
+        self.inner.list(page).await
+    }
+
+    async fn search_by_name(
+        &self,
+        name: &str,
+        page: &Page<ProductId>,
+    ) -> Result<Vec<Product>, Error> {
+        self.inner.search_by_name(name, page).await
+    }
+
+    async fn insert(&self, actor: &str, product: &NewProduct) -> Result<ProductId, Error> {
+        self.inner.insert(actor, product).await
+    }
+
+    async fn insert_many(
+        &self,
+        actor: &str,
+        products: &[NewProduct],
+    ) -> Result<IdRange<ProductId>, Error> {
+        self.inner.insert_many(actor, products).await
+    }
+
+    async fn update(&self, actor: &str, product: &Product) -> Result<Product, UpdateProductError> {
+        let result = self.inner.update(actor, product).await;
+        self.cache
+            .invalidate(&[self.cache.key(PRODUCTS, product.id)])
+            .await;
+        result
+    }
+
+    async fn delete(&self, actor: &str, id: ProductId) -> Result<bool, Error> {
+        let result = self.inner.delete(actor, id).await;
+        self.cache.invalidate(&[self.cache.key(PRODUCTS, id)]).await;
+        result
+    }
+}
+
+#[async_trait]
+impl<R: SellerRepository> SellerRepository for Cached<R> {
+    async fn find_by_id(&self, id: SellerId) -> Result<Option<Seller>, Error> {
+        let key = self.cache.key(SELLERS, id);
+        self.cache
+            .get_or_load(&key, || self.inner.find_by_id(id))
+            .await
+    }
+
+    async fn find_by_ids(&self, ids: &[SellerId]) -> Result<Vec<Seller>, Error> {
+        self.inner.find_by_ids(ids).await
+    }
+
+    async fn list(&self, page: &Page<SellerId>) -> Result<Vec<Seller>, Error> {
+        self.inner.list(page).await
+    }
+
+    async fn search_by_name(
+        &self,
+        name: &str,
+        page: &Page<SellerId>,
+    ) -> Result<Vec<Seller>, Error> {
+        self.inner.search_by_name(name, page).await
+    }
+
+    async fn insert(&self, actor: &str, seller: &NewSeller) -> Result<SellerId, Error> {
+        self.inner.insert(actor, seller).await
+    }
+
+    async fn insert_many(
+        &self,
+        actor: &str,
+        sellers: &[NewSeller],
+    ) -> Result<IdRange<SellerId>, Error> {
+        self.inner.insert_many(actor, sellers).await
+    }
+
+    async fn update(&self, actor: &str, seller: &Seller) -> Result<bool, Error> {
+        let result = self.inner.update(actor, seller).await;
+        self.cache
+            .invalidate(&[self.cache.key(SELLERS, seller.id)])
+            .await;
+        result
+    }
+
+    async fn delete(&self, actor: &str, id: SellerId) -> Result<bool, Error> {
+        let result = self.inner.delete(actor, id).await;
+        self.cache.invalidate(&[self.cache.key(SELLERS, id)]).await;
+        result
+    }
+}
+
+#[async_trait]
+impl<R: TransactionRepository> TransactionRepository for Cached<R> {
+    async fn find_by_id(&self, id: TransactionId) -> Result<Option<Transaction>, Error> {
+        self.inner.find_by_id(id).await
+    }
+
+    async fn list(&self, page: &Page<TransactionId>) -> Result<Vec<Transaction>, Error> {
+        self.inner.list(page).await
+    }
+
+    async fn checkout(
+        &self,
+        actor: &str,
+        items: &[NewTransactionItem],
+    ) -> Result<Transaction, CheckoutError> {
+        let transaction = self.inner.checkout(actor, items).await?;
+        let keys: Vec<String> = transaction
+            .items
+            .iter()
+            .map(|item| self.cache.key(PRODUCTS, item.product_id))
+            .collect();
+        self.cache.invalidate(&keys).await;
+        Ok(transaction)
+    }
+
+    async fn units_sold(
+        &self,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<Vec<(ProductId, u64)>, Error> {
+        self.inner.units_sold(since).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+        time::Duration,
+    };
+
+    use belajar_rust_database::{
+        model::ProductId,
+        repository::{
+            NewTransactionItem, ProductRepository, SqliteProductRepository,
+            SqliteTransactionRepository, TransactionRepository,
+        },
+        testing::TestDatabase,
+    };
+    use sqlx::Error;
+
+    use super::{CacheStats, Cached, RedisCache, SingleFlight};
+    use crate::testing;
+
+    /// Prefix unique to one test run, so runs do not read each other's keys.
+    fn prefix(name: &str) -> String {
+        format!("test:{}:{}", std::process::id(), name)
+    }
+
+    #[test]
+    fn test_hit_ratio() {
+        assert_eq!(0.0, CacheStats::default().hit_ratio());
+        let stats = CacheStats {
+            hits: 3,
+            misses: 1,
+            errors: 0,
+        };
+        assert_eq!(0.75, stats.hit_ratio());
+    }
+
+    #[tokio::test]
+    async fn test_single_flight() {
+        let flights = SingleFlight::default();
+        let active = Arc::new(AtomicUsize::new(0));
+        let max_active = Arc::new(AtomicUsize::new(0));
+
+        let tasks: Vec<_> = (0..8)
+            .map(|_| {
+                let (flights, active, max_active) =
+                    (flights.clone(), active.clone(), max_active.clone());
+                tokio::spawn(async move {
+                    let _flight = flights.enter("products:1").await;
+                    let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_active.fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(5)).await;
+                    active.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect();
+        let _other = flights.enter("products:2").await;
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        assert_eq!(1, max_active.load(Ordering::SeqCst));
+        assert_eq!(1, flights.len());
+        drop(_other);
+        assert!(flights.is_empty());
+    }
+
+    #[tokio::test]
+    #[ignore = "requires Redis, set TEST_REDIS_URL"]
+    async fn test_stampede_loads_once() -> Result<(), Box<dyn std::error::Error>> {
+        let cache = RedisCache::new(
+            testing::connection().await?,
+            &prefix("stampede"),
+            Duration::from_secs(10),
+        );
+        let key = cache.key("products", 1);
+        cache.invalidate(std::slice::from_ref(&key)).await;
+        let loads = Arc::new(AtomicUsize::new(0));
+
+        let tasks: Vec<_> = (0..10)
+            .map(|_| {
+                let (cache, key, loads) = (cache.clone(), key.clone(), loads.clone());
+                tokio::spawn(async move {
+                    cache
+                        .get_or_load(&key, || async {
+                            loads.fetch_add(1, Ordering::SeqCst);
+                            tokio::time::sleep(Duration::from_millis(50)).await;
+                            Ok::<_, Error>(Some("Laptop Pro".to_string()))
+                        })
+                        .await
+                })
+            })
+            .collect();
+        for task in tasks {
+            assert_eq!(Some("Laptop Pro".to_string()), task.await??);
+        }
+
+        assert_eq!(1, loads.load(Ordering::SeqCst));
+        assert_eq!(
+            CacheStats {
+                hits: 9,
+                misses: 1,
+                errors: 0
+            },
+            cache.stats()
+        );
+        cache.invalidate(&[key]).await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[ignore = "requires Redis, set TEST_REDIS_URL"]
+    async fn test_cached_repository() -> Result<(), Box<dyn std::error::Error>> {
+        let database = TestDatabase::sqlite().await?;
+        let cache = RedisCache::new(
+            testing::connection().await?,
+            &prefix("repository"),
+            Duration::from_secs(10),
+        );
+        cache
+            .invalidate(&[cache.key("products", 1), cache.key("products", 2)])
+            .await;
+        let products = Cached::new(
+            SqliteProductRepository::new(database.pool().clone()),
+            cache.clone(),
+        );
+        let transactions = Cached::new(
+            SqliteTransactionRepository::new(database.pool().clone()),
+            cache.clone(),
+        );
+
+        let product = products.find_by_id(ProductId(1)).await?.unwrap();
+        assert_eq!(
+            Some(product.clone()),
+            products.find_by_id(ProductId(1)).await?
+        );
+        assert_eq!((1, 1), (cache.stats().hits, cache.stats().misses));
+
+        let mut changed = product.clone();
+        changed.price = 14_500_000;
+        let changed = products.update("admin", &changed).await?;
+        assert_eq!(Some(changed), products.find_by_id(ProductId(1)).await?);
+
+        transactions
+            .checkout(
+                "kasir",
+                &[NewTransactionItem {
+                    product_id: ProductId(1),
+                    quantity: 2,
+                }],
+            )
+            .await?;
+        assert_eq!(48, products.find_by_id(ProductId(1)).await?.unwrap().stock);
+
+        assert!(products.delete("admin", ProductId(1)).await?);
+        assert_eq!(None, products.find_by_id(ProductId(1)).await?);
+        assert_eq!(0, cache.stats().errors);
+        Ok(())
+    }
+}
@@ -0,0 +1,591 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use config::{Config, ConfigError, Environment, File, FileFormat};
+use log::{info, warn};
+use redis::{
+    Client, Cmd, ErrorKind, Pipeline, RedisError, RedisFuture, Value,
+    aio::{ConnectionLike, MultiplexedConnection, PubSub},
+    cluster::ClusterClient,
+    cluster_async::ClusterConnection,
+    sentinel::{SentinelClient, SentinelServerType},
+};
+use serde::Deserialize;
+use tokio::time::Instant;
+
+use crate::backoff::Backoff;
+
+/*
+CONNECTION
+- `RedisConnection` adalah pengganti `MultiplexedConnection` yang dipakai semua module di project ini,
+cara pakainya sama, karena ia juga implement `ConnectionLike`, sehingga bisa dipakai dengan `AsyncCommands`,
+`redis::cmd()` dan `redis::pipe()`
+- Konfigurasi dibaca menggunakan library Config dari file `redis.yaml` dan environment variable dengan prefix `REDIS`,
+misal `REDIS_TOPOLOGY=sentinel`, `REDIS_URLS=redis://sentinel-1:26379,redis://sentinel-2:26379` dan `REDIS_MASTER_NAME=mymaster`
+- Topology `standalone` terhubung ke satu server, `sentinel` menanyakan alamat master ke Sentinel,
+dan `cluster` terhubung ke Redis Cluster, `urls` berisi alamat server, Sentinel, atau node cluster sesuai topology nya
+- Jika koneksi terputus atau server menjadi replica setelah failover (error `READONLY`), koneksi dibuang
+dan dibuat ulang dengan backoff, untuk Sentinel alamat master ditanyakan ulang, sehingga koneksi mengikuti master yang baru
+- Perintah yang sedang berjalan saat koneksi terputus tidak dikirim ulang, karena bisa saja sudah dijalankan server,
+perintah hanya dikirim ulang sekali jika server pasti belum menjalankannya, misal koneksi ditolak atau error `READONLY`
+- Setiap perintah diberi timeout `command_timeout_ms`, kecuali perintah blocking seperti `BLPOP` dan `XREADGROUP ... BLOCK`,
+begitu juga pipeline yang berisi salah satu perintah blocking tersebut,
+menunggu koneksi dibuat ulang dibatasi `connect_timeout_ms`, sehingga request tidak tertahan lama saat Redis mati,
+perintah yang timeout juga membuat koneksi dibuat ulang, karena server yang tidak menjawab bisa jadi sudah diganti
+- `health_check` menjalankan `PING` secara berkala, untuk Sentinel juga memastikan server nya masih master,
+dan membuang koneksi jika gagal, sehingga server yang tidak menjawab sama sekali juga terdeteksi
+
+CLUSTER
+- Di Redis Cluster, perintah dengan beberapa key (misal `PFMERGE`, `RENAME`, `MULTI`) hanya bisa dijalankan
+jika semua key nya berada di slot yang sama, gunakan hash tag di prefix key, misal `{minipos}`,
+agar semua key dengan prefix tersebut berada di slot yang sama
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Topology {
+    #[default]
+    Standalone,
+    Sentinel,
+    Cluster,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ConnectionSettings {
+    pub topology: Topology,
+    /// Server, Sentinel or cluster node addresses, depending on `topology`.
+    pub urls: Vec<String>,
+    /// Master monitored by the Sentinels.
+    pub master_name: Option<String>,
+    pub connect_timeout_ms: u64,
+    pub command_timeout_ms: u64,
+    pub reconnect_min_ms: u64,
+    pub reconnect_max_ms: u64,
+    pub health_check_secs: u64,
+}
+
+impl Default for ConnectionSettings {
+    fn default() -> Self {
+        ConnectionSettings {
+            topology: Topology::Standalone,
+            urls: vec!["redis://localhost:6379".to_string()],
+            master_name: None,
+            connect_timeout_ms: 3000,
+            command_timeout_ms: 1000,
+            reconnect_min_ms: 100,
+            reconnect_max_ms: 5000,
+            health_check_secs: 10,
+        }
+    }
+}
+
+impl ConnectionSettings {
+    /// Reads `redis.yaml` when it exists, then `REDIS_*` environment variables.
+    pub fn load() -> Result<Self, ConfigError> {
+        Config::builder()
+            .add_source(File::new("redis", FileFormat::Yaml).required(false))
+            .add_source(
+                Environment::with_prefix("REDIS")
+                    .try_parsing(true)
+                    .list_separator(",")
+                    .with_list_parse_key("urls"),
+            )
+            .build()?
+            .try_deserialize()
+    }
+
+    pub fn connect_timeout(&self) -> Duration {
+        Duration::from_millis(self.connect_timeout_ms)
+    }
+
+    pub fn command_timeout(&self) -> Duration {
+        Duration::from_millis(self.command_timeout_ms)
+    }
+
+    pub fn health_check_interval(&self) -> Duration {
+        Duration::from_secs(self.health_check_secs)
+    }
+}
+
+/// Commands that wait on the server, they are not given the command timeout.
+const BLOCKING_COMMANDS: [&str; 9] = [
+    "BLPOP",
+    "BRPOP",
+    "BLMOVE",
+    "BRPOPLPUSH",
+    "BLMPOP",
+    "BZPOPMIN",
+    "BZPOPMAX",
+    "BZMPOP",
+    "WAIT",
+];
+
+fn is_blocking(cmd: &Cmd) -> bool {
+    let mut args = cmd.args_iter().filter_map(|arg| match arg {
+        redis::Arg::Simple(arg) => Some(arg),
+        redis::Arg::Cursor => None,
+    });
+    let Some(name) = args.next() else {
+        return false;
+    };
+    if BLOCKING_COMMANDS
+        .iter()
+        .any(|command| command.as_bytes().eq_ignore_ascii_case(name))
+    {
+        return true;
+    }
+    (name.eq_ignore_ascii_case(b"XREAD") || name.eq_ignore_ascii_case(b"XREADGROUP"))
+        && args.any(|arg| arg.eq_ignore_ascii_case(b"BLOCK"))
+}
+
+/// A pipeline waits as long as its slowest command, so one blocking command
+/// is enough.
+fn is_blocking_pipeline(pipeline: &Pipeline) -> bool {
+    pipeline.cmd_iter().any(is_blocking)
+}
+
+/// Errors after which the current connection is replaced.
+fn needs_reconnect(error: &RedisError) -> bool {
+    error.is_unrecoverable_error() || error.is_timeout() || error.kind() == ErrorKind::ReadOnly
+}
+
+/// Errors of commands the server certainly did not run, so they can be sent again.
+fn is_rejected(error: &RedisError) -> bool {
+    error.is_connection_refusal() || error.kind() == ErrorKind::ReadOnly
+}
+
+fn timed_out(message: &str) -> RedisError {
+    std::io::Error::new(std::io::ErrorKind::TimedOut, message.to_string()).into()
+}
+
+enum Connector {
+    Standalone(Client),
+    Sentinel(tokio::sync::Mutex<SentinelClient>),
+    Cluster(ClusterClient),
+}
+
+#[derive(Clone)]
+enum Node {
+    Single(MultiplexedConnection),
+    Cluster(ClusterConnection),
+}
+
+impl ConnectionLike for Node {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        match self {
+            Node::Single(connection) => connection.req_packed_command(cmd),
+            Node::Cluster(connection) => connection.req_packed_command(cmd),
+        }
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        pipeline: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        match self {
+            Node::Single(connection) => connection.req_packed_commands(pipeline, offset, count),
+            Node::Cluster(connection) => connection.req_packed_commands(pipeline, offset, count),
+        }
+    }
+
+    fn get_db(&self) -> i64 {
+        match self {
+            Node::Single(connection) => connection.get_db(),
+            Node::Cluster(connection) => connection.get_db(),
+        }
+    }
+}
+
+struct Reconnect {
+    generation: u64,
+    backoff: Backoff,
+    /// Earliest time of the next attempt after a failed one.
+    retry_at: Option<Instant>,
+}
+
+struct Shared {
+    settings: ConnectionSettings,
+    connector: Connector,
+    /// The connection in use and its generation, `None` after it broke.
+    current: Mutex<Option<(u64, Node)>>,
+    /// Held while connecting, so only one caller reconnects at a time.
+    reconnect: tokio::sync::Mutex<Reconnect>,
+}
+
+/// Connection to Redis that reconnects by itself, cheap to clone like
+/// `MultiplexedConnection`.
+#[derive(Clone)]
+pub struct RedisConnection {
+    shared: Arc<Shared>,
+}
+
+impl RedisConnection {
+    /// Connects right away, so wrong settings fail at startup.
+    pub async fn connect(settings: &ConnectionSettings) -> Result<Self, RedisError> {
+        let connector = match settings.topology {
+            Topology::Standalone => {
+                let url = settings.urls.first().ok_or_else(|| {
+                    RedisError::from((ErrorKind::InvalidClientConfig, "no Redis url configured"))
+                })?;
+                Connector::Standalone(Client::open(url.as_str())?)
+            }
+            Topology::Sentinel => {
+                let master_name = settings.master_name.clone().ok_or_else(|| {
+                    RedisError::from((
+                        ErrorKind::InvalidClientConfig,
+                        "master_name is required for sentinel",
+                    ))
+                })?;
+                Connector::Sentinel(tokio::sync::Mutex::new(SentinelClient::build(
+                    settings.urls.clone(),
+                    master_name,
+                    None,
+                    SentinelServerType::Master,
+                )?))
+            }
+            Topology::Cluster => Connector::Cluster(
+                ClusterClient::builder(settings.urls.clone())
+                    .connection_timeout(settings.connect_timeout())
+                    .build()?,
+            ),
+        };
+        let backoff = Backoff::new(
+            Duration::from_millis(settings.reconnect_min_ms),
+            Duration::from_millis(settings.reconnect_max_ms),
+        );
+        let connection = RedisConnection {
+            shared: Arc::new(Shared {
+                settings: settings.clone(),
+                connector,
+                current: Mutex::new(None),
+                reconnect: tokio::sync::Mutex::new(Reconnect {
+                    generation: 0,
+                    backoff,
+                    retry_at: None,
+                }),
+            }),
+        };
+        connection.node().await?;
+        Ok(connection)
+    }
+
+    pub fn settings(&self) -> &ConnectionSettings {
+        &self.shared.settings
+    }
+
+    async fn open(&self) -> Result<Node, RedisError> {
+        let timeout = self.shared.settings.connect_timeout();
+        let config = redis::AsyncConnectionConfig::new().set_connection_timeout(timeout);
+        match &self.shared.connector {
+            Connector::Standalone(client) => Ok(Node::Single(
+                client
+                    .get_multiplexed_async_connection_with_config(&config)
+                    .await?,
+            )),
+            Connector::Sentinel(sentinel) => {
+                // Asking the Sentinels every time, the master may have changed since the last connection.
+                let client = sentinel.lock().await.async_get_client().await?;
+                Ok(Node::Single(
+                    client
+                        .get_multiplexed_async_connection_with_config(&config)
+                        .await?,
+                ))
+            }
+            Connector::Cluster(client) => Ok(Node::Cluster(client.get_async_connection().await?)),
+        }
+    }
+
+    fn current(&self) -> Option<(u64, Node)> {
+        self.shared.current.lock().unwrap().clone()
+    }
+
+    /// The current connection, connecting first when there is none.
+    async fn node(&self) -> Result<(u64, Node), RedisError> {
+        if let Some(current) = self.current() {
+            return Ok(current);
+        }
+        let deadline = Instant::now() + self.shared.settings.connect_timeout();
+        let mut reconnect = tokio::time::timeout_at(deadline, self.shared.reconnect.lock())
+            .await
+            .map_err(|_| timed_out("timed out waiting for a Redis connection"))?;
+        // Another caller may have connected while this one waited for the lock.
+        if let Some(current) = self.current() {
+            return Ok(current);
+        }
+        loop {
+            if let Some(retry_at) = reconnect.retry_at {
+                // Failing right away instead of waiting for an attempt that comes too late.
+                if retry_at >= deadline {
+                    return Err(timed_out("Redis is unavailable, waiting to reconnect"));
+                }
+                tokio::time::sleep_until(retry_at).await;
+            }
+            let error = match tokio::time::timeout_at(deadline, self.open()).await {
+                Ok(Ok(node)) => {
+                    reconnect.backoff.reset();
+                    reconnect.retry_at = None;
+                    reconnect.generation += 1;
+                    let current = (reconnect.generation, node);
+                    *self.shared.current.lock().unwrap() = Some(current.clone());
+                    info!("Connected to Redis ({:?})", self.shared.settings.topology);
+                    return Ok(current);
+                }
+                Ok(Err(error)) => error,
+                Err(_) => timed_out("timed out connecting to Redis"),
+            };
+            let retry_at = Instant::now() + reconnect.backoff.next_delay();
+            reconnect.retry_at = Some(retry_at);
+            if retry_at >= deadline {
+                return Err(error);
+            }
+            warn!("Failed to connect to Redis, retrying: {}", error);
+        }
+    }
+
+    /// Drops the connection of `generation`, unless it was already replaced.
+    fn reset(&self, generation: u64) {
+        let mut current = self.shared.current.lock().unwrap();
+        if current
+            .as_ref()
+            .is_some_and(|(current, _)| *current == generation)
+        {
+            *current = None;
+        }
+    }
+
+    async fn execute<'a, T, F>(&self, blocking: bool, request: F) -> Result<T, RedisError>
+    where
+        F: Fn(Node) -> RedisFuture<'a, T>,
+    {
+        let mut retried = false;
+        loop {
+            let (generation, node) = self.node().await?;
+            let result = if blocking {
+                request(node).await
+            } else {
+                tokio::time::timeout(self.shared.settings.command_timeout(), request(node))
+                    .await
+                    .unwrap_or_else(|_| Err(timed_out("Redis command timed out")))
+            };
+            match result {
+                Err(error) if needs_reconnect(&error) => {
+                    warn!("Redis connection failed, reconnecting: {}", error);
+                    self.reset(generation);
+                    if retried || !is_rejected(&error) {
+                        return Err(error);
+                    }
+                    retried = true;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Round trip time of a `PING`.
+    pub async fn ping(&self) -> Result<Duration, RedisError> {
+        let started = Instant::now();
+        let _: String = redis::cmd("PING").query_async(&mut self.clone()).await?;
+        Ok(started.elapsed())
+    }
+
+    /// Pings the server, with Sentinel also checks that it is still the master.
+    pub async fn check(&self) -> Result<Duration, RedisError> {
+        let latency = self.ping().await?;
+        if self.shared.settings.topology == Topology::Sentinel {
+            let role: Vec<Value> = redis::cmd("ROLE").query_async(&mut self.clone()).await?;
+            let role: String = match role.first() {
+                Some(role) => redis::from_redis_value(role)?,
+                None => String::new(),
+            };
+            if role != "master" {
+                return Err(RedisError::from((
+                    ErrorKind::ReadOnly,
+                    "connected server is no longer the master",
+                    role,
+                )));
+            }
+        }
+        Ok(latency)
+    }
+
+    /// Checks the connection every `interval`, dropping it when the check
+    /// fails so the next command reconnects. Runs until the application stops.
+    pub async fn health_check(self, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if let Err(error) = self.check().await {
+                warn!("Redis health check failed: {}", error);
+                if let Some((generation, _)) = self.current() {
+                    self.reset(generation);
+                }
+            }
+        }
+    }
+
+    /// New Pub/Sub connection, to the current master with Sentinel and to
+    /// the first reachable node with Cluster.
+    pub async fn pubsub(&self) -> Result<PubSub, RedisError> {
+        let timeout = self.shared.settings.connect_timeout();
+        let connect = async {
+            match &self.shared.connector {
+                Connector::Standalone(client) => client.get_async_pubsub().await,
+                Connector::Sentinel(sentinel) => {
+                    let client = sentinel.lock().await.async_get_client().await?;
+                    client.get_async_pubsub().await
+                }
+                Connector::Cluster(_) => {
+                    // Published messages reach every node of the cluster, any node will do.
+                    let mut last = None;
+                    for url in &self.shared.settings.urls {
+                        match Client::open(url.as_str())?.get_async_pubsub().await {
+                            Ok(pubsub) => return Ok(pubsub),
+                            Err(error) => last = Some(error),
+                        }
+                    }
+                    Err(last.unwrap_or_else(|| {
+                        RedisError::from((
+                            ErrorKind::InvalidClientConfig,
+                            "no Redis url configured",
+                        ))
+                    }))
+                }
+            }
+        };
+        tokio::time::timeout(timeout, connect)
+            .await
+            .unwrap_or_else(|_| Err(timed_out("timed out connecting to Redis")))
+    }
+}
+
+impl ConnectionLike for RedisConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        Box::pin(self.execute(is_blocking(cmd), move |mut node| {
+            Box::pin(async move { node.req_packed_command(cmd).await })
+        }))
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        pipeline: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        Box::pin(
+            self.execute(is_blocking_pipeline(pipeline), move |mut node| {
+                Box::pin(async move { node.req_packed_commands(pipeline, offset, count).await })
+            }),
+        )
+    }
+
+    fn get_db(&self) -> i64 {
+        self.current().map(|(_, node)| node.get_db()).unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use config::{Config, File, FileFormat};
+    use redis::{AsyncCommands, Value};
+
+    use super::{ConnectionSettings, RedisConnection, Topology, is_blocking, is_blocking_pipeline};
+    use crate::testing;
+
+    #[test]
+    fn test_settings() {
+        let settings: ConnectionSettings = Config::builder()
+            .add_source(File::from_str(
+                "topology: sentinel\nurls:\n  - redis://sentinel-1:26379\n  - redis://sentinel-2:26379\nmaster_name: mymaster\ncommand_timeout_ms: 500",
+                FileFormat::Yaml,
+            ))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+        assert_eq!(Topology::Sentinel, settings.topology);
+        assert_eq!(2, settings.urls.len());
+        assert_eq!(Some("mymaster"), settings.master_name.as_deref());
+        assert_eq!(500, settings.command_timeout_ms);
+        assert_eq!(
+            ConnectionSettings::default().connect_timeout_ms,
+            settings.connect_timeout_ms
+        );
+    }
+
+    #[test]
+    fn test_is_blocking() {
+        assert!(is_blocking(redis::cmd("BLPOP").arg("jobs").arg(0)));
+        assert!(is_blocking(
+            redis::cmd("xreadgroup")
+                .arg("GROUP")
+                .arg("members")
+                .arg("worker-1")
+                .arg("BLOCK")
+                .arg(100)
+        ));
+        assert!(!is_blocking(redis::cmd("XREAD").arg("STREAMS")));
+        assert!(!is_blocking(redis::cmd("GET").arg("BLPOP")));
+
+        let mut pipeline = redis::pipe();
+        pipeline.set("key", "value").get("key");
+        assert!(!is_blocking_pipeline(&pipeline));
+        pipeline.blpop("jobs", 1.0);
+        assert!(is_blocking_pipeline(&pipeline));
+        assert!(is_blocking_pipeline(
+            redis::pipe()
+                .atomic()
+                .cmd("XREADGROUP")
+                .arg("BLOCK")
+                .arg(100)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_connect_rejects_invalid_settings() {
+        let sentinel = ConnectionSettings {
+            topology: Topology::Sentinel,
+            ..ConnectionSettings::default()
+        };
+        assert!(RedisConnection::connect(&sentinel).await.is_err());
+        let empty = ConnectionSettings {
+            urls: Vec::new(),
+            ..ConnectionSettings::default()
+        };
+        assert!(RedisConnection::connect(&empty).await.is_err());
+    }
+
+    #[tokio::test]
+    #[ignore = "requires Redis, set TEST_REDIS_URL"]
+    async fn test_reconnects_after_connection_killed() -> Result<(), Box<dyn std::error::Error>> {
+        let mut connection = testing::connection().await?;
+        let key = format!("test:{}:connection", std::process::id());
+        let _: () = connection.set(&key, "Zhafir").await?;
+
+        // Killing every other connection, including the one behind `connection`.
+        let mut admin = redis::Client::open(testing::url())?
+            .get_multiplexed_async_connection()
+            .await?;
+        let _: Value = redis::cmd("CLIENT")
+            .arg("KILL")
+            .arg("TYPE")
+            .arg("normal")
+            .arg("SKIPME")
+            .arg("yes")
+            .query_async(&mut admin)
+            .await?;
+
+        // The command in flight may fail, the next one runs on a new connection.
+        if connection.ping().await.is_err() {
+            connection.ping().await?;
+        }
+        let value: String = connection.get(&key).await?;
+        assert_eq!("Zhafir", value);
+        let _: () = connection.del(&key).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,371 @@
+use std::{collections::HashMap, fmt};
+
+use belajar_rust_database::model::{Transaction, TransactionId, TransactionItem};
+use chrono::{DateTime, Utc};
+use redis::{AsyncCommands, RedisError, Value, streams::StreamMaxlen};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::{
+    connection::RedisConnection,
+    stream::{Consumer, ConsumerOptions, DecodeError, from_fields},
+};
+
+/*
+EVENT
+- Event adalah kejadian di domain aplikasi, misal "transaction.created", yang dikirim ke Redis Stream
+agar bisa diproses aplikasi lain
+- Setiap entry berisi field `type` (tipe event), `version` (versi schema) dan `payload` (isi event dalam bentuk JSON)
+- Jika isi event berubah dengan cara yang tidak kompatibel, `VERSION` dinaikkan, dan consumer lama akan menolak
+versi yang tidak dikenal dengan `DecodeError::UnknownVersion`, bukan salah membaca datanya
+- Consumer yang sudah diperbarui bisa tetap membaca versi lama dengan meng-override `Event::decode`
+- Field `type` dibaca terlebih dahulu, sehingga event dengan tipe lain dilewati walaupun field lainnya berbeda,
+dan tidak dianggap entry yang rusak
+
+MAXLEN
+- Stream tidak pernah mengecil dengan sendirinya, sehingga producer memotong stream menggunakan `XADD key MAXLEN ~ n`
+- Tanda `~` membuat Redis memotong secara approximate (per node internal), jauh lebih cepat daripada memotong tepat n entry,
+sehingga panjang stream bisa sedikit lebih dari n
+*/
+
+pub const TYPE_FIELD: &str = "type";
+pub const VERSION_FIELD: &str = "version";
+pub const PAYLOAD_FIELD: &str = "payload";
+
+pub trait Event: Serialize + DeserializeOwned {
+    /// Name of the event, for example `transaction.created`.
+    const TYPE: &'static str;
+    /// Schema version written by [`Producer::publish`].
+    const VERSION: u32;
+
+    /// Reads a payload written with schema `version`. Only [`Event::VERSION`]
+    /// is accepted by default.
+    fn decode(version: u32, payload: &str) -> Result<Self, DecodeError> {
+        if version != Self::VERSION {
+            return Err(DecodeError::UnknownVersion {
+                event_type: Self::TYPE.to_string(),
+                version,
+            });
+        }
+        serde_json::from_str(payload).map_err(|error| DecodeError::Invalid(error.to_string()))
+    }
+}
+
+#[derive(Deserialize)]
+struct EventType {
+    #[serde(rename = "type")]
+    event_type: String,
+}
+
+#[derive(Deserialize)]
+struct Envelope {
+    version: u32,
+    payload: String,
+}
+
+/// Reads an entry written by [`Producer::publish`]. The type is checked
+/// first, so events of another type are never read as this one, whatever
+/// their other fields are.
+pub fn decode_event<E: Event>(fields: &HashMap<String, Value>) -> Result<E, DecodeError> {
+    let EventType { event_type } = from_fields(fields)?;
+    if event_type != E::TYPE {
+        return Err(DecodeError::UnexpectedType {
+            expected: E::TYPE.to_string(),
+            found: event_type,
+        });
+    }
+    let envelope: Envelope = from_fields(fields)?;
+    E::decode(envelope.version, &envelope.payload)
+}
+
+impl<E: Event> Consumer<E> {
+    /// Consumer that reads entries with [`decode_event`].
+    pub fn for_event(connection: RedisConnection, stream: &str, options: ConsumerOptions) -> Self {
+        Consumer::with_decoder(connection, stream, options, decode_event::<E>)
+    }
+}
+
+#[derive(Debug)]
+pub enum PublishError {
+    Redis(RedisError),
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for PublishError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PublishError::Redis(error) => write!(f, "{}", error),
+            PublishError::Json(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for PublishError {}
+
+impl From<RedisError> for PublishError {
+    fn from(error: RedisError) -> Self {
+        PublishError::Redis(error)
+    }
+}
+
+impl From<serde_json::Error> for PublishError {
+    fn from(error: serde_json::Error) -> Self {
+        PublishError::Json(error)
+    }
+}
+
+#[derive(Clone)]
+pub struct Producer {
+    connection: RedisConnection,
+    stream: String,
+    maxlen: usize,
+}
+
+impl Producer {
+    /// Producer that keeps roughly the last `maxlen` entries of `stream`.
+    pub fn new(connection: RedisConnection, stream: &str, maxlen: usize) -> Self {
+        Producer {
+            connection,
+            stream: stream.to_string(),
+            maxlen,
+        }
+    }
+
+    /// Returns the id of the new entry.
+    pub async fn publish<E: Event>(&self, event: &E) -> Result<String, PublishError> {
+        let fields = [
+            (TYPE_FIELD, E::TYPE.to_string()),
+            (VERSION_FIELD, E::VERSION.to_string()),
+            (PAYLOAD_FIELD, serde_json::to_string(event)?),
+        ];
+        let id: String = self
+            .connection
+            .clone()
+            .xadd_maxlen(
+                &self.stream,
+                StreamMaxlen::Approx(self.maxlen),
+                "*",
+                &fields,
+            )
+            .await?;
+        Ok(id)
+    }
+}
+
+/// Published by the POS after a checkout is committed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionCreated {
+    pub transaction_id: TransactionId,
+    pub actor: String,
+    pub total: u64,
+    pub items: Vec<TransactionItem>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl TransactionCreated {
+    pub fn new(actor: &str, transaction: &Transaction) -> Self {
+        TransactionCreated {
+            transaction_id: transaction.id,
+            actor: actor.to_string(),
+            total: transaction.total,
+            items: transaction.items.clone(),
+            created_at: transaction.created_at,
+        }
+    }
+}
+
+impl Event for TransactionCreated {
+    const TYPE: &'static str = "transaction.created";
+    const VERSION: u32 = 1;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, time::Duration};
+
+    use redis::{AsyncCommands, Value};
+    use serde::{Deserialize, Serialize};
+
+    use super::{Event, Producer, TransactionCreated, decode_event};
+    use crate::{
+        stream::{Consumer, ConsumerOptions, DecodeError},
+        testing,
+    };
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct MemberJoined {
+        name: String,
+        city: String,
+    }
+
+    impl Event for MemberJoined {
+        const TYPE: &'static str = "member.joined";
+        const VERSION: u32 = 2;
+
+        /// Version 1 had a single `address` field instead of `city`.
+        fn decode(version: u32, payload: &str) -> Result<Self, DecodeError> {
+            #[derive(Deserialize)]
+            struct V1 {
+                name: String,
+                address: String,
+            }
+
+            match version {
+                1 => {
+                    let v1: V1 = serde_json::from_str(payload)
+                        .map_err(|error| DecodeError::Invalid(error.to_string()))?;
+                    Ok(MemberJoined {
+                        name: v1.name,
+                        city: v1.address,
+                    })
+                }
+                2 => serde_json::from_str(payload)
+                    .map_err(|error| DecodeError::Invalid(error.to_string())),
+                version => Err(DecodeError::UnknownVersion {
+                    event_type: Self::TYPE.to_string(),
+                    version,
+                }),
+            }
+        }
+    }
+
+    fn entry(event_type: &str, version: &str, payload: &str) -> HashMap<String, Value> {
+        [
+            ("type", event_type),
+            ("version", version),
+            ("payload", payload),
+        ]
+        .into_iter()
+        .map(|(name, value)| {
+            (
+                name.to_string(),
+                Value::BulkString(value.as_bytes().to_vec()),
+            )
+        })
+        .collect()
+    }
+
+    #[test]
+    fn test_decode_event() {
+        let joined = MemberJoined {
+            name: "Zhafir".to_string(),
+            city: "Jakarta".to_string(),
+        };
+        assert_eq!(
+            Ok(&joined),
+            decode_event::<MemberJoined>(&entry(
+                "member.joined",
+                "2",
+                r#"{"name":"Zhafir","city":"Jakarta"}"#
+            ))
+            .as_ref()
+        );
+        assert_eq!(
+            Ok(&joined),
+            decode_event::<MemberJoined>(&entry(
+                "member.joined",
+                "1",
+                r#"{"name":"Zhafir","address":"Jakarta"}"#
+            ))
+            .as_ref()
+        );
+        assert_eq!(
+            Err(DecodeError::UnknownVersion {
+                event_type: "member.joined".to_string(),
+                version: 3
+            }),
+            decode_event::<MemberJoined>(&entry("member.joined", "3", "{}"))
+        );
+        assert_eq!(
+            Err(DecodeError::UnexpectedType {
+                expected: "member.joined".to_string(),
+                found: "transaction.created".to_string()
+            }),
+            decode_event::<MemberJoined>(&entry("transaction.created", "1", "{}"))
+        );
+        assert!(matches!(
+            decode_event::<TransactionCreated>(&entry("transaction.created", "2", "{}")),
+            Err(DecodeError::UnknownVersion { version: 2, .. })
+        ));
+
+        // Another event type is skipped even when it has a different shape.
+        let mut other = entry("stock.changed", "1", "{}");
+        other.remove("payload");
+        assert!(matches!(
+            decode_event::<MemberJoined>(&other),
+            Err(DecodeError::UnexpectedType { .. })
+        ));
+        let mut broken = entry("member.joined", "2", "{}");
+        broken.remove("payload");
+        assert!(matches!(
+            decode_event::<MemberJoined>(&broken),
+            Err(DecodeError::Invalid(message)) if message.contains("payload")
+        ));
+    }
+
+    #[tokio::test]
+    #[ignore = "requires Redis, set TEST_REDIS_URL"]
+    async fn test_publish_and_consume() -> Result<(), Box<dyn std::error::Error>> {
+        let mut connection = testing::connection().await?;
+        let stream = format!("test:{}:events", std::process::id());
+        let _: () = connection.del(&stream).await?;
+
+        let producer = Producer::new(connection.clone(), &stream, 100);
+        for i in 0..500 {
+            let joined = MemberJoined {
+                name: format!("Zhafir {}", i),
+                city: "Jakarta".to_string(),
+            };
+            producer.publish(&joined).await?;
+        }
+        let length: usize = connection.xlen(&stream).await?;
+        assert!((100..500).contains(&length));
+
+        let _: () = connection.del(&stream).await?;
+        producer
+            .publish(&MemberJoined {
+                name: "Rasyid".to_string(),
+                city: "Bandung".to_string(),
+            })
+            .await?;
+        let _: String = connection
+            .xadd(
+                &stream,
+                "*",
+                &[
+                    ("type", "transaction.created"),
+                    ("version", "1"),
+                    ("payload", "{}"),
+                ],
+            )
+            .await?;
+        // Events of another type may have a different shape, they are skipped
+        // before the envelope is read.
+        let _: String = connection
+            .xadd(&stream, "*", &[("type", "stock.changed"), ("version", "1")])
+            .await?;
+
+        let consumer: Consumer<MemberJoined> = Consumer::for_event(
+            connection.clone(),
+            &stream,
+            ConsumerOptions {
+                block: Duration::from_millis(10),
+                ..ConsumerOptions::new("members", "worker-1")
+            },
+        );
+        consumer.create_group().await?;
+        let report = consumer
+            .poll(&|joined: MemberJoined| async move {
+                assert_eq!("Bandung", joined.city);
+                Ok::<_, String>(())
+            })
+            .await?;
+        assert_eq!(
+            (1, 2, 0),
+            (report.handled, report.skipped, report.dead_lettered)
+        );
+        let dead_letters: usize = connection.xlen(consumer.dead_letter_stream()).await?;
+        assert_eq!(0, dead_letters);
+        let _: () = connection.del(&stream).await?;
+        Ok(())
+    }
+}
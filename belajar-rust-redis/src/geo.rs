@@ -0,0 +1,446 @@
+use std::fmt;
+
+use async_trait::async_trait;
+use belajar_rust_database::{
+    model::{Seller, SellerId},
+    repository::{IdRange, NewSeller, Page, SellerRepository},
+};
+use log::warn;
+use redis::{
+    AsyncCommands, RedisError,
+    geo::{RadiusOptions, RadiusOrder, RadiusSearchResult, Unit},
+};
+use serde::{Deserialize, Serialize};
+use sqlx::Error;
+
+use crate::connection::RedisConnection;
+
+/*
+GEO INDEX
+- Lokasi seller disimpan di Redis Geo dengan member berupa id seller, sehingga seller terdekat bisa dicari
+menggunakan `GEORADIUS` tanpa menghitung jarak semua seller di database
+- `GeoIndexed` membungkus SellerRepository, setiap insert, update, dan delete yang berhasil di database
+juga mengubah index, seller tanpa lokasi (atau lokasinya dihapus) dikeluarkan dari index
+- Database tetap menjadi sumber data utama, jika Redis gagal, perubahan di database tetap berhasil dan error nya hanya dicatat,
+index yang tertinggal bisa dibangun ulang menggunakan `SellerGeoIndex::rebuild`
+- Rebuild menulis ke key sementara lalu menggantinya dengan `RENAME`, sehingga pencarian tidak pernah melihat index setengah jadi
+- Redis hanya menerima latitude antara -85.05112878 dan 85.05112878, lokasi di luar batas tersebut tidak dimasukkan ke index
+- `GEORADIUS` tidak memiliki offset, sehingga halaman sebelumnya ikut dibaca, karena itu pencarian dibatasi
+`MAX_NEARBY_RESULTS` hasil pertama, halaman setelahnya selalu kosong
+*/
+
+pub const MAX_LATITUDE: f64 = 85.05112878;
+
+/// Deepest result a nearby search reads, offset included.
+pub const MAX_NEARBY_RESULTS: usize = 1000;
+
+pub fn is_valid_location(latitude: f64, longitude: f64) -> bool {
+    (-MAX_LATITUDE..=MAX_LATITUDE).contains(&latitude) && (-180.0..=180.0).contains(&longitude)
+}
+
+fn location(seller: &Seller) -> Option<(f64, f64)> {
+    let (latitude, longitude) = seller.latitude.zip(seller.longitude)?;
+    is_valid_location(latitude, longitude).then_some((latitude, longitude))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DistanceUnit {
+    M,
+    #[default]
+    Km,
+    Mi,
+    Ft,
+}
+
+impl DistanceUnit {
+    fn unit(self) -> Unit {
+        match self {
+            DistanceUnit::M => Unit::Meters,
+            DistanceUnit::Km => Unit::Kilometers,
+            DistanceUnit::Mi => Unit::Miles,
+            DistanceUnit::Ft => Unit::Feet,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GeoQuery {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub radius: f64,
+    pub unit: DistanceUnit,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NearbySeller {
+    pub id: SellerId,
+    /// Distance from the query point, in the unit of the query.
+    pub distance: f64,
+}
+
+#[derive(Debug)]
+pub enum RebuildError {
+    Database(Error),
+    Redis(RedisError),
+}
+
+impl fmt::Display for RebuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RebuildError::Database(error) => write!(f, "{}", error),
+            RebuildError::Redis(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for RebuildError {}
+
+impl From<Error> for RebuildError {
+    fn from(error: Error) -> Self {
+        RebuildError::Database(error)
+    }
+}
+
+impl From<RedisError> for RebuildError {
+    fn from(error: RedisError) -> Self {
+        RebuildError::Redis(error)
+    }
+}
+
+#[derive(Clone)]
+pub struct SellerGeoIndex {
+    connection: RedisConnection,
+    key: String,
+}
+
+impl SellerGeoIndex {
+    pub fn new(connection: RedisConnection, key: &str) -> Self {
+        SellerGeoIndex {
+            connection,
+            key: key.to_string(),
+        }
+    }
+
+    /// Adds or moves the sellers, sellers without a valid location are removed.
+    pub async fn add(&self, sellers: &[Seller]) -> Result<(), RedisError> {
+        write(&mut self.connection.clone(), &self.key, sellers).await
+    }
+
+    pub async fn remove(&self, ids: &[SellerId]) -> Result<(), RedisError> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        let members: Vec<String> = ids.iter().map(SellerId::to_string).collect();
+        self.connection.clone().zrem(&self.key, members).await
+    }
+
+    /// Sellers within the radius, nearest first.
+    pub async fn nearby(&self, query: &GeoQuery) -> Result<Vec<NearbySeller>, RedisError> {
+        let count = query
+            .offset
+            .saturating_add(query.limit)
+            .min(MAX_NEARBY_RESULTS);
+        if count <= query.offset {
+            return Ok(Vec::new());
+        }
+        // GEORADIUS has no offset, so the previous pages are read and skipped.
+        let options = RadiusOptions::default()
+            .with_dist()
+            .order(RadiusOrder::Asc)
+            .limit(count);
+        let found: Vec<RadiusSearchResult> = self
+            .connection
+            .clone()
+            .geo_radius(
+                &self.key,
+                query.longitude,
+                query.latitude,
+                query.radius,
+                query.unit.unit(),
+                options,
+            )
+            .await?;
+        Ok(found
+            .into_iter()
+            .skip(query.offset)
+            .filter_map(|result| {
+                Some(NearbySeller {
+                    id: SellerId(result.name.parse().ok()?),
+                    distance: result.dist.unwrap_or_default(),
+                })
+            })
+            .collect())
+    }
+
+    /// Replaces the index with the sellers in `repository`, reading `batch`
+    /// sellers at a time. Returns the number of sellers indexed.
+    pub async fn rebuild(
+        &self,
+        repository: &dyn SellerRepository,
+        batch: u32,
+    ) -> Result<usize, RebuildError> {
+        let mut connection = self.connection.clone();
+        let temporary = format!("{}:rebuild", self.key);
+        let _: () = connection.del(&temporary).await?;
+
+        let mut indexed = 0;
+        let mut after = None;
+        loop {
+            let sellers = repository
+                .list(&Page::After {
+                    limit: batch,
+                    after,
+                })
+                .await?;
+            let Some(last) = sellers.last() else {
+                break;
+            };
+            after = Some(last.id);
+            indexed += sellers.iter().filter_map(location).count();
+            write(&mut connection, &temporary, &sellers).await?;
+        }
+
+        if indexed == 0 {
+            let _: () = connection.del(&[&temporary, &self.key]).await?;
+        } else {
+            let _: () = connection.rename(&temporary, &self.key).await?;
+        }
+        Ok(indexed)
+    }
+}
+
+async fn write(
+    connection: &mut RedisConnection,
+    key: &str,
+    sellers: &[Seller],
+) -> Result<(), RedisError> {
+    let (located, unlocated): (Vec<&Seller>, Vec<&Seller>) = sellers
+        .iter()
+        .partition(|seller| location(seller).is_some());
+
+    let mut pipe = redis::pipe();
+    if !located.is_empty() {
+        let points: Vec<(f64, f64, String)> = located
+            .iter()
+            .filter_map(|seller| {
+                let (latitude, longitude) = location(seller)?;
+                Some((longitude, latitude, seller.id.to_string()))
+            })
+            .collect();
+        pipe.geo_add(key, points);
+    }
+    if !unlocated.is_empty() {
+        let members: Vec<String> = unlocated
+            .iter()
+            .map(|seller| seller.id.to_string())
+            .collect();
+        pipe.zrem(key, members);
+    }
+    pipe.exec_async(connection).await
+}
+
+/// Repository that keeps a [`SellerGeoIndex`] in sync with the database.
+pub struct GeoIndexed<R> {
+    inner: R,
+    index: SellerGeoIndex,
+}
+
+impl<R> GeoIndexed<R> {
+    pub fn new(inner: R, index: SellerGeoIndex) -> Self {
+        GeoIndexed { inner, index }
+    }
+
+    pub fn index(&self) -> &SellerGeoIndex {
+        &self.index
+    }
+}
+
+fn log_sync_error(result: Result<(), RedisError>) {
+    if let Err(error) = result {
+        warn!("Failed to update seller geo index: {}", error);
+    }
+}
+
+#[async_trait]
+impl<R: SellerRepository> SellerRepository for GeoIndexed<R> {
+    async fn find_by_id(&self, id: SellerId) -> Result<Option<Seller>, Error> {
+        self.inner.find_by_id(id).await
+    }
+
+    async fn find_by_ids(&self, ids: &[SellerId]) -> Result<Vec<Seller>, Error> {
+        self.inner.find_by_ids(ids).await
+    }
+
+    async fn list(&self, page: &Page<SellerId>) -> Result<Vec<Seller>, Error> {
+        self.inner.list(page).await
+    }
+
+    async fn search_by_name(
+        &self,
+        name: &str,
+        page: &Page<SellerId>,
+    ) -> Result<Vec<Seller>, Error> {
+        self.inner.search_by_name(name, page).await
+    }
+
+    async fn insert(&self, actor: &str, seller: &NewSeller) -> Result<SellerId, Error> {
+        let id = self.inner.insert(actor, seller).await?;
+        let seller = Seller {
+            id,
+            name: seller.name.clone(),
+            latitude: seller.latitude,
+            longitude: seller.longitude,
+        };
+        log_sync_error(self.index.add(&[seller]).await);
+        Ok(id)
+    }
+
+    async fn insert_many(
+        &self,
+        actor: &str,
+        sellers: &[NewSeller],
+    ) -> Result<IdRange<SellerId>, Error> {
+        let ids = self.inner.insert_many(actor, sellers).await?;
+        let sellers: Vec<Seller> = ids
+            .iter()
+            .zip(sellers)
+            .map(|(id, seller)| Seller {
+                id,
+                name: seller.name.clone(),
+                latitude: seller.latitude,
+                longitude: seller.longitude,
+            })
+            .collect();
+        log_sync_error(self.index.add(&sellers).await);
+        Ok(ids)
+    }
+
+    async fn update(&self, actor: &str, seller: &Seller) -> Result<bool, Error> {
+        let updated = self.inner.update(actor, seller).await?;
+        if updated {
+            log_sync_error(self.index.add(std::slice::from_ref(seller)).await);
+        }
+        Ok(updated)
+    }
+
+    async fn delete(&self, actor: &str, id: SellerId) -> Result<bool, Error> {
+        let deleted = self.inner.delete(actor, id).await?;
+        if deleted {
+            log_sync_error(self.index.remove(&[id]).await);
+        }
+        Ok(deleted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use belajar_rust_database::{
+        model::{Seller, SellerId},
+        repository::{NewSeller, SellerRepository, SqliteSellerRepository},
+        testing::TestDatabase,
+    };
+    use redis::AsyncCommands;
+
+    use super::{DistanceUnit, GeoIndexed, GeoQuery, SellerGeoIndex, is_valid_location};
+    use crate::testing;
+
+    const ACTOR: &str = "test";
+
+    #[test]
+    fn test_is_valid_location() {
+        assert!(is_valid_location(-6.17759, 106.822702));
+        assert!(!is_valid_location(-89.0, 106.822702));
+        assert!(!is_valid_location(-6.17759, 181.0));
+        assert_eq!(
+            DistanceUnit::Mi,
+            serde_json::from_str::<DistanceUnit>("\"mi\"").unwrap()
+        );
+    }
+
+    fn new_seller(name: &str, location: Option<(f64, f64)>) -> NewSeller {
+        NewSeller {
+            name: name.to_string(),
+            latitude: location.map(|(latitude, _)| latitude),
+            longitude: location.map(|(_, longitude)| longitude),
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "requires Redis, set TEST_REDIS_URL"]
+    async fn test_geo_indexed_repository() -> Result<(), Box<dyn std::error::Error>> {
+        let database = TestDatabase::sqlite().await?;
+        let mut connection = testing::connection().await?;
+        let key = format!("test:{}:sellers:geo", std::process::id());
+        let _: () = connection.del(&key).await?;
+        let index = SellerGeoIndex::new(connection.clone(), &key);
+        let repository = GeoIndexed::new(
+            SqliteSellerRepository::new(database.pool().clone()),
+            index.clone(),
+        );
+
+        let toko_a = repository
+            .insert(ACTOR, &new_seller("Toko A", Some((-6.17759, 106.822702))))
+            .await?;
+        let ids = repository
+            .insert_many(
+                ACTOR,
+                &[
+                    new_seller("Toko B", Some((-6.174964, 106.820889))),
+                    new_seller("Toko C", None),
+                ],
+            )
+            .await?;
+        let toko_b = ids.start;
+
+        let query = GeoQuery {
+            latitude: -6.175105,
+            longitude: 106.821825,
+            radius: 5.0,
+            unit: DistanceUnit::Km,
+            offset: 0,
+            limit: 10,
+        };
+        let nearby: Vec<SellerId> = index
+            .nearby(&query)
+            .await?
+            .into_iter()
+            .map(|seller| seller.id)
+            .collect();
+        assert_eq!(vec![toko_b, toko_a], nearby);
+        let second_page = index
+            .nearby(&GeoQuery {
+                offset: 1,
+                limit: 1,
+                ..query
+            })
+            .await?;
+        assert_eq!(toko_a, second_page[0].id);
+
+        // Moving Toko B far away takes it out of the radius.
+        repository
+            .update(
+                ACTOR,
+                &Seller {
+                    id: toko_b,
+                    name: "Toko B".to_string(),
+                    latitude: Some(-7.797068),
+                    longitude: Some(110.370529),
+                },
+            )
+            .await?;
+        assert_eq!(1, index.nearby(&query).await?.len());
+        repository.delete(ACTOR, toko_a).await?;
+        assert!(index.nearby(&query).await?.is_empty());
+
+        // The seeded seller has no location, only Toko B is indexed.
+        let _: () = connection.del(&key).await?;
+        assert_eq!(1, index.rebuild(&repository, 1).await?);
+        let count: usize = connection.zcard(&key).await?;
+        assert_eq!(1, count);
+        let _: () = connection.del(&key).await?;
+        Ok(())
+    }
+}
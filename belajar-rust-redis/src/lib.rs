@@ -0,0 +1,42 @@
+/*
+LIBRARY
+- Selain contoh perintah Redis di `main.rs`, project ini juga berisi module yang bisa digunakan project lain,
+misal cache untuk repository dari belajar-rust-database
+- Test yang membutuhkan server Redis di-ignore secara default, jalankan dengan
+`TEST_REDIS_URL=redis://localhost:6379 cargo test -- --include-ignored`
+*/
+
+pub mod backoff;
+pub mod cache;
+pub mod connection;
+pub mod event;
+pub mod geo;
+pub mod lock;
+pub mod pubsub;
+pub mod ranking;
+pub mod rate_limit;
+pub mod session;
+pub mod stream;
+pub mod visitors;
+
+#[cfg(test)]
+pub(crate) mod testing {
+    use redis::RedisError;
+
+    use crate::connection::{ConnectionSettings, RedisConnection};
+
+    const REDIS_URL_VAR: &str = "TEST_REDIS_URL";
+
+    /// The server in `TEST_REDIS_URL`, or localhost.
+    pub fn url() -> String {
+        std::env::var(REDIS_URL_VAR).unwrap_or_else(|_| "redis://localhost:6379".to_string())
+    }
+
+    pub async fn connection() -> Result<RedisConnection, RedisError> {
+        RedisConnection::connect(&ConnectionSettings {
+            urls: vec![url()],
+            ..ConnectionSettings::default()
+        })
+        .await
+    }
+}
@@ -0,0 +1,245 @@
+use std::{
+    sync::{
+        Arc, LazyLock,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use log::warn;
+use redis::{RedisError, Script};
+use tokio::{task::JoinHandle, time::Instant};
+
+use crate::connection::RedisConnection;
+
+/*
+DISTRIBUTED LOCK
+- Lock dibuat menggunakan `SET key token NX PX lease`, hanya satu instance yang berhasil membuat key tersebut
+- Token adalah UUID acak milik pemegang lock, lock hanya dilepas atau diperpanjang jika isi key masih token tersebut,
+sehingga instance yang lease nya sudah habis tidak menghapus lock milik instance lain
+- Pemeriksaan token dan `DEL`/`PEXPIRE` dijalankan di Lua script, karena script dijalankan Redis secara atomic
+- Lease membuat lock terlepas sendiri jika pemegangnya mati, selama lock dipegang,
+background task memperpanjang lease setiap sepertiga lease
+- Jika perpanjangan menemukan token lain, lock dianggap hilang (`LockGuard::is_lost`) dan pekerjaan sebaiknya dihentikan
+*/
+
+static RELEASE: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r"
+        if redis.call('GET', KEYS[1]) == ARGV[1] then
+            return redis.call('DEL', KEYS[1])
+        end
+        return 0
+        ",
+    )
+});
+
+static RENEW: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r"
+        if redis.call('GET', KEYS[1]) == ARGV[1] then
+            return redis.call('PEXPIRE', KEYS[1], ARGV[2])
+        end
+        return 0
+        ",
+    )
+});
+
+const RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Clone)]
+pub struct RedisLock {
+    connection: RedisConnection,
+    key: String,
+    lease: Duration,
+}
+
+impl RedisLock {
+    pub fn new(connection: RedisConnection, key: &str, lease: Duration) -> Self {
+        RedisLock {
+            connection,
+            key: key.to_string(),
+            lease,
+        }
+    }
+
+    /// Returns `None` if another owner holds the lock.
+    pub async fn try_acquire(&self) -> Result<Option<LockGuard>, RedisError> {
+        let token = uuid::Uuid::new_v4().to_string();
+        let acquired: Option<String> = redis::cmd("SET")
+            .arg(&self.key)
+            .arg(&token)
+            .arg("NX")
+            .arg("PX")
+            .arg(self.lease.as_millis() as u64)
+            .query_async(&mut self.connection.clone())
+            .await?;
+        if acquired.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(LockGuard::new(
+            self.connection.clone(),
+            &self.key,
+            token,
+            self.lease,
+        )))
+    }
+
+    /// Retries until the lock is acquired or `wait` has passed.
+    pub async fn acquire(&self, wait: Duration) -> Result<Option<LockGuard>, RedisError> {
+        let deadline = Instant::now() + wait;
+        loop {
+            if let Some(guard) = self.try_acquire().await? {
+                return Ok(Some(guard));
+            }
+            if Instant::now() + RETRY_INTERVAL > deadline {
+                return Ok(None);
+            }
+            tokio::time::sleep(RETRY_INTERVAL).await;
+        }
+    }
+}
+
+/// A held lock. Dropping it stops the renewal, the key then expires after the lease.
+pub struct LockGuard {
+    connection: RedisConnection,
+    key: String,
+    token: String,
+    lease: Duration,
+    lost: Arc<AtomicBool>,
+    renewal: JoinHandle<()>,
+}
+
+impl LockGuard {
+    fn new(connection: RedisConnection, key: &str, token: String, lease: Duration) -> Self {
+        let lost = Arc::new(AtomicBool::new(false));
+        let renewal = tokio::spawn(keep_alive(
+            connection.clone(),
+            key.to_string(),
+            token.clone(),
+            lease,
+            lost.clone(),
+        ));
+        LockGuard {
+            connection,
+            key: key.to_string(),
+            token,
+            lease,
+            lost,
+            renewal,
+        }
+    }
+
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    /// True once a renewal found the key gone or owned by someone else.
+    pub fn is_lost(&self) -> bool {
+        self.lost.load(Ordering::Relaxed)
+    }
+
+    /// Extends the lease, returns false if the lock is no longer ours.
+    pub async fn renew(&self) -> Result<bool, RedisError> {
+        let renewed = renew(&self.connection, &self.key, &self.token, self.lease).await?;
+        if !renewed {
+            self.lost.store(true, Ordering::Relaxed);
+        }
+        Ok(renewed)
+    }
+
+    /// Deletes the key, returns false if the lock was already lost.
+    pub async fn release(self) -> Result<bool, RedisError> {
+        self.renewal.abort();
+        let deleted: i64 = RELEASE
+            .key(&self.key)
+            .arg(&self.token)
+            .invoke_async(&mut self.connection.clone())
+            .await?;
+        Ok(deleted == 1)
+    }
+}
+
+impl Drop for LockGuard {
+    fn drop(&mut self) {
+        self.renewal.abort();
+    }
+}
+
+async fn renew(
+    connection: &RedisConnection,
+    key: &str,
+    token: &str,
+    lease: Duration,
+) -> Result<bool, RedisError> {
+    let renewed: i64 = RENEW
+        .key(key)
+        .arg(token)
+        .arg(lease.as_millis() as u64)
+        .invoke_async(&mut connection.clone())
+        .await?;
+    Ok(renewed == 1)
+}
+
+async fn keep_alive(
+    connection: RedisConnection,
+    key: String,
+    token: String,
+    lease: Duration,
+    lost: Arc<AtomicBool>,
+) {
+    loop {
+        tokio::time::sleep(lease / 3).await;
+        match renew(&connection, &key, &token, lease).await {
+            Ok(true) => {}
+            Ok(false) => {
+                warn!("Lock {} was lost", key);
+                lost.store(true, Ordering::Relaxed);
+                return;
+            }
+            Err(error) => warn!("Failed to renew lock {}: {}", key, error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use redis::AsyncCommands;
+
+    use super::RedisLock;
+    use crate::testing;
+
+    #[tokio::test]
+    #[ignore = "requires Redis, set TEST_REDIS_URL"]
+    async fn test_lock() -> Result<(), Box<dyn std::error::Error>> {
+        let mut connection = testing::connection().await?;
+        let key = format!("test:{}:lock", std::process::id());
+        let _: () = connection.del(&key).await?;
+
+        let lock = RedisLock::new(connection.clone(), &key, Duration::from_millis(300));
+        let guard = lock.try_acquire().await?.unwrap();
+        assert!(lock.try_acquire().await?.is_none());
+
+        // Renewal keeps the lock well past the lease.
+        tokio::time::sleep(Duration::from_millis(900)).await;
+        assert!(!guard.is_lost());
+        assert!(lock.acquire(Duration::from_millis(200)).await?.is_none());
+
+        // Another owner cannot be released by a stale guard.
+        let _: () = connection.set(&key, "someone-else").await?;
+        assert!(!guard.renew().await?);
+        assert!(guard.is_lost());
+        assert!(!guard.release().await?);
+        let owner: String = connection.get(&key).await?;
+        assert_eq!("someone-else", owner);
+
+        let _: () = connection.del(&key).await?;
+        let guard = lock.acquire(Duration::from_millis(200)).await?.unwrap();
+        assert!(guard.release().await?);
+        assert!(lock.try_acquire().await?.is_some());
+        let _: () = connection.del(&key).await?;
+        Ok(())
+    }
+}
@@ -4,10 +4,11 @@ fn main() {
 
 #[cfg(test)]
 mod tests {
+    use belajar_rust_redis::connection::{ConnectionSettings, RedisConnection};
     use futures::StreamExt;
     use redis::{
-        AsyncCommands, Client, Commands, RedisError, Value,
-        aio::{MultiplexedConnection, PubSub},
+        AsyncCommands, Client, Commands, ErrorKind, RedisError, Value,
+        aio::PubSub,
         geo::{RadiusOptions, Unit},
         streams::{StreamReadOptions, StreamReadReply},
     };
@@ -33,11 +34,19 @@ mod tests {
     ASYNC CLIENT
     - Library Redis juga menyediakan fitur Async jika kita ingin menggunakan Rust Async IO
     - Kita bisa pilih library Async yang akan kita gunakan, contohnya disini kita akan menggunakan Tokio
+    - Di sini koneksi dibuat menggunakan `RedisConnection` dari module `connection`, alamat server nya dibaca
+    dari file `redis.yaml` atau environment variable `REDIS_*`, dan koneksi dibuat ulang sendiri jika terputus
      */
 
-    async fn get_client() -> Result<MultiplexedConnection, RedisError> {
-        let client = Client::open("redis://localhost:6379")?;
-        client.get_multiplexed_async_connection().await
+    async fn get_client() -> Result<RedisConnection, RedisError> {
+        let settings = ConnectionSettings::load().map_err(|error| {
+            RedisError::from((
+                ErrorKind::InvalidClientConfig,
+                "invalid Redis settings",
+                error.to_string(),
+            ))
+        })?;
+        RedisConnection::connect(&settings).await
     }
 
     #[tokio::test]
@@ -368,8 +377,7 @@ mod tests {
      */
 
     async fn get_pubsub() -> Result<PubSub, RedisError> {
-        let client = Client::open("redis://localhost:6379")?;
-        client.get_async_pubsub().await
+        get_client().await?.pubsub().await
     }
 
     #[tokio::test]
@@ -0,0 +1,189 @@
+use std::time::Duration;
+
+use futures::StreamExt;
+use log::{info, warn};
+use redis::{Msg, RedisError};
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::{backoff::Backoff, connection::RedisConnection};
+
+/*
+PUBSUB RELAY
+- Satu koneksi Pub/Sub ke Redis bisa subscribe ke banyak channel (`SUBSCRIBE`) dan pattern (`PSUBSCRIBE`, misal `minipos:*`)
+- Setiap pesan yang diterima dikirim ke `tokio::sync::broadcast`, sehingga banyak receiver (misal koneksi WebSocket
+ke dashboard) bisa membaca pesan yang sama tanpa membuat koneksi Redis sendiri - sendiri
+- Receiver yang terlalu lambat akan kehilangan pesan lama (`RecvError::Lagged`), bukan menahan receiver lain
+- Redis Pub/Sub tidak menyimpan pesan, sehingga pesan yang dikirim saat koneksi terputus akan hilang
+- Jika koneksi terputus, `Subscriber::run` membuat koneksi baru dengan backoff lalu subscribe ulang ke semua channel dan pattern,
+koneksi baru dibuat dari `RedisConnection`, sehingga dengan Sentinel subscriber ikut pindah ke master yang baru setelah failover
+*/
+
+/// A message received from a channel or pattern subscription.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Message {
+    pub channel: String,
+    /// The pattern that matched, `None` for channel subscriptions.
+    pub pattern: Option<String>,
+    pub payload: String,
+}
+
+impl Message {
+    fn from_msg(msg: &Msg) -> Self {
+        Message {
+            channel: msg.get_channel_name().to_string(),
+            pattern: msg.get_pattern().ok(),
+            payload: String::from_utf8_lossy(msg.get_payload_bytes()).into_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SubscriberOptions {
+    pub channels: Vec<String>,
+    pub patterns: Vec<String>,
+    /// Number of messages kept for slow receivers.
+    pub capacity: usize,
+    pub reconnect_min: Duration,
+    pub reconnect_max: Duration,
+}
+
+impl SubscriberOptions {
+    pub fn new(channels: &[&str], patterns: &[&str]) -> Self {
+        SubscriberOptions {
+            channels: channels.iter().map(|channel| channel.to_string()).collect(),
+            patterns: patterns.iter().map(|pattern| pattern.to_string()).collect(),
+            capacity: 256,
+            reconnect_min: Duration::from_millis(100),
+            reconnect_max: Duration::from_secs(30),
+        }
+    }
+}
+
+pub struct Subscriber {
+    connection: RedisConnection,
+    options: SubscriberOptions,
+    sender: broadcast::Sender<Message>,
+}
+
+impl Subscriber {
+    pub fn new(connection: RedisConnection, options: SubscriberOptions) -> Self {
+        let (sender, _) = broadcast::channel(options.capacity.max(1));
+        Subscriber {
+            connection,
+            options,
+            sender,
+        }
+    }
+
+    /// Sender whose `subscribe` creates new receivers, it can be kept after [`Subscriber::run`] takes `self`.
+    pub fn sender(&self) -> broadcast::Sender<Message> {
+        self.sender.clone()
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Message> {
+        self.sender.subscribe()
+    }
+
+    /// Relays messages forever, reconnecting whenever the connection drops.
+    pub async fn run(self) {
+        let mut backoff = Backoff::new(self.options.reconnect_min, self.options.reconnect_max);
+        loop {
+            match self.listen(&mut backoff).await {
+                Ok(()) => warn!("Redis pub/sub connection closed"),
+                Err(error) => warn!("Redis pub/sub error: {}", error),
+            }
+            tokio::time::sleep(backoff.next_delay()).await;
+        }
+    }
+
+    async fn listen(&self, backoff: &mut Backoff) -> Result<(), RedisError> {
+        let mut pubsub = self.connection.pubsub().await?;
+        for channel in &self.options.channels {
+            pubsub.subscribe(channel).await?;
+        }
+        for pattern in &self.options.patterns {
+            pubsub.psubscribe(pattern).await?;
+        }
+        backoff.reset();
+        info!(
+            "Subscribed to channels {:?} and patterns {:?}",
+            self.options.channels, self.options.patterns
+        );
+
+        let mut messages = pubsub.into_on_message();
+        while let Some(msg) = messages.next().await {
+            // Sending only fails when no receiver is connected, the message is dropped then.
+            let _ = self.sender.send(Message::from_msg(&msg));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use redis::{AsyncCommands, Value};
+    use tokio::{sync::broadcast::Receiver, time::timeout};
+
+    use super::{Message, Subscriber, SubscriberOptions};
+    use crate::testing;
+
+    async fn publish_until_received(
+        receiver: &mut Receiver<Message>,
+        channel: &str,
+        payload: &str,
+    ) -> Result<Message, Box<dyn std::error::Error>> {
+        let mut connection = testing::connection().await?;
+        // The subscriber may still be (re)connecting, so publish until a receiver gets it.
+        for _ in 0..50 {
+            let _: () = connection.publish(channel, payload).await?;
+            if let Ok(message) = timeout(Duration::from_millis(100), receiver.recv()).await {
+                return Ok(message?);
+            }
+        }
+        Err("message not received".into())
+    }
+
+    #[tokio::test]
+    #[ignore = "requires Redis, set TEST_REDIS_URL"]
+    async fn test_subscriber_relays_and_reconnects() -> Result<(), Box<dyn std::error::Error>> {
+        let channel = format!("test:{}:members", std::process::id());
+        let pattern = format!("test:{}:orders:*", std::process::id());
+        let subscriber = Subscriber::new(
+            testing::connection().await?,
+            SubscriberOptions::new(&[&channel], &[&pattern]),
+        );
+        let mut receiver = subscriber.subscribe();
+        let task = tokio::spawn(subscriber.run());
+
+        let message = publish_until_received(&mut receiver, &channel, "Zhafir").await?;
+        assert_eq!(
+            Message {
+                channel: channel.clone(),
+                pattern: None,
+                payload: "Zhafir".to_string()
+            },
+            message
+        );
+
+        let order = format!("test:{}:orders:1", std::process::id());
+        let message = publish_until_received(&mut receiver, &order, "paid").await?;
+        assert_eq!(Some(pattern), message.pattern);
+
+        let mut connection = testing::connection().await?;
+        let _: Value = redis::cmd("CLIENT")
+            .arg("KILL")
+            .arg("TYPE")
+            .arg("pubsub")
+            .query_async(&mut connection)
+            .await?;
+        while receiver.try_recv().is_ok() {}
+        let message = publish_until_received(&mut receiver, &channel, "Rasyid").await?;
+        assert_eq!("Rasyid", message.payload);
+
+        task.abort();
+        Ok(())
+    }
+}
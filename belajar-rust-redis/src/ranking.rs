@@ -0,0 +1,340 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use belajar_rust_database::{
+    model::{ProductId, Transaction, TransactionId},
+    repository::{CheckoutError, NewTransactionItem, Page, TransactionRepository},
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use log::warn;
+use redis::{AsyncCommands, RedisError};
+use serde::{Deserialize, Serialize};
+use sqlx::Error;
+
+use crate::{connection::RedisConnection, geo::RebuildError, visitors::Period};
+
+/*
+SALES RANKING
+- Jumlah unit terjual per product disimpan di Sorted Set, member nya id product dan score nya jumlah unit terjual,
+sehingga product terlaris cukup dibaca dengan `ZREVRANGE`, dan peringkat satu product dengan `ZREVRANK`
+- Ada tiga papan peringkat: harian `sales:daily:<tanggal>`, mingguan `sales:weekly:<senin>`, dan sepanjang waktu `sales:all_time`
+- Papan harian dan mingguan diberi TTL, sehingga papan yang sudah lewat terhapus sendiri,
+hari dan minggu dihitung dalam UTC, minggu dimulai hari Senin
+- `Ranked` membungkus TransactionRepository, setiap checkout yang berhasil menambah score product nya dengan `ZINCRBY`,
+jika Redis gagal, transaksi tetap berhasil dan error nya hanya dicatat
+- Jika Redis di-flush atau tertinggal, papan hari ini, minggu ini, dan sepanjang waktu bisa dihitung ulang
+dari tabel transaksi menggunakan `SalesRanking::rebuild`, papan hari dan minggu sebelumnya tidak dihitung ulang
+*/
+
+const DAILY_TTL: Duration = Duration::from_secs(8 * 24 * 60 * 60);
+const WEEKLY_TTL: Duration = Duration::from_secs(35 * 24 * 60 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Board {
+    Daily,
+    Weekly,
+    AllTime,
+}
+
+impl Board {
+    const ALL: [Board; 3] = [Board::Daily, Board::Weekly, Board::AllTime];
+
+    /// Start of the board containing `at`, `None` for the all-time board.
+    fn start(self, at: DateTime<Utc>) -> Option<NaiveDate> {
+        let date = at.date_naive();
+        match self {
+            Board::Daily => Some(date),
+            Board::Weekly => Some(Period::Week.range(date).0),
+            Board::AllTime => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Board::Daily => "daily",
+            Board::Weekly => "weekly",
+            Board::AllTime => "all_time",
+        }
+    }
+
+    fn ttl(self) -> Option<Duration> {
+        match self {
+            Board::Daily => Some(DAILY_TTL),
+            Board::Weekly => Some(WEEKLY_TTL),
+            Board::AllTime => None,
+        }
+    }
+}
+
+fn board_key(prefix: &str, board: Board, at: DateTime<Utc>) -> String {
+    match board.start(at) {
+        Some(start) => format!("{}:sales:{}:{}", prefix, board.name(), start),
+        None => format!("{}:sales:{}", prefix, board.name()),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SalesRank {
+    pub product_id: ProductId,
+    /// 1 for the best-selling product.
+    pub rank: u64,
+    /// Units sold.
+    pub score: u64,
+}
+
+#[derive(Clone)]
+pub struct SalesRanking {
+    connection: RedisConnection,
+    prefix: String,
+}
+
+impl SalesRanking {
+    pub fn new(connection: RedisConnection, prefix: &str) -> Self {
+        SalesRanking {
+            connection,
+            prefix: prefix.to_string(),
+        }
+    }
+
+    fn key(&self, board: Board, at: DateTime<Utc>) -> String {
+        board_key(&self.prefix, board, at)
+    }
+
+    /// Adds the items of a committed transaction to every board.
+    pub async fn record(&self, transaction: &Transaction) -> Result<(), RedisError> {
+        let mut pipe = redis::pipe();
+        for board in Board::ALL {
+            let key = self.key(board, transaction.created_at);
+            for item in &transaction.items {
+                pipe.zincr(&key, item.product_id.to_string(), item.quantity);
+            }
+            if let Some(ttl) = board.ttl() {
+                pipe.expire(&key, ttl.as_secs() as i64);
+            }
+        }
+        pipe.exec_async(&mut self.connection.clone()).await
+    }
+
+    /// The `limit` best-selling products of the board containing `at`.
+    pub async fn top(
+        &self,
+        board: Board,
+        at: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<SalesRank>, RedisError> {
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+        let found: Vec<(String, f64)> = self
+            .connection
+            .clone()
+            .zrevrange_withscores(self.key(board, at), 0, limit as isize - 1)
+            .await?;
+        Ok(found
+            .into_iter()
+            .zip(1..)
+            .filter_map(|((member, score), rank)| {
+                Some(SalesRank {
+                    product_id: ProductId(member.parse().ok()?),
+                    rank,
+                    score: score as u64,
+                })
+            })
+            .collect())
+    }
+
+    /// Rank and score of a product, `None` when it sold nothing.
+    pub async fn rank(
+        &self,
+        board: Board,
+        at: DateTime<Utc>,
+        product_id: ProductId,
+    ) -> Result<Option<SalesRank>, RedisError> {
+        let key = self.key(board, at);
+        let member = product_id.to_string();
+        let (rank, score): (Option<u64>, Option<f64>) = redis::pipe()
+            .zrevrank(&key, &member)
+            .zscore(&key, &member)
+            .query_async(&mut self.connection.clone())
+            .await?;
+        Ok(rank.zip(score).map(|(rank, score)| SalesRank {
+            product_id,
+            rank: rank + 1,
+            score: score as u64,
+        }))
+    }
+
+    /// Recomputes the boards containing `now` from the transactions table.
+    pub async fn rebuild(
+        &self,
+        repository: &dyn TransactionRepository,
+        now: DateTime<Utc>,
+    ) -> Result<(), RebuildError> {
+        let mut connection = self.connection.clone();
+        for board in Board::ALL {
+            let since = board
+                .start(now)
+                .and_then(|start| start.and_hms_opt(0, 0, 0))
+                .map(|start| start.and_utc());
+            let sold = repository.units_sold(since).await?;
+
+            let key = self.key(board, now);
+            if sold.is_empty() {
+                let _: () = connection.del(&key).await?;
+                continue;
+            }
+            // The board is written to another key first, so readers never see it half built.
+            let temporary = format!("{}:rebuild", key);
+            let members: Vec<(u64, String)> = sold
+                .iter()
+                .map(|(product_id, units)| (*units, product_id.to_string()))
+                .collect();
+            let mut pipe = redis::pipe();
+            pipe.del(&temporary)
+                .zadd_multiple(&temporary, &members)
+                .rename(&temporary, &key);
+            if let Some(ttl) = board.ttl() {
+                pipe.expire(&key, ttl.as_secs() as i64);
+            }
+            pipe.exec_async(&mut connection).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Repository that adds every committed checkout to a [`SalesRanking`].
+pub struct Ranked<R> {
+    inner: R,
+    ranking: SalesRanking,
+}
+
+impl<R> Ranked<R> {
+    pub fn new(inner: R, ranking: SalesRanking) -> Self {
+        Ranked { inner, ranking }
+    }
+}
+
+#[async_trait]
+impl<R: TransactionRepository> TransactionRepository for Ranked<R> {
+    async fn find_by_id(&self, id: TransactionId) -> Result<Option<Transaction>, Error> {
+        self.inner.find_by_id(id).await
+    }
+
+    async fn list(&self, page: &Page<TransactionId>) -> Result<Vec<Transaction>, Error> {
+        self.inner.list(page).await
+    }
+
+    async fn checkout(
+        &self,
+        actor: &str,
+        items: &[NewTransactionItem],
+    ) -> Result<Transaction, CheckoutError> {
+        let transaction = self.inner.checkout(actor, items).await?;
+        if let Err(error) = self.ranking.record(&transaction).await {
+            warn!(
+                "Failed to rank sales of transaction {}: {}",
+                transaction.id, error
+            );
+        }
+        Ok(transaction)
+    }
+
+    async fn units_sold(
+        &self,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<Vec<(ProductId, u64)>, Error> {
+        self.inner.units_sold(since).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use belajar_rust_database::{
+        model::ProductId,
+        repository::{NewTransactionItem, SqliteTransactionRepository, TransactionRepository},
+        testing::TestDatabase,
+    };
+    use chrono::{TimeZone, Utc};
+    use redis::AsyncCommands;
+
+    use super::{Board, Ranked, SalesRank, SalesRanking, board_key};
+    use crate::testing;
+
+    #[test]
+    fn test_board_key() {
+        // Wednesday, the week started on Monday the 8th.
+        let at = Utc.with_ymd_and_hms(2025, 9, 10, 23, 59, 0).unwrap();
+        assert_eq!(
+            "minipos:sales:daily:2025-09-10",
+            board_key("minipos", Board::Daily, at)
+        );
+        assert_eq!(
+            "minipos:sales:weekly:2025-09-08",
+            board_key("minipos", Board::Weekly, at)
+        );
+        assert_eq!(
+            "minipos:sales:all_time",
+            board_key("minipos", Board::AllTime, at)
+        );
+    }
+
+    fn item(product_id: u64, quantity: u32) -> NewTransactionItem {
+        NewTransactionItem {
+            product_id: ProductId(product_id),
+            quantity,
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "requires Redis, set TEST_REDIS_URL"]
+    async fn test_sales_ranking() -> Result<(), Box<dyn std::error::Error>> {
+        let database = TestDatabase::sqlite().await?;
+        let mut connection = testing::connection().await?;
+        let ranking =
+            SalesRanking::new(connection.clone(), &format!("test:{}", std::process::id()));
+        let repository = Ranked::new(
+            SqliteTransactionRepository::new(database.pool().clone()),
+            ranking.clone(),
+        );
+
+        repository.checkout("kasir", &[item(1, 2)]).await?;
+        let transaction = repository
+            .checkout("kasir", &[item(1, 1), item(2, 5)])
+            .await?;
+        let now = transaction.created_at;
+
+        let expected = vec![
+            SalesRank {
+                product_id: ProductId(2),
+                rank: 1,
+                score: 5,
+            },
+            SalesRank {
+                product_id: ProductId(1),
+                rank: 2,
+                score: 3,
+            },
+        ];
+        for board in [Board::Daily, Board::Weekly, Board::AllTime] {
+            assert_eq!(expected, ranking.top(board, now, 10).await?);
+        }
+        assert_eq!(expected[..1], ranking.top(Board::Daily, now, 1).await?);
+        assert_eq!(
+            Some(expected[1]),
+            ranking.rank(Board::AllTime, now, ProductId(1)).await?
+        );
+        assert_eq!(None, ranking.rank(Board::AllTime, now, ProductId(3)).await?);
+
+        // Losing the boards, as after a flush, and rebuilding them from the database.
+        for board in [Board::Daily, Board::Weekly, Board::AllTime] {
+            let _: () = connection.del(ranking.key(board, now)).await?;
+        }
+        ranking.rebuild(&repository, now).await?;
+        for board in [Board::Daily, Board::Weekly, Board::AllTime] {
+            assert_eq!(expected, ranking.top(board, now, 10).await?);
+            let _: () = connection.del(ranking.key(board, now)).await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,204 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use redis::{RedisError, Script};
+
+use crate::connection::RedisConnection;
+
+/*
+SLIDING WINDOW RATE LIMIT
+- Setiap request dicatat di Sorted Set dengan score waktu request (milidetik), satu Sorted Set untuk satu key,
+misal `rate:login:127.0.0.1`
+- Saat request datang, entry yang lebih lama dari window dihapus dengan `ZREMRANGEBYSCORE`,
+lalu jumlah entry yang tersisa dihitung dengan `ZCARD`, jika masih di bawah limit, request dicatat dengan `ZADD`
+- Berbeda dengan fixed window, tidak ada lonjakan request di pergantian window, karena yang dihitung selalu request
+dalam `window` terakhir
+- Semua langkah dijalankan di satu Lua script dengan waktu dari `TIME` milik Redis, sehingga semua instance aplikasi
+berbagi hitungan yang sama walaupun jam server nya berbeda
+- `MemoryRateLimiter` menggunakan algoritma yang sama di memory, untuk test atau aplikasi dengan satu instance
+*/
+
+static SLIDING_WINDOW: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r"
+        local time = redis.call('TIME')
+        local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+        local window = tonumber(ARGV[1])
+        local limit = tonumber(ARGV[2])
+        redis.call('ZREMRANGEBYSCORE', KEYS[1], '-inf', now - window)
+        local count = redis.call('ZCARD', KEYS[1])
+        if count < limit then
+            redis.call('ZADD', KEYS[1], now, now .. ':' .. ARGV[3])
+            redis.call('PEXPIRE', KEYS[1], window)
+            return {1, limit - count - 1, 0}
+        end
+        local oldest = redis.call('ZRANGE', KEYS[1], 0, 0, 'WITHSCORES')
+        return {0, 0, tonumber(oldest[2]) + window - now}
+        ",
+    )
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rule {
+    /// Requests allowed within `window`.
+    pub limit: u64,
+    pub window: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub allowed: bool,
+    pub remaining: u64,
+    /// How long until another request is allowed, zero when allowed.
+    pub retry_after: Duration,
+}
+
+#[async_trait]
+pub trait RateLimiter: Send + Sync {
+    /// Records a request for `key` if `rule` still allows it.
+    async fn check(&self, key: &str, rule: &Rule) -> Result<RateLimit, RedisError>;
+}
+
+pub struct RedisRateLimiter {
+    connection: RedisConnection,
+    prefix: String,
+}
+
+impl RedisRateLimiter {
+    pub fn new(connection: RedisConnection, prefix: &str) -> Self {
+        RedisRateLimiter {
+            connection,
+            prefix: prefix.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl RateLimiter for RedisRateLimiter {
+    async fn check(&self, key: &str, rule: &Rule) -> Result<RateLimit, RedisError> {
+        let (allowed, remaining, retry_after): (i64, u64, u64) = SLIDING_WINDOW
+            .key(format!("{}:{}", self.prefix, key))
+            .arg(rule.window.as_millis() as u64)
+            .arg(rule.limit)
+            .arg(uuid::Uuid::new_v4().to_string())
+            .invoke_async(&mut self.connection.clone())
+            .await?;
+        Ok(RateLimit {
+            allowed: allowed == 1,
+            remaining,
+            retry_after: Duration::from_millis(retry_after),
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryRateLimiter {
+    requests: Mutex<Requests>,
+}
+
+#[derive(Default)]
+struct Requests {
+    times: HashMap<String, VecDeque<Instant>>,
+    /// Longest window seen, keys idle for longer than this are dropped.
+    max_window: Duration,
+}
+
+impl MemoryRateLimiter {
+    fn check_at(&self, key: &str, rule: &Rule, now: Instant) -> RateLimit {
+        let mut requests = self.requests.lock().unwrap();
+        let max_window = requests.max_window.max(rule.window);
+        requests.max_window = max_window;
+        requests.times.retain(|_, times| {
+            times
+                .back()
+                .is_some_and(|last| now.duration_since(*last) < max_window)
+        });
+        let times = requests.times.entry(key.to_string()).or_default();
+        while times
+            .front()
+            .is_some_and(|first| now.duration_since(*first) >= rule.window)
+        {
+            times.pop_front();
+        }
+
+        let count = times.len() as u64;
+        if count < rule.limit {
+            times.push_back(now);
+            return RateLimit {
+                allowed: true,
+                remaining: rule.limit - count - 1,
+                retry_after: Duration::ZERO,
+            };
+        }
+        let oldest = times.front().copied().unwrap_or(now);
+        RateLimit {
+            allowed: false,
+            remaining: 0,
+            retry_after: rule.window.saturating_sub(now.duration_since(oldest)),
+        }
+    }
+}
+
+#[async_trait]
+impl RateLimiter for MemoryRateLimiter {
+    async fn check(&self, key: &str, rule: &Rule) -> Result<RateLimit, RedisError> {
+        Ok(self.check_at(key, rule, Instant::now()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{MemoryRateLimiter, RateLimiter, RedisRateLimiter, Rule};
+    use crate::testing;
+
+    const RULE: Rule = Rule {
+        limit: 2,
+        window: Duration::from_secs(10),
+    };
+
+    #[test]
+    fn test_memory_sliding_window() {
+        let limiter = MemoryRateLimiter::default();
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+
+        assert_eq!(1, limiter.check_at("login:a", &RULE, at(0)).remaining);
+        assert_eq!(0, limiter.check_at("login:a", &RULE, at(4)).remaining);
+        let denied = limiter.check_at("login:a", &RULE, at(6));
+        assert!(!denied.allowed);
+        assert_eq!(Duration::from_secs(4), denied.retry_after);
+        assert!(limiter.check_at("login:b", &RULE, at(6)).allowed);
+
+        // The first request left the window, the second one is still in it.
+        assert!(limiter.check_at("login:a", &RULE, at(10)).allowed);
+        assert!(!limiter.check_at("login:a", &RULE, at(11)).allowed);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires Redis, set TEST_REDIS_URL"]
+    async fn test_redis_sliding_window() -> Result<(), Box<dyn std::error::Error>> {
+        let connection = testing::connection().await?;
+        let limiter =
+            RedisRateLimiter::new(connection, &format!("test:{}:rate", std::process::id()));
+        let rule = Rule {
+            limit: 2,
+            window: Duration::from_millis(500),
+        };
+
+        assert!(limiter.check("login", &rule).await?.allowed);
+        assert!(limiter.check("login", &rule).await?.allowed);
+        let denied = limiter.check("login", &rule).await?;
+        assert!(!denied.allowed);
+        assert!(denied.retry_after <= rule.window);
+
+        tokio::time::sleep(rule.window).await;
+        assert_eq!(1, limiter.check("login", &rule).await?.remaining);
+        Ok(())
+    }
+}
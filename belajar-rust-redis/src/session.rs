@@ -0,0 +1,286 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, MutexGuard},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use redis::{AsyncCommands, RedisError, Value};
+use serde::{Deserialize, Serialize};
+
+use crate::{connection::RedisConnection, stream::from_fields};
+
+/*
+SESSION STORE
+- Setiap token yang dibuat saat login memiliki session, disimpan di Hash `session:<id>` dengan id berupa jti dari token
+- Id session milik satu user disimpan di Set `user:<user_id>:sessions`, sehingga semua session user bisa ditampilkan
+atau dibatalkan sekaligus
+- TTL Hash session disamakan dengan waktu kadaluarsa token menggunakan `EXPIREAT`, setelah itu token juga sudah ditolak,
+sehingga session yang kadaluarsa terhapus sendiri tanpa job pembersih
+- Set milik user diperpanjang sampai session yang paling lama kadaluarsa (`EXPIREAT ... NX` lalu `EXPIREAT ... GT`),
+id session yang Hash nya sudah hilang dibersihkan saat session user ditampilkan
+- Token dianggap valid hanya jika session nya masih ada, sehingga membatalkan token cukup dengan menghapus session nya
+- `MemorySessionStore` menyimpan session di memory, untuk test atau aplikasi dengan satu instance
+*/
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Session {
+    pub id: String,
+    pub user_id: u64,
+    /// Unix timestamp in seconds.
+    pub created_at: i64,
+    /// Unix timestamp in seconds, the same as the token expiry.
+    pub expires_at: i64,
+    pub user_agent: Option<String>,
+}
+
+#[async_trait]
+pub trait SessionStore: Send + Sync {
+    async fn create(&self, session: &Session) -> Result<(), RedisError>;
+
+    /// True while the session exists and has not expired.
+    async fn is_active(&self, id: &str) -> Result<bool, RedisError>;
+
+    /// Active sessions of a user, oldest first.
+    async fn list(&self, user_id: u64) -> Result<Vec<Session>, RedisError>;
+
+    /// Returns false if the session did not exist.
+    async fn revoke(&self, id: &str) -> Result<bool, RedisError>;
+
+    /// Returns the number of sessions revoked.
+    async fn revoke_all(&self, user_id: u64) -> Result<usize, RedisError>;
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+#[derive(Clone)]
+pub struct RedisSessionStore {
+    connection: RedisConnection,
+    prefix: String,
+}
+
+impl RedisSessionStore {
+    pub fn new(connection: RedisConnection, prefix: &str) -> Self {
+        RedisSessionStore {
+            connection,
+            prefix: prefix.to_string(),
+        }
+    }
+
+    fn session_key(&self, id: &str) -> String {
+        format!("{}:session:{}", self.prefix, id)
+    }
+
+    fn user_key(&self, user_id: u64) -> String {
+        format!("{}:user:{}:sessions", self.prefix, user_id)
+    }
+}
+
+#[async_trait]
+impl SessionStore for RedisSessionStore {
+    async fn create(&self, session: &Session) -> Result<(), RedisError> {
+        let mut fields = vec![
+            ("id", session.id.clone()),
+            ("user_id", session.user_id.to_string()),
+            ("created_at", session.created_at.to_string()),
+            ("expires_at", session.expires_at.to_string()),
+        ];
+        if let Some(user_agent) = &session.user_agent {
+            fields.push(("user_agent", user_agent.clone()));
+        }
+
+        let session_key = self.session_key(&session.id);
+        let user_key = self.user_key(session.user_id);
+        redis::pipe()
+            .hset_multiple(&session_key, &fields)
+            .expire_at(&session_key, session.expires_at)
+            .sadd(&user_key, &session.id)
+            .cmd("EXPIREAT")
+            .arg(&user_key)
+            .arg(session.expires_at)
+            .arg("NX")
+            .cmd("EXPIREAT")
+            .arg(&user_key)
+            .arg(session.expires_at)
+            .arg("GT")
+            .exec_async(&mut self.connection.clone())
+            .await
+    }
+
+    async fn is_active(&self, id: &str) -> Result<bool, RedisError> {
+        self.connection.clone().exists(self.session_key(id)).await
+    }
+
+    async fn list(&self, user_id: u64) -> Result<Vec<Session>, RedisError> {
+        let mut connection = self.connection.clone();
+        let user_key = self.user_key(user_id);
+        let ids: Vec<String> = connection.smembers(&user_key).await?;
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut pipe = redis::pipe();
+        for id in &ids {
+            pipe.hgetall(self.session_key(id));
+        }
+        let found: Vec<HashMap<String, Value>> = pipe.query_async(&mut connection).await?;
+
+        let mut sessions = Vec::new();
+        let mut stale = Vec::new();
+        for (id, fields) in ids.into_iter().zip(found) {
+            match from_fields::<Session>(&fields) {
+                Ok(session) => sessions.push(session),
+                Err(_) => stale.push(id),
+            }
+        }
+        if !stale.is_empty() {
+            let _: () = connection.srem(&user_key, &stale).await?;
+        }
+        sessions.sort_by_key(|session| session.created_at);
+        Ok(sessions)
+    }
+
+    async fn revoke(&self, id: &str) -> Result<bool, RedisError> {
+        let mut connection = self.connection.clone();
+        let session_key = self.session_key(id);
+        let user_id: Option<u64> = connection.hget(&session_key, "user_id").await?;
+        let Some(user_id) = user_id else {
+            return Ok(false);
+        };
+        let (deleted, _): (usize, usize) = redis::pipe()
+            .del(&session_key)
+            .srem(self.user_key(user_id), id)
+            .query_async(&mut connection)
+            .await?;
+        Ok(deleted == 1)
+    }
+
+    async fn revoke_all(&self, user_id: u64) -> Result<usize, RedisError> {
+        let mut connection = self.connection.clone();
+        let user_key = self.user_key(user_id);
+        let ids: Vec<String> = connection.smembers(&user_key).await?;
+        if ids.is_empty() {
+            return Ok(0);
+        }
+
+        // Only the ids read above are removed, so a session created meanwhile stays listed.
+        let keys: Vec<String> = ids.iter().map(|id| self.session_key(id)).collect();
+        let (deleted, _): (usize, usize) = redis::pipe()
+            .del(&keys)
+            .srem(&user_key, &ids)
+            .query_async(&mut connection)
+            .await?;
+        Ok(deleted)
+    }
+}
+
+#[derive(Default)]
+pub struct MemorySessionStore {
+    sessions: Mutex<HashMap<String, Session>>,
+}
+
+impl MemorySessionStore {
+    fn active(&self) -> MutexGuard<'_, HashMap<String, Session>> {
+        let now = now();
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.retain(|_, session| session.expires_at > now);
+        sessions
+    }
+}
+
+#[async_trait]
+impl SessionStore for MemorySessionStore {
+    async fn create(&self, session: &Session) -> Result<(), RedisError> {
+        self.active().insert(session.id.clone(), session.clone());
+        Ok(())
+    }
+
+    async fn is_active(&self, id: &str) -> Result<bool, RedisError> {
+        Ok(self.active().contains_key(id))
+    }
+
+    async fn list(&self, user_id: u64) -> Result<Vec<Session>, RedisError> {
+        let mut sessions: Vec<Session> = self
+            .active()
+            .values()
+            .filter(|session| session.user_id == user_id)
+            .cloned()
+            .collect();
+        sessions.sort_by_key(|session| session.created_at);
+        Ok(sessions)
+    }
+
+    async fn revoke(&self, id: &str) -> Result<bool, RedisError> {
+        Ok(self.active().remove(id).is_some())
+    }
+
+    async fn revoke_all(&self, user_id: u64) -> Result<usize, RedisError> {
+        let mut sessions = self.active();
+        let before = sessions.len();
+        sessions.retain(|_, session| session.user_id != user_id);
+        Ok(before - sessions.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MemorySessionStore, RedisSessionStore, Session, SessionStore, now};
+    use crate::testing;
+
+    fn session(id: &str, user_id: u64, created_at: i64, expires_at: i64) -> Session {
+        Session {
+            id: id.to_string(),
+            user_id,
+            created_at,
+            expires_at,
+            user_agent: (id == "a").then(|| "PostmanRuntime/7.45.0".to_string()),
+        }
+    }
+
+    async fn check_store(store: &dyn SessionStore) -> Result<(), Box<dyn std::error::Error>> {
+        let now = now();
+        store.create(&session("a", 1, now - 20, now + 60)).await?;
+        store.create(&session("b", 1, now - 10, now + 3600)).await?;
+        store.create(&session("c", 2, now, now + 60)).await?;
+        store.create(&session("d", 1, now - 30, now - 1)).await?;
+
+        assert!(store.is_active("a").await?);
+        assert!(!store.is_active("d").await?);
+        let ids: Vec<String> = store
+            .list(1)
+            .await?
+            .into_iter()
+            .map(|session| session.id)
+            .collect();
+        assert_eq!(vec!["a", "b"], ids);
+        assert_eq!(session("a", 1, now - 20, now + 60), store.list(1).await?[0]);
+
+        assert!(store.revoke("a").await?);
+        assert!(!store.revoke("a").await?);
+        assert!(!store.is_active("a").await?);
+
+        assert_eq!(1, store.revoke_all(1).await?);
+        assert!(store.list(1).await?.is_empty());
+        assert!(store.is_active("c").await?);
+        assert_eq!(1, store.revoke_all(2).await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_memory_session_store() -> Result<(), Box<dyn std::error::Error>> {
+        check_store(&MemorySessionStore::default()).await
+    }
+
+    #[tokio::test]
+    #[ignore = "requires Redis, set TEST_REDIS_URL"]
+    async fn test_redis_session_store() -> Result<(), Box<dyn std::error::Error>> {
+        let connection = testing::connection().await?;
+        let store = RedisSessionStore::new(connection, &format!("test:{}", std::process::id()));
+        check_store(&store).await
+    }
+}
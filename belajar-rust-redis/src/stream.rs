@@ -0,0 +1,711 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    time::Duration,
+};
+
+use log::warn;
+use redis::{
+    AsyncCommands, RedisError, Value,
+    streams::{
+        StreamAutoClaimOptions, StreamAutoClaimReply, StreamId, StreamPendingCountReply,
+        StreamReadOptions, StreamReadReply,
+    },
+};
+use serde::{
+    Deserializer,
+    de::{
+        self, DeserializeOwned, IntoDeserializer, Visitor,
+        value::{MapDeserializer, StrDeserializer},
+    },
+    forward_to_deserialize_any,
+};
+
+use crate::{backoff::Backoff, connection::RedisConnection};
+
+/*
+STREAM CONSUMER
+- Consumer membaca entry stream menggunakan consumer group (`XREADGROUP`), mengubah field entry menjadi struct
+menggunakan serde, lalu memanggil handler
+- Jika handler berhasil, entry di-acknowledge menggunakan `XACK`, sehingga dihapus dari daftar pending (PEL)
+- Jika handler gagal, atau consumer mati sebelum `XACK`, entry tetap pending, dan setelah idle lebih lama dari
+`claim_idle`, entry diambil alih (`XAUTOCLAIM`) oleh consumer yang sedang berjalan untuk dicoba lagi
+- Jumlah pengiriman entry yang diambil alih dibaca dengan satu `XPENDING` untuk rentang id nya, bukan satu `XPENDING` per entry
+- `Consumer::run` tidak berhenti ketika Redis error, misal saat Redis restart atau failover, error nya dicatat di log
+lalu dicoba lagi dengan backoff, consumer group juga dibuat ulang jika hilang (`NOGROUP`)
+
+DEAD LETTER
+- Entry yang selalu gagal (poison message) tidak boleh dicoba terus menerus
+- Setelah dikirim `max_attempts` kali, entry dipindahkan ke stream dead letter, misal `members:dead-letter`,
+bersama id asal, jumlah percobaan dan pesan error nya, lalu di-acknowledge dari stream asal
+- Entry yang tidak bisa di-decode langsung dipindahkan ke dead letter, karena mencoba lagi tidak akan berhasil,
+termasuk event dengan versi schema yang tidak dikenal
+- Event dengan tipe lain di stream yang sama di-acknowledge tanpa memanggil handler, karena ditujukan untuk consumer group lain
+*/
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodeError {
+    /// A field is missing or cannot be read as the expected type.
+    Invalid(String),
+    /// The entry is another event type, see [`crate::event`].
+    UnexpectedType { expected: String, found: String },
+    /// The event was written with a schema version this consumer cannot read.
+    UnknownVersion { event_type: String, version: u32 },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Invalid(message) => write!(f, "invalid stream entry: {}", message),
+            DecodeError::UnexpectedType { expected, found } => {
+                write!(f, "expected event {} but found {}", expected, found)
+            }
+            DecodeError::UnknownVersion {
+                event_type,
+                version,
+            } => write!(f, "unknown version {} of event {}", version, event_type),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl de::Error for DecodeError {
+    fn custom<T: fmt::Display>(message: T) -> Self {
+        DecodeError::Invalid(message.to_string())
+    }
+}
+
+/// One field value. Stream values are always strings, so numbers and bools
+/// are parsed when the target type asks for them, and sequences or maps are
+/// read as JSON.
+struct FieldValue<'de>(&'de str);
+
+macro_rules! parse_field {
+    ($($method:ident => $visit:ident),* $(,)?) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DecodeError> {
+                let value = self.0.parse().map_err(|_| {
+                    DecodeError::Invalid(format!("cannot parse {:?}", self.0))
+                })?;
+                visitor.$visit(value)
+            }
+        )*
+    };
+}
+
+macro_rules! json_field {
+    ($($method:ident($($arg:ident: $ty:ty),*)),* $(,)?) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value, DecodeError> {
+                serde_json::Deserializer::from_str(self.0)
+                    .$method($($arg,)* visitor)
+                    .map_err(de::Error::custom)
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for FieldValue<'de> {
+    type Error = DecodeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DecodeError> {
+        visitor.visit_borrowed_str(self.0)
+    }
+
+    parse_field!(
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+    );
+
+    json_field!(
+        deserialize_seq(),
+        deserialize_tuple(len: usize),
+        deserialize_tuple_struct(name: &'static str, len: usize),
+        deserialize_map(),
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]),
+    );
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DecodeError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DecodeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DecodeError> {
+        let variant: StrDeserializer<DecodeError> = self.0.into_deserializer();
+        visitor.visit_enum(variant)
+    }
+
+    forward_to_deserialize_any! {
+        char str string bytes byte_buf unit unit_struct identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, DecodeError> for FieldValue<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+/// Reads the fields of a stream entry or a hash, for example the ones written
+/// by `xadd_map` or `HGETALL`, into `T`. Fields that `T` does not have are ignored.
+pub fn from_fields<T: DeserializeOwned>(fields: &HashMap<String, Value>) -> Result<T, DecodeError> {
+    let fields = fields
+        .iter()
+        .map(|(name, value)| {
+            let value: String = redis::from_redis_value(value)
+                .map_err(|_| DecodeError::Invalid(format!("field {} is not a string", name)))?;
+            Ok((name.as_str(), value))
+        })
+        .collect::<Result<Vec<_>, DecodeError>>()?;
+    T::deserialize(MapDeserializer::new(
+        fields
+            .iter()
+            .map(|(name, value)| (*name, FieldValue(value.as_str()))),
+    ))
+}
+
+#[derive(Debug, Clone)]
+pub struct ConsumerOptions {
+    pub group: String,
+    pub consumer: String,
+    /// Maximum number of entries read or claimed per poll.
+    pub count: usize,
+    /// How long a poll waits for new entries.
+    pub block: Duration,
+    /// How long an entry stays pending before another consumer claims it.
+    pub claim_idle: Duration,
+    /// Number of deliveries after which a failing entry is dead lettered.
+    pub max_attempts: usize,
+    /// Defaults to `<stream>:dead-letter`.
+    pub dead_letter: Option<String>,
+    /// Delay range before [`Consumer::run`] retries after a Redis error.
+    pub retry_min: Duration,
+    pub retry_max: Duration,
+}
+
+impl ConsumerOptions {
+    pub fn new(group: &str, consumer: &str) -> Self {
+        ConsumerOptions {
+            group: group.to_string(),
+            consumer: consumer.to_string(),
+            count: 10,
+            block: Duration::from_secs(5),
+            claim_idle: Duration::from_secs(30),
+            max_attempts: 5,
+            dead_letter: None,
+            retry_min: Duration::from_millis(100),
+            retry_max: Duration::from_secs(30),
+        }
+    }
+}
+
+/// What happened during one [`Consumer::poll`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PollReport {
+    /// Entries taken over from consumers that did not acknowledge them.
+    pub claimed: usize,
+    pub handled: usize,
+    /// Entries whose handler failed, they stay pending and are retried.
+    pub failed: usize,
+    pub dead_lettered: usize,
+    /// Events of another type, acknowledged without calling the handler.
+    pub skipped: usize,
+}
+
+impl PollReport {
+    pub fn is_empty(&self) -> bool {
+        self.claimed + self.handled + self.failed + self.dead_lettered + self.skipped == 0
+    }
+}
+
+pub type Decoder<T> = fn(&HashMap<String, Value>) -> Result<T, DecodeError>;
+
+pub struct Consumer<T> {
+    connection: RedisConnection,
+    stream: String,
+    options: ConsumerOptions,
+    decode: Decoder<T>,
+}
+
+impl<T: DeserializeOwned> Consumer<T> {
+    /// Consumer that reads entries with [`from_fields`].
+    pub fn new(connection: RedisConnection, stream: &str, options: ConsumerOptions) -> Self {
+        Consumer::with_decoder(connection, stream, options, from_fields::<T>)
+    }
+}
+
+impl<T> Consumer<T> {
+    pub fn with_decoder(
+        connection: RedisConnection,
+        stream: &str,
+        options: ConsumerOptions,
+        decode: Decoder<T>,
+    ) -> Self {
+        Consumer {
+            connection,
+            stream: stream.to_string(),
+            options,
+            decode,
+        }
+    }
+
+    pub fn dead_letter_stream(&self) -> String {
+        self.options
+            .dead_letter
+            .clone()
+            .unwrap_or_else(|| format!("{}:dead-letter", self.stream))
+    }
+
+    /// Creates the stream and the consumer group, reading from the first
+    /// entry. An existing group is left as it is.
+    pub async fn create_group(&self) -> Result<(), RedisError> {
+        let result: Result<(), RedisError> = self
+            .connection
+            .clone()
+            .xgroup_create_mkstream(&self.stream, &self.options.group, "0")
+            .await;
+        match result {
+            Err(error) if error.code() == Some("BUSYGROUP") => Ok(()),
+            result => result,
+        }
+    }
+
+    /// Handles stale pending entries first, then new ones. Waits up to
+    /// `block` for new entries when there was nothing to claim.
+    pub async fn poll<F, Fut, E>(&self, handler: &F) -> Result<PollReport, RedisError>
+    where
+        F: Fn(T) -> Fut,
+        Fut: Future<Output = Result<(), E>>,
+        E: fmt::Display,
+    {
+        let mut connection = self.connection.clone();
+        let mut report = PollReport::default();
+
+        let reply: StreamAutoClaimReply = connection
+            .xautoclaim_options(
+                &self.stream,
+                &self.options.group,
+                &self.options.consumer,
+                self.options.claim_idle.as_millis() as u64,
+                "0-0",
+                StreamAutoClaimOptions::default().count(self.options.count),
+            )
+            .await?;
+        report.claimed = reply.claimed.len();
+        let mut attempts = self.delivery_counts(&reply.claimed).await?;
+        for entry in reply.claimed {
+            let attempts = attempts.remove(&entry.id).unwrap_or(1);
+            self.process(entry, attempts, handler, &mut report).await?;
+        }
+
+        let mut options = StreamReadOptions::default()
+            .group(&self.options.group, &self.options.consumer)
+            .count(self.options.count);
+        if report.claimed == 0 {
+            options = options.block(self.options.block.as_millis() as usize);
+        }
+        let reply: Option<StreamReadReply> = connection
+            .xread_options(&[&self.stream], &[">"], &options)
+            .await?;
+        for key in reply.map(|reply| reply.keys).unwrap_or_default() {
+            for entry in key.ids {
+                self.process(entry, 1, handler, &mut report).await?;
+            }
+        }
+        Ok(report)
+    }
+
+    /// Creates the group, then polls forever. Redis errors are logged and
+    /// retried with a backoff, the group is created again when it is gone.
+    pub async fn run<F, Fut, E>(&self, handler: F)
+    where
+        F: Fn(T) -> Fut,
+        Fut: Future<Output = Result<(), E>>,
+        E: fmt::Display,
+    {
+        let mut backoff = Backoff::new(self.options.retry_min, self.options.retry_max);
+        let mut has_group = false;
+        loop {
+            let result = if has_group {
+                self.poll(&handler).await.map(|_| ())
+            } else {
+                self.create_group().await
+            };
+            match result {
+                Ok(()) => {
+                    has_group = true;
+                    backoff.reset();
+                }
+                Err(error) => {
+                    has_group = has_group && error.code() != Some("NOGROUP");
+                    warn!("Consumer of stream {} failed: {}", self.stream, error);
+                    tokio::time::sleep(backoff.next_delay()).await;
+                }
+            }
+        }
+    }
+
+    /// Delivery counts of entries just claimed by this consumer, in the order
+    /// `XAUTOCLAIM` returned them. Entries this consumer already had pending
+    /// can sit between them, so the range is read in pages until all are found.
+    async fn delivery_counts(
+        &self,
+        entries: &[StreamId],
+    ) -> Result<HashMap<String, usize>, RedisError> {
+        let mut counts = HashMap::new();
+        let (Some(first), Some(last)) = (entries.first(), entries.last()) else {
+            return Ok(counts);
+        };
+        let mut wanted: HashSet<&str> = entries.iter().map(|entry| entry.id.as_str()).collect();
+        let page = self.options.count.max(entries.len());
+        let mut start = first.id.clone();
+        while !wanted.is_empty() {
+            let reply: StreamPendingCountReply = self
+                .connection
+                .clone()
+                .xpending_consumer_count(
+                    &self.stream,
+                    &self.options.group,
+                    &start,
+                    &last.id,
+                    page,
+                    &self.options.consumer,
+                )
+                .await?;
+            let Some(end) = reply.ids.last() else {
+                break;
+            };
+            start = format!("({}", end.id);
+            let full = reply.ids.len() == page;
+            for pending in reply.ids {
+                if wanted.remove(pending.id.as_str()) {
+                    counts.insert(pending.id, pending.times_delivered);
+                }
+            }
+            if !full {
+                break;
+            }
+        }
+        Ok(counts)
+    }
+
+    async fn process<F, Fut, E>(
+        &self,
+        entry: StreamId,
+        attempts: usize,
+        handler: &F,
+        report: &mut PollReport,
+    ) -> Result<(), RedisError>
+    where
+        F: Fn(T) -> Fut,
+        Fut: Future<Output = Result<(), E>>,
+        E: fmt::Display,
+    {
+        let message = match (self.decode)(&entry.map) {
+            Ok(message) => message,
+            Err(DecodeError::UnexpectedType { .. }) => {
+                let _: () = self
+                    .connection
+                    .clone()
+                    .xack(&self.stream, &self.options.group, &[&entry.id])
+                    .await?;
+                report.skipped += 1;
+                return Ok(());
+            }
+            Err(error) => return self.dead_letter(entry, attempts, error, report).await,
+        };
+        if attempts > self.options.max_attempts {
+            let error = format!("gave up after {} attempts", self.options.max_attempts);
+            return self.dead_letter(entry, attempts, error, report).await;
+        }
+
+        match handler(message).await {
+            Ok(()) => {
+                let _: () = self
+                    .connection
+                    .clone()
+                    .xack(&self.stream, &self.options.group, &[&entry.id])
+                    .await?;
+                report.handled += 1;
+                Ok(())
+            }
+            Err(error) if attempts >= self.options.max_attempts => {
+                self.dead_letter(entry, attempts, error, report).await
+            }
+            Err(_) => {
+                report.failed += 1;
+                Ok(())
+            }
+        }
+    }
+
+    /// Copies the entry to the dead letter stream and acknowledges it, in one
+    /// transaction so it is never lost or duplicated.
+    async fn dead_letter(
+        &self,
+        entry: StreamId,
+        attempts: usize,
+        error: impl fmt::Display,
+        report: &mut PollReport,
+    ) -> Result<(), RedisError> {
+        let mut fields: Vec<(String, Vec<u8>)> = entry
+            .map
+            .iter()
+            .filter_map(|(name, value)| {
+                let value = redis::from_redis_value(value).ok()?;
+                Some((name.clone(), value))
+            })
+            .collect();
+        fields.push(("_source_id".to_string(), entry.id.clone().into_bytes()));
+        fields.push(("_attempts".to_string(), attempts.to_string().into_bytes()));
+        fields.push(("_error".to_string(), error.to_string().into_bytes()));
+
+        redis::pipe()
+            .atomic()
+            .xadd(self.dead_letter_stream(), "*", &fields)
+            .xack(&self.stream, &self.options.group, &[&entry.id])
+            .exec_async(&mut self.connection.clone())
+            .await?;
+        report.dead_lettered += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, time::Duration};
+
+    use redis::{AsyncCommands, RedisError, Value, streams::StreamRangeReply};
+    use serde::Deserialize;
+    use tokio::{sync::mpsc, time::timeout};
+
+    use super::{Consumer, ConsumerOptions, DecodeError, PollReport, from_fields};
+    use crate::testing;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Level {
+        Gold,
+        Silver,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Member {
+        name: String,
+        age: u32,
+        active: bool,
+        level: Level,
+        city: Option<String>,
+        tags: Vec<String>,
+    }
+
+    fn fields(values: &[(&str, &str)]) -> HashMap<String, Value> {
+        values
+            .iter()
+            .map(|(name, value)| {
+                (
+                    name.to_string(),
+                    Value::BulkString(value.as_bytes().to_vec()),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_from_fields() {
+        let member: Member = from_fields(&fields(&[
+            ("name", "123"),
+            ("age", "30"),
+            ("active", "true"),
+            ("level", "gold"),
+            ("tags", r#"["vip", "jakarta"]"#),
+            ("address", "Indonesia"),
+        ]))
+        .unwrap();
+        assert_eq!(
+            Member {
+                name: "123".to_string(),
+                age: 30,
+                active: true,
+                level: Level::Gold,
+                city: None,
+                tags: vec!["vip".to_string(), "jakarta".to_string()],
+            },
+            member
+        );
+
+        let result: Result<Member, DecodeError> = from_fields(&fields(&[
+            ("name", "Zhafir"),
+            ("age", "tiga puluh"),
+            ("active", "true"),
+            ("level", "silver"),
+            ("tags", "[]"),
+        ]));
+        assert_eq!(
+            Err(DecodeError::Invalid(
+                "cannot parse \"tiga puluh\"".to_string()
+            )),
+            result
+        );
+
+        let result: Result<Member, DecodeError> = from_fields(&fields(&[("name", "Zhafir")]));
+        assert!(matches!(result, Err(DecodeError::Invalid(message)) if message.contains("age")));
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Order {
+        id: u32,
+    }
+
+    #[tokio::test]
+    #[ignore = "requires Redis, set TEST_REDIS_URL"]
+    async fn test_consumer_dead_letters_poison_messages() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let mut connection = testing::connection().await?;
+        let stream = format!("test:{}:orders", std::process::id());
+        let _: () = connection
+            .del(&[stream.clone(), format!("{}:dead-letter", stream)])
+            .await?;
+
+        let consumer: Consumer<Order> = Consumer::new(
+            connection.clone(),
+            &stream,
+            ConsumerOptions {
+                block: Duration::from_millis(10),
+                claim_idle: Duration::ZERO,
+                max_attempts: 2,
+                ..ConsumerOptions::new("workers", "worker-1")
+            },
+        );
+        consumer.create_group().await?;
+        consumer.create_group().await?;
+        for id in ["1", "2", "bukan-angka"] {
+            let _: String = connection.xadd(&stream, "*", &[("id", id)]).await?;
+        }
+
+        let handler = |order: Order| async move {
+            if order.id == 2 {
+                Err("order 2 is broken")
+            } else {
+                Ok(())
+            }
+        };
+        assert_eq!(
+            PollReport {
+                claimed: 0,
+                handled: 1,
+                failed: 1,
+                dead_lettered: 1,
+                skipped: 0
+            },
+            consumer.poll(&handler).await?
+        );
+        assert_eq!(
+            PollReport {
+                claimed: 1,
+                handled: 0,
+                failed: 0,
+                dead_lettered: 1,
+                skipped: 0
+            },
+            consumer.poll(&handler).await?
+        );
+        assert!(consumer.poll(&handler).await?.is_empty());
+
+        let dead: StreamRangeReply = connection.xrange_all(consumer.dead_letter_stream()).await?;
+        let errors: Vec<String> = dead
+            .ids
+            .iter()
+            .map(|entry| entry.get("_error").unwrap())
+            .collect();
+        assert!(errors[0].starts_with("invalid stream entry"));
+        assert_eq!("order 2 is broken", errors[1]);
+        assert_eq!(Some("2".to_string()), dead.ids[1].get("_attempts"));
+
+        let pending: redis::streams::StreamPendingReply =
+            connection.xpending(&stream, "workers").await?;
+        assert_eq!(0, pending.count());
+        let _: Result<(), RedisError> = connection.del(&stream).await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[ignore = "requires Redis, set TEST_REDIS_URL"]
+    async fn test_run_survives_redis_errors() -> Result<(), Box<dyn std::error::Error>> {
+        let mut connection = testing::connection().await?;
+        let stream = format!("test:{}:run", std::process::id());
+        let _: () = connection.del(&stream).await?;
+
+        let consumer: Consumer<Order> = Consumer::new(
+            connection.clone(),
+            &stream,
+            ConsumerOptions {
+                block: Duration::from_millis(10),
+                retry_min: Duration::from_millis(10),
+                retry_max: Duration::from_millis(50),
+                ..ConsumerOptions::new("workers", "worker-1")
+            },
+        );
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let task = tokio::spawn(async move {
+            consumer
+                .run(move |order: Order| {
+                    let sender = sender.clone();
+                    async move { sender.send(order.id).map_err(|error| error.to_string()) }
+                })
+                .await
+        });
+
+        let _: String = connection.xadd(&stream, "*", &[("id", "1")]).await?;
+        assert_eq!(
+            Some(1),
+            timeout(Duration::from_secs(5), receiver.recv()).await?
+        );
+
+        // Polling fails with NOGROUP until the consumer creates the group again.
+        let _: () = redis::cmd("XGROUP")
+            .arg("DESTROY")
+            .arg(&stream)
+            .arg("workers")
+            .query_async(&mut connection)
+            .await?;
+        let _: String = connection.xadd(&stream, "*", &[("id", "2")]).await?;
+        let mut received = Vec::new();
+        while !received.contains(&2) {
+            received.push(
+                timeout(Duration::from_secs(5), receiver.recv())
+                    .await?
+                    .unwrap(),
+            );
+        }
+        assert!(!task.is_finished());
+
+        task.abort();
+        let _: Result<(), RedisError> = connection.del(&stream).await;
+        Ok(())
+    }
+}
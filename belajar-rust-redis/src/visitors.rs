@@ -0,0 +1,255 @@
+use std::time::Duration;
+
+use belajar_rust_database::model::DailyVisitors;
+use chrono::{Datelike, Days, Months, NaiveDate};
+use redis::{AsyncCommands, RedisError};
+use serde::{Deserialize, Serialize};
+
+use crate::connection::RedisConnection;
+
+/*
+UNIQUE VISITORS
+- Pengunjung unik dihitung menggunakan HyperLogLog, satu key per hari `visitors:<tanggal>` dan satu key per hari per route
+`visitors:<tanggal>:route:<route>`, misal `visitors:2025-09-08:route:GET /products`
+- HyperLogLog hanya membutuhkan maksimal 12KB per key berapapun jumlah pengunjungnya, dengan error sekitar 0.81%,
+sehingga memory tidak bertambah walaupun pengunjung bertambah
+- Semua key diberi TTL, sehingga jumlah key juga tidak bertambah terus, jumlah harian disimpan ke database
+oleh job terjadwal sebelum key nya kadaluarsa
+- Jumlah mingguan dan bulanan dihitung dengan `PFMERGE` key harian ke key baru, karena pengunjung yang sama
+di beberapa hari hanya boleh terhitung sekali, menjumlahkan hitungan harian akan menghasilkan angka yang terlalu besar
+- Route yang dikunjungi di satu hari dicatat di Set `visitors:<tanggal>:routes`, agar job bisa membaca semua key route nya
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Period {
+    #[default]
+    Day,
+    /// Monday to Sunday.
+    Week,
+    Month,
+}
+
+impl Period {
+    /// First and last day of the period containing `date`.
+    pub fn range(self, date: NaiveDate) -> (NaiveDate, NaiveDate) {
+        match self {
+            Period::Day => (date, date),
+            Period::Week => {
+                let start = date - Days::new(date.weekday().num_days_from_monday() as u64);
+                (start, start + Days::new(6))
+            }
+            Period::Month => {
+                let start = date.with_day(1).unwrap_or(date);
+                (start, start + Months::new(1) - Days::new(1))
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct VisitorCounter {
+    connection: RedisConnection,
+    prefix: String,
+    ttl: Duration,
+}
+
+impl VisitorCounter {
+    /// Daily keys expire after `ttl`, which should be longer than a month to
+    /// keep monthly counts complete.
+    pub fn new(connection: RedisConnection, prefix: &str, ttl: Duration) -> Self {
+        VisitorCounter {
+            connection,
+            prefix: prefix.to_string(),
+            ttl,
+        }
+    }
+
+    fn day_key(&self, date: NaiveDate, route: Option<&str>) -> String {
+        match route {
+            Some(route) => format!("{}:visitors:{}:route:{}", self.prefix, date, route),
+            None => format!("{}:visitors:{}", self.prefix, date),
+        }
+    }
+
+    fn routes_key(&self, date: NaiveDate) -> String {
+        format!("{}:visitors:{}:routes", self.prefix, date)
+    }
+
+    fn merged_key(&self, period: Period, start: NaiveDate, route: Option<&str>) -> String {
+        let period = match period {
+            Period::Day => "day",
+            Period::Week => "week",
+            Period::Month => "month",
+        };
+        match route {
+            Some(route) => format!(
+                "{}:visitors:{}:{}:route:{}",
+                self.prefix, period, start, route
+            ),
+            None => format!("{}:visitors:{}:{}", self.prefix, period, start),
+        }
+    }
+
+    pub async fn record(
+        &self,
+        visitor: &str,
+        route: &str,
+        date: NaiveDate,
+    ) -> Result<(), RedisError> {
+        let ttl = self.ttl.as_secs() as i64;
+        let total_key = self.day_key(date, None);
+        let route_key = self.day_key(date, Some(route));
+        let routes_key = self.routes_key(date);
+        redis::pipe()
+            .pfadd(&total_key, visitor)
+            .expire(&total_key, ttl)
+            .pfadd(&route_key, visitor)
+            .expire(&route_key, ttl)
+            .sadd(&routes_key, route)
+            .expire(&routes_key, ttl)
+            .exec_async(&mut self.connection.clone())
+            .await
+    }
+
+    /// Unique visitors of the period containing `date`, of every route when
+    /// `route` is `None`.
+    pub async fn count(
+        &self,
+        period: Period,
+        date: NaiveDate,
+        route: Option<&str>,
+    ) -> Result<u64, RedisError> {
+        let mut connection = self.connection.clone();
+        let (start, end) = period.range(date);
+        if start == end {
+            return connection.pfcount(self.day_key(start, route)).await;
+        }
+
+        let days: Vec<String> = start
+            .iter_days()
+            .take_while(|day| *day <= end)
+            .map(|day| self.day_key(day, route))
+            .collect();
+        let merged = self.merged_key(period, start, route);
+        let (_, count, _): ((), u64, ()) = redis::pipe()
+            .pfmerge(&merged, days)
+            .pfcount(&merged)
+            .expire(&merged, self.ttl.as_secs() as i64)
+            .query_async(&mut connection)
+            .await?;
+        Ok(count)
+    }
+
+    /// Counts of `date` for every route, and for all routes together.
+    pub async fn daily_counts(&self, date: NaiveDate) -> Result<Vec<DailyVisitors>, RedisError> {
+        let mut connection = self.connection.clone();
+        let routes: Vec<String> = connection.smembers(self.routes_key(date)).await?;
+
+        let mut pipe = redis::pipe();
+        pipe.pfcount(self.day_key(date, None));
+        for route in &routes {
+            pipe.pfcount(self.day_key(date, Some(route)));
+        }
+        let counts: Vec<u64> = pipe.query_async(&mut connection).await?;
+
+        Ok(std::iter::once(DailyVisitors::ALL_ROUTES.to_string())
+            .chain(routes)
+            .zip(counts)
+            .map(|(route, visitors)| DailyVisitors {
+                date,
+                route,
+                visitors,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use belajar_rust_database::model::DailyVisitors;
+    use chrono::NaiveDate;
+
+    use super::{Period, VisitorCounter};
+    use crate::testing;
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_period_range() {
+        assert_eq!((date(9, 10), date(9, 10)), Period::Day.range(date(9, 10)));
+        assert_eq!((date(9, 8), date(9, 14)), Period::Week.range(date(9, 10)));
+        assert_eq!((date(9, 8), date(9, 14)), Period::Week.range(date(9, 14)));
+        assert_eq!((date(2, 1), date(2, 28)), Period::Month.range(date(2, 15)));
+        assert_eq!(
+            (date(12, 1), date(12, 31)),
+            Period::Month.range(date(12, 31))
+        );
+    }
+
+    #[tokio::test]
+    #[ignore = "requires Redis, set TEST_REDIS_URL"]
+    async fn test_visitor_counter() -> Result<(), Box<dyn std::error::Error>> {
+        let connection = testing::connection().await?;
+        let counter = VisitorCounter::new(
+            connection,
+            &format!("test:{}", std::process::id()),
+            Duration::from_secs(60),
+        );
+
+        counter
+            .record("ip:10.0.0.1", "GET /products", date(9, 8))
+            .await?;
+        counter
+            .record("ip:10.0.0.1", "GET /products", date(9, 8))
+            .await?;
+        counter
+            .record("ip:10.0.0.2", "GET /products", date(9, 8))
+            .await?;
+        counter
+            .record("user:1", "POST /transactions", date(9, 8))
+            .await?;
+        counter
+            .record("ip:10.0.0.1", "GET /products", date(9, 9))
+            .await?;
+        counter
+            .record("ip:10.0.0.3", "GET /products", date(9, 30))
+            .await?;
+
+        assert_eq!(3, counter.count(Period::Day, date(9, 8), None).await?);
+        assert_eq!(
+            2,
+            counter
+                .count(Period::Day, date(9, 8), Some("GET /products"))
+                .await?
+        );
+        // The visitor of the 9th already visited on the 8th.
+        assert_eq!(3, counter.count(Period::Week, date(9, 9), None).await?);
+        assert_eq!(
+            3,
+            counter
+                .count(Period::Month, date(9, 1), Some("GET /products"))
+                .await?
+        );
+
+        let mut counts = counter.daily_counts(date(9, 8)).await?;
+        counts.sort_by(|a, b| a.route.cmp(&b.route));
+        let counts: Vec<(String, u64)> = counts
+            .into_iter()
+            .map(|count| (count.route, count.visitors))
+            .collect();
+        assert_eq!(
+            vec![
+                (DailyVisitors::ALL_ROUTES.to_string(), 3),
+                ("GET /products".to_string(), 2),
+                ("POST /transactions".to_string(), 1),
+            ],
+            counts
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,38 @@
+use validator::Validate;
+
+/*
+CATALOG
+- Validator untuk data katalog toko yang diimport dari file, yaitu kategori dan produk
+- Batas panjang field mengikuti ukuran kolom di tabel database, misal `varchar(100)` untuk id kategori
+dan `varchar(255)` untuk nama produk
+- Struct ini terpisah dari struct Product dan ProductVariant di catatan belajar `main.rs`,
+sehingga aturan import bisa berubah tanpa mengubah contoh di catatan belajar
+*/
+
+#[derive(Debug, Validate)]
+pub struct Category {
+    #[validate(length(
+        min = 1,
+        max = 100,
+        message = "ID must be between 1 and 100 characters"
+    ))]
+    pub id: String,
+    #[validate(length(
+        min = 1,
+        max = 100,
+        message = "Name must be between 1 and 100 characters"
+    ))]
+    pub name: String,
+}
+
+#[derive(Debug, Validate)]
+pub struct Product {
+    #[validate(length(
+        min = 1,
+        max = 255,
+        message = "Name must be between 1 and 255 characters"
+    ))]
+    pub name: String,
+    #[validate(range(min = 1, message = "Price must be at least 1"))]
+    pub price: u64,
+}
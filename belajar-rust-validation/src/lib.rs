@@ -0,0 +1,7 @@
+/*
+LIBRARY
+- Selain berisi catatan belajar di `main.rs`, validator yang dipakai ulang oleh project lain disimpan di library ini
+- Misal validator katalog di module `catalog` digunakan oleh importer CSV di project belajar-rust-database
+*/
+
+pub mod catalog;